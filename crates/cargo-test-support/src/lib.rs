@@ -143,6 +143,7 @@ pub mod git;
 pub mod paths;
 pub mod publish;
 pub mod registry;
+pub mod signed_registry;
 
 /*
  *
@@ -1646,7 +1647,10 @@ fn substitute_macros(input: &str) -> String {
         ("[NOTE]", "note:"),
         ("[DOCUMENTING]", " Documenting"),
         ("[FRESH]", "       Fresh"),
+        ("[DIRTY]", "       Dirty"),
         ("[UPDATING]", "    Updating"),
+        ("[UPGRADING]", "   Upgrading"),
+        ("[DOWNGRADING]", " Downgrading"),
         ("[ADDING]", "      Adding"),
         ("[REMOVING]", "    Removing"),
         ("[DOCTEST]", "   Doc-tests"),
@@ -1665,6 +1669,7 @@ fn substitute_macros(input: &str) -> String {
         ("[IGNORED]", "     Ignored"),
         ("[INSTALLED]", "   Installed"),
         ("[REPLACED]", "    Replaced"),
+        ("[RESTORED]", "    Restored"),
     ];
     let mut result = input.to_owned();
     for &(pat, subst) in &macros {
@@ -140,6 +140,7 @@ pub struct Package {
     files: Vec<(String, String)>,
     extra_files: Vec<(String, String)>,
     yanked: bool,
+    deprecated: Option<(String, Option<String>)>,
     features: HashMap<String, Vec<String>>,
     local: bool,
     alternative: bool,
@@ -239,6 +240,7 @@ impl Package {
             files: Vec::new(),
             extra_files: Vec::new(),
             yanked: false,
+            deprecated: None,
             features: HashMap::new(),
             local: false,
             alternative: false,
@@ -347,6 +349,13 @@ impl Package {
         self
     }
 
+    /// Marks the package as deprecated in the index, with an optional
+    /// replacement.
+    pub fn deprecated(&mut self, message: &str, replacement: Option<&str>) -> &mut Package {
+        self.deprecated = Some((message.to_string(), replacement.map(|s| s.to_string())));
+        self
+    }
+
     /// Specifies whether or not this is a proc macro.
     pub fn proc_macro(&mut self, proc_macro: bool) -> &mut Package {
         self.proc_macro = proc_macro;
@@ -413,7 +422,7 @@ impl Package {
         } else {
             serde_json::json!(self.name)
         };
-        let line = serde_json::json!({
+        let mut line = serde_json::json!({
             "name": name,
             "vers": self.vers,
             "deps": deps,
@@ -421,8 +430,14 @@ impl Package {
             "features": self.features,
             "yanked": self.yanked,
             "pm": self.proc_macro,
-        })
-        .to_string();
+        });
+        if let Some((message, replacement)) = &self.deprecated {
+            line["deprecated"] = serde_json::json!({
+                "message": message,
+                "replacement": replacement,
+            });
+        }
+        let line = line.to_string();
 
         let file = match self.name.len() {
             1 => format!("1/{}", self.name),
@@ -0,0 +1,342 @@
+//! An HTTP registry web API which requires signed tokens.
+//!
+//! The regular test registries use `file://` URLs for their API and download
+//! endpoints, so Cargo's `Authorization` headers are never seen. This module
+//! runs a small HTTP server in front of the "alternative" registry instead,
+//! which verifies the `v4.public` token sent with every request (see
+//! `cargo::util::auth`) before handling it.
+//!
+//! # Example
+//! ```
+//! let server = SignedRegistry::start();
+//! Package::new("bar", "1.0.0").alternative(true).publish();
+//! p.cargo("build -Zsigned-tokens").masquerade_as_nightly_cargo().run();
+//! assert_eq!(server.requests(), ["download bar 1.0.0"]);
+//! ```
+
+use crate::git;
+use crate::paths;
+use crate::registry::{self, alt_api_path, alt_dl_path, alt_registry_path, alt_registry_url};
+use ed25519_compact::{KeyPair, PublicKey, Seed, Signature};
+use std::collections::HashSet;
+use std::fs;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+const HEADER: &str = "v4.public.";
+
+/// The secret key written to the credentials file by `SignedRegistry::start`.
+pub fn secret_key() -> String {
+    key_pair_string(&KeyPair::from_seed(Seed::new([1; Seed::BYTES])))
+}
+
+/// A secret key the registry does not know about.
+pub fn unknown_secret_key() -> String {
+    key_pair_string(&KeyPair::from_seed(Seed::new([2; Seed::BYTES])))
+}
+
+fn key_pair_string(pair: &KeyPair) -> String {
+    format!(
+        "k4.secret.{}",
+        base64::encode_config(&pair.sk[..], base64::URL_SAFE_NO_PAD)
+    )
+}
+
+pub struct SignedRegistry {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+struct State {
+    public_key: PublicKey,
+    // Test paths are computed up front, as they cannot be looked up from the
+    // server's thread.
+    index_url: String,
+    api_path: PathBuf,
+    dl_path: PathBuf,
+    nonces: HashSet<String>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+impl SignedRegistry {
+    /// Starts the server and points the alternative registry at it.
+    ///
+    /// The registry's `config.json` is rewritten to advertise
+    /// `signed-tokens`, and the credentials file gains a `secret-key` for
+    /// the alternative registry matching the key the server trusts.
+    pub fn start() -> SignedRegistry {
+        registry::init();
+        let listener = t!(TcpListener::bind("127.0.0.1:0"));
+        let addr = t!(listener.local_addr());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let mut state = State {
+            public_key: KeyPair::from_seed(Seed::new([1; Seed::BYTES])).pk,
+            index_url: alt_registry_url().to_string(),
+            api_path: alt_api_path(),
+            dl_path: alt_dl_path(),
+            nonces: HashSet::new(),
+            requests: requests.clone(),
+        };
+        thread::spawn(move || {
+            for conn in listener.incoming() {
+                let conn = t!(conn);
+                state.handle(conn);
+            }
+        });
+
+        let repo = t!(git2::Repository::open(alt_registry_path()));
+        t!(fs::write(
+            alt_registry_path().join("config.json"),
+            format!(
+                r#"{{"dl":"http://{0}/dl","api":"http://{0}","signed-tokens":true}}"#,
+                addr
+            ),
+        ));
+        git::add(&repo);
+        git::commit(&repo);
+
+        t!(fs::write(
+            paths::home().join(".cargo/credentials"),
+            format!(
+                r#"
+                [registry]
+                token = "api-token"
+
+                [registries.alternative]
+                token = "api-token"
+                secret-key = "{}"
+                "#,
+                secret_key()
+            ),
+        ));
+
+        SignedRegistry { addr, requests }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns a log of the requests handled so far, such as
+    /// `"publish foo 0.1.0"`, or `"rejected: <reason>"`.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl State {
+    fn handle(&mut self, mut conn: TcpStream) {
+        let req = match read_request(&mut conn) {
+            Some(req) => req,
+            None => return,
+        };
+        let (status, body) = match self.respond(&req) {
+            Ok((op, name, vers, body)) => {
+                let mut entry = format!("{} {}", op, name);
+                if let Some(vers) = vers {
+                    entry.push_str(&format!(" {}", vers));
+                }
+                self.requests.lock().unwrap().push(entry);
+                ("200 OK", body)
+            }
+            Err(reason) => {
+                let body = serde_json::json!({ "errors": [{ "detail": reason }] });
+                self.requests
+                    .lock()
+                    .unwrap()
+                    .push(format!("rejected: {}", reason));
+                ("403 Forbidden", body.to_string().into_bytes())
+            }
+        };
+        let _ = write!(
+            conn,
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            body.len()
+        );
+        let _ = conn.write_all(&body);
+    }
+
+    /// Routes `req`, returning the operation it performed along with the
+    /// response body, or the reason the request was rejected.
+    fn respond(
+        &mut self,
+        req: &Request,
+    ) -> Result<(&'static str, String, Option<String>, Vec<u8>), String> {
+        let parts = req
+            .path
+            .trim_start_matches('/')
+            .split('/')
+            .collect::<Vec<_>>();
+        let (op, name, vers) = match (req.method.as_str(), &parts[..]) {
+            ("PUT", ["api", "v1", "crates", "new"]) => {
+                let (name, vers) = upload_name_and_version(&req.body);
+                ("publish", name, Some(vers))
+            }
+            ("DELETE", ["api", "v1", "crates", name, vers, "yank"]) => {
+                ("yank", name.to_string(), Some(vers.to_string()))
+            }
+            ("PUT", ["api", "v1", "crates", name, vers, "unyank"]) => {
+                ("unyank", name.to_string(), Some(vers.to_string()))
+            }
+            (_, ["api", "v1", "crates", name, "owners"]) => ("owners", name.to_string(), None),
+            ("GET", ["dl", name, vers, "download"]) => {
+                ("download", name.to_string(), Some(vers.to_string()))
+            }
+            _ => return Err(format!("unexpected request {} {}", req.method, req.path)),
+        };
+        self.verify(req, op, &name, vers.as_deref())?;
+
+        let body = match op {
+            "publish" => {
+                let dst = self.api_path.join("api/v1/crates/new");
+                t!(fs::create_dir_all(dst.parent().unwrap()));
+                t!(fs::write(&dst, &req.body));
+                b"{}".to_vec()
+            }
+            "yank" | "unyank" => br#"{"ok":true}"#.to_vec(),
+            "owners" if req.method == "GET" => br#"{"users":[]}"#.to_vec(),
+            "owners" => br#"{"ok":true,"msg":"owners updated"}"#.to_vec(),
+            "download" => {
+                let vers = vers.as_ref().unwrap();
+                let path = self
+                    .dl_path
+                    .join(&name)
+                    .join(vers)
+                    .join(format!("{}-{}.crate", name, vers));
+                t!(fs::read(path))
+            }
+            _ => unreachable!(),
+        };
+        Ok((op, name, vers, body))
+    }
+
+    /// Checks the token sent with `req` is signed by the trusted key and
+    /// authorizes exactly this operation.
+    fn verify(
+        &mut self,
+        req: &Request,
+        op: &str,
+        name: &str,
+        vers: Option<&str>,
+    ) -> Result<(), String> {
+        let token = req
+            .authorization
+            .as_ref()
+            .ok_or_else(|| "missing authorization".to_string())?;
+        if !token.starts_with(HEADER) {
+            return Err("expected a signed token".to_string());
+        }
+        let mut parts = token[HEADER.len()..].split('.');
+        let decode = |s: Option<&str>| {
+            base64::decode_config(s.unwrap_or(""), base64::URL_SAFE_NO_PAD)
+                .map_err(|_| "malformed token".to_string())
+        };
+        let payload = decode(parts.next())?;
+        let footer = decode(parts.next())?;
+        if payload.len() < Signature::BYTES {
+            return Err("malformed token".to_string());
+        }
+        let (message, signature) = payload.split_at(payload.len() - Signature::BYTES);
+        let signature = Signature::from_slice(signature).unwrap();
+        self.public_key
+            .verify(
+                pre_auth_encode(&[HEADER.as_bytes(), message, &footer, b""]),
+                &signature,
+            )
+            .map_err(|_| "invalid signature".to_string())?;
+
+        let footer: serde_json::Value = serde_json::from_slice(&footer).unwrap();
+        if footer["url"] != self.index_url.as_str() {
+            return Err(format!("token is for another registry: {}", footer["url"]));
+        }
+        let claims: serde_json::Value = serde_json::from_slice(message).unwrap();
+        if claims["op"] != op || claims["name"] != name || claims["vers"].as_str() != vers {
+            return Err(format!("token does not authorize {} {}", op, name));
+        }
+        let now = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        if claims["exp"].as_str().unwrap() < now.as_str() {
+            return Err("token expired".to_string());
+        }
+        let nonce = claims["nonce"].as_str().unwrap().to_string();
+        if !self.nonces.insert(nonce) {
+            return Err("token was already used".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn read_request(conn: &mut TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(t!(conn.try_clone()));
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut request_line = line.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+
+    let mut authorization = None;
+    let mut content_length = 0;
+    let mut expect_continue = false;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        let mut header = line.splitn(2, ':');
+        let key = header.next()?.trim().to_lowercase();
+        let value = header.next().unwrap_or("").trim();
+        match key.as_str() {
+            "authorization" => authorization = Some(value.to_string()),
+            "content-length" => content_length = value.parse().ok()?,
+            "expect" => expect_continue = value.eq_ignore_ascii_case("100-continue"),
+            _ => {}
+        }
+    }
+    if expect_continue {
+        t!(conn.write_all(b"HTTP/1.1 100 Continue\r\n\r\n"));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+    Some(Request {
+        method,
+        path,
+        authorization,
+        body,
+    })
+}
+
+/// Extracts the name and version from the JSON metadata at the start of a
+/// publish request.
+fn upload_name_and_version(body: &[u8]) -> (String, String) {
+    let mut len = [0; 4];
+    len.copy_from_slice(&body[..4]);
+    let len = u32::from_le_bytes(len) as usize;
+    let json: serde_json::Value = serde_json::from_slice(&body[4..4 + len]).unwrap();
+    (
+        json["name"].as_str().unwrap().to_string(),
+        json["vers"].as_str().unwrap().to_string(),
+    )
+}
+
+fn pre_auth_encode(pieces: &[&[u8]]) -> Vec<u8> {
+    let mut out = (pieces.len() as u64).to_le_bytes().to_vec();
+    for piece in pieces {
+        out.extend_from_slice(&(piece.len() as u64).to_le_bytes());
+        out.extend_from_slice(piece);
+    }
+    out
+}
@@ -19,10 +19,69 @@ pub struct Registry {
     /// Optional authorization token.
     /// If None, commands requiring authorization will fail.
     token: Option<String>,
+    /// Optional signer which mints a fresh token for every authorized
+    /// request. Takes priority over `token` when set.
+    signer: Option<Box<dyn FnMut(Operation<'_>) -> Result<String>>>,
     /// Curl handle for issuing requests.
     handle: Easy,
 }
 
+/// The operation an authorized request performs.
+///
+/// Registries which support signed tokens expect the operation to be part of
+/// the signed claims so that a token cannot be replayed for another purpose.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation<'a> {
+    Publish { name: &'a str, vers: &'a str },
+    Yank { name: &'a str, vers: &'a str },
+    Unyank { name: &'a str, vers: &'a str },
+    Deprecate { name: &'a str, vers: &'a str },
+    Undeprecate { name: &'a str, vers: &'a str },
+    Owners { name: &'a str },
+    Download { name: &'a str, vers: &'a str },
+}
+
+impl<'a> Operation<'a> {
+    /// The name of the operation as it appears in a signed token.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Publish { .. } => "publish",
+            Operation::Yank { .. } => "yank",
+            Operation::Unyank { .. } => "unyank",
+            Operation::Deprecate { .. } => "deprecate",
+            Operation::Undeprecate { .. } => "undeprecate",
+            Operation::Owners { .. } => "owners",
+            Operation::Download { .. } => "download",
+        }
+    }
+
+    /// The name of the crate this operation applies to.
+    pub fn name(&self) -> &'a str {
+        match *self {
+            Operation::Publish { name, .. }
+            | Operation::Yank { name, .. }
+            | Operation::Unyank { name, .. }
+            | Operation::Deprecate { name, .. }
+            | Operation::Undeprecate { name, .. }
+            | Operation::Owners { name }
+            | Operation::Download { name, .. } => name,
+        }
+    }
+
+    /// The version this operation applies to, if it is version specific.
+    pub fn vers(&self) -> Option<&'a str> {
+        match *self {
+            Operation::Publish { vers, .. }
+            | Operation::Yank { vers, .. }
+            | Operation::Unyank { vers, .. }
+            | Operation::Deprecate { vers, .. }
+            | Operation::Undeprecate { vers, .. }
+            | Operation::Download { vers, .. } => Some(vers),
+            Operation::Owners { .. } => None,
+        }
+    }
+}
+
 #[derive(PartialEq, Clone, Copy)]
 pub enum Auth {
     Authorized,
@@ -82,6 +141,14 @@ pub struct User {
     pub name: Option<String>,
 }
 
+/// An advisory attached to a published version, telling users of the version
+/// that it should no longer be used.
+#[derive(Serialize)]
+pub struct Deprecation {
+    pub message: String,
+    pub replacement: Option<String>,
+}
+
 pub struct Warnings {
     pub invalid_categories: Vec<String>,
     pub invalid_badges: Vec<String>,
@@ -131,10 +198,20 @@ impl Registry {
         Registry {
             host,
             token,
+            signer: None,
             handle,
         }
     }
 
+    /// Sets a callback used to mint a token for each authorized request,
+    /// instead of sending the same bearer token every time.
+    pub fn set_signer<F>(&mut self, signer: F)
+    where
+        F: FnMut(Operation<'_>) -> Result<String> + 'static,
+    {
+        self.signer = Some(Box::new(signer));
+    }
+
     pub fn host(&self) -> &str {
         &self.host
     }
@@ -147,20 +224,27 @@ impl Registry {
 
     pub fn add_owners(&mut self, krate: &str, owners: &[&str]) -> Result<String> {
         let body = serde_json::to_string(&OwnersReq { users: owners })?;
-        let body = self.put(&format!("/crates/{}/owners", krate), body.as_bytes())?;
+        let op = Operation::Owners { name: krate };
+        let body = self.put(&format!("/crates/{}/owners", krate), body.as_bytes(), op)?;
         assert!(serde_json::from_str::<OwnerResponse>(&body)?.ok);
         Ok(serde_json::from_str::<OwnerResponse>(&body)?.msg)
     }
 
     pub fn remove_owners(&mut self, krate: &str, owners: &[&str]) -> Result<()> {
         let body = serde_json::to_string(&OwnersReq { users: owners })?;
-        let body = self.delete(&format!("/crates/{}/owners", krate), Some(body.as_bytes()))?;
+        let op = Operation::Owners { name: krate };
+        let body = self.delete(
+            &format!("/crates/{}/owners", krate),
+            Some(body.as_bytes()),
+            op,
+        )?;
         assert!(serde_json::from_str::<OwnerResponse>(&body)?.ok);
         Ok(())
     }
 
     pub fn list_owners(&mut self, krate: &str) -> Result<Vec<User>> {
-        let body = self.get(&format!("/crates/{}/owners", krate))?;
+        let op = Operation::Owners { name: krate };
+        let body = self.get(&format!("/crates/{}/owners", krate), op)?;
         Ok(serde_json::from_str::<Users>(&body)?.users)
     }
 
@@ -203,10 +287,10 @@ impl Registry {
 
         let url = format!("{}/api/v1/crates/new", self.host);
 
-        let token = match self.token.as_ref() {
-            Some(s) => s,
-            None => bail!("no upload token found, please run `cargo login`"),
-        };
+        let token = self.authorization(Operation::Publish {
+            name: &krate.name,
+            vers: &krate.vers,
+        })?;
         self.handle.put(true)?;
         self.handle.url(&url)?;
         self.handle.in_filesize(size as u64)?;
@@ -257,6 +341,7 @@ impl Registry {
             &format!("/crates?q={}&per_page={}", formatted_query, limit),
             None,
             Auth::Unauthorized,
+            None,
         )?;
 
         let crates = serde_json::from_str::<Crates>(&body)?;
@@ -264,43 +349,102 @@ impl Registry {
     }
 
     pub fn yank(&mut self, krate: &str, version: &str) -> Result<()> {
-        let body = self.delete(&format!("/crates/{}/{}/yank", krate, version), None)?;
+        let op = Operation::Yank {
+            name: krate,
+            vers: version,
+        };
+        let body = self.delete(&format!("/crates/{}/{}/yank", krate, version), None, op)?;
         assert!(serde_json::from_str::<R>(&body)?.ok);
         Ok(())
     }
 
     pub fn unyank(&mut self, krate: &str, version: &str) -> Result<()> {
-        let body = self.put(&format!("/crates/{}/{}/unyank", krate, version), &[])?;
+        let op = Operation::Unyank {
+            name: krate,
+            vers: version,
+        };
+        let body = self.put(&format!("/crates/{}/{}/unyank", krate, version), &[], op)?;
+        assert!(serde_json::from_str::<R>(&body)?.ok);
+        Ok(())
+    }
+
+    /// Marks a version as deprecated. Unlike a yank, this does not stop the
+    /// version from being selected, but Cargo will warn whenever it is.
+    pub fn deprecate(
+        &mut self,
+        krate: &str,
+        version: &str,
+        deprecation: &Deprecation,
+    ) -> Result<()> {
+        let body = serde_json::to_string(deprecation)?;
+        let op = Operation::Deprecate {
+            name: krate,
+            vers: version,
+        };
+        let body = self.put(
+            &format!("/crates/{}/{}/deprecate", krate, version),
+            body.as_bytes(),
+            op,
+        )?;
+        assert!(serde_json::from_str::<R>(&body)?.ok);
+        Ok(())
+    }
+
+    pub fn undeprecate(&mut self, krate: &str, version: &str) -> Result<()> {
+        let op = Operation::Undeprecate {
+            name: krate,
+            vers: version,
+        };
+        let body = self.delete(
+            &format!("/crates/{}/{}/deprecate", krate, version),
+            None,
+            op,
+        )?;
         assert!(serde_json::from_str::<R>(&body)?.ok);
         Ok(())
     }
 
-    fn put(&mut self, path: &str, b: &[u8]) -> Result<String> {
+    fn put(&mut self, path: &str, b: &[u8], op: Operation<'_>) -> Result<String> {
         self.handle.put(true)?;
-        self.req(path, Some(b), Auth::Authorized)
+        self.req(path, Some(b), Auth::Authorized, Some(op))
     }
 
-    fn get(&mut self, path: &str) -> Result<String> {
+    fn get(&mut self, path: &str, op: Operation<'_>) -> Result<String> {
         self.handle.get(true)?;
-        self.req(path, None, Auth::Authorized)
+        self.req(path, None, Auth::Authorized, Some(op))
     }
 
-    fn delete(&mut self, path: &str, b: Option<&[u8]>) -> Result<String> {
+    fn delete(&mut self, path: &str, b: Option<&[u8]>, op: Operation<'_>) -> Result<String> {
         self.handle.custom_request("DELETE")?;
-        self.req(path, b, Auth::Authorized)
+        self.req(path, b, Auth::Authorized, Some(op))
+    }
+
+    /// Returns the value of the `Authorization` header for `op`.
+    fn authorization(&mut self, op: Operation<'_>) -> Result<String> {
+        if let Some(signer) = self.signer.as_mut() {
+            return signer(op);
+        }
+        match self.token.as_ref() {
+            Some(s) => Ok(s.clone()),
+            None => bail!("no upload token found, please run `cargo login`"),
+        }
     }
 
-    fn req(&mut self, path: &str, body: Option<&[u8]>, authorized: Auth) -> Result<String> {
+    fn req(
+        &mut self,
+        path: &str,
+        body: Option<&[u8]>,
+        authorized: Auth,
+        op: Option<Operation<'_>>,
+    ) -> Result<String> {
         self.handle.url(&format!("{}/api/v1{}", self.host, path))?;
         let mut headers = List::new();
         headers.append("Accept: application/json")?;
         headers.append("Content-Type: application/json")?;
 
         if authorized == Auth::Authorized {
-            let token = match self.token.as_ref() {
-                Some(s) => s,
-                None => bail!("no upload token found, please run `cargo login`"),
-            };
+            let op = op.expect("authorized requests must name an operation");
+            let token = self.authorization(op)?;
             headers.append(&format!("Authorization: {}", token))?;
         }
         self.handle.http_headers(headers)?;
@@ -188,6 +188,7 @@ pub fn resolve_with_config_raw(
         &HashSet::new(),
         config,
         true,
+        &HashSet::new(),
     );
 
     // The largest test in our suite takes less then 30 sec.
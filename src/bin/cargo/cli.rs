@@ -34,8 +34,23 @@ Available unstable (nightly-only) flags:
     -Z no-index-update  -- Do not update the registry, avoids a network request for benchmarking
     -Z unstable-options -- Allow the usage of unstable options
     -Z timings          -- Display concurrency information
-    -Z doctest-xcompile -- Compile and run doctests for non-host target using runner config
+    -Z doctest-xcompile -- Enable per-target ignores in doctests
     -Z crate-versions   -- Add crate versions to generated docs
+    -Z signed-tokens    -- Sign registry requests with a `secret-key` instead of sending a `token`
+    -Z future-incompat-report -- Report future-incompatible warnings in dependencies
+    -Z dirty-reasons    -- Explain why units are rebuilt with `-v` or `--message-format=json`
+    -Z checksum-freshness -- Don't rebuild for source files whose mtime changed but contents didn't
+    -Z patch-in-config  -- Allow `[patch]` and `[replace]` sections in .cargo/config files
+    -Z config-cfg       -- Allow config tables keyed on `cfg(...)` expressions matching the host
+    -Z direct-minimal-versions -- Resolve direct dependencies to minimal versions, others to maximal
+    -Z lockfile-diff    -- Display the changes to Cargo.lock after any command which updates it
+    -Z parallel-tests   -- Run test executables in parallel as soon as they are built
+    -Z doctest-bins     -- Run the doc tests of binaries with `doctest = true`
+    -Z merged-doctests  -- Compile the doc tests of each crate into one binary, as jobs of the build
+    -Z build-script-directives -- Accept validated `cargo::` directives from build scripts
+    -Z sandbox-build-scripts -- Run build scripts without network access and with a restricted filesystem
+    -Z build-script-cache -- Restore the output of build scripts whose declared inputs are unchanged
+    -Z run-multiple     -- Allow `cargo run` to run several binaries or examples concurrently
 
 Run with 'cargo -Z [FLAG] [SUBCOMMAND]'"
         );
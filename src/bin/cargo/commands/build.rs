@@ -42,6 +42,7 @@ pub fn cli() -> App {
         .arg_message_format()
         .arg_build_plan()
         .arg_unit_graph()
+        .arg_watch("Build again whenever the files read by the build change (unstable)")
         .after_help(
             "\
 All packages in the workspace are built if the `--workspace` flag is supplied. The
@@ -75,6 +76,19 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             .cli_unstable()
             .fail_if_stable_opt("--out-dir", 6790)?;
     }
-    ops::compile(&ws, &compile_opts)?;
+    if compile_opts.watch.is_some() {
+        let config = &*config;
+        ops::watch(
+            ws,
+            &compile_opts,
+            || args.workspace(config),
+            |ws| {
+                ops::compile(ws, &compile_opts)?;
+                Ok(None)
+            },
+        )?;
+    } else {
+        ops::compile(&ws, &compile_opts)?;
+    }
     Ok(())
 }
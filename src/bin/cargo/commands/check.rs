@@ -34,6 +34,7 @@ pub fn cli() -> App {
         .arg_manifest_path()
         .arg_message_format()
         .arg_unit_graph()
+        .arg_watch("Check again whenever the files read by the check change (unstable)")
         .after_help(
             "\
 If the `--package` argument is given, then SPEC is a package ID specification
@@ -72,6 +73,19 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let mode = CompileMode::Check { test };
     let compile_opts = args.compile_options(config, mode, Some(&ws), ProfileChecking::Unchecked)?;
 
-    ops::compile(&ws, &compile_opts)?;
+    if compile_opts.watch.is_some() {
+        let config = &*config;
+        ops::watch(
+            ws,
+            &compile_opts,
+            || args.workspace(config),
+            |ws| {
+                ops::compile(ws, &compile_opts)?;
+                Ok(None)
+            },
+        )?;
+    } else {
+        ops::compile(&ws, &compile_opts)?;
+    }
     Ok(())
 }
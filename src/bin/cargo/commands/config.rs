@@ -0,0 +1,44 @@
+use crate::command_prelude::*;
+
+use cargo::ops::cargo_config;
+
+pub fn cli() -> App {
+    subcommand("config")
+        .about("Inspect configuration values (unstable)")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("get")
+                .about("Display the merged configuration, or the value of a key")
+                .arg(Arg::with_name("key").help("The config key to display"))
+                .arg(
+                    opt("format", "Display format")
+                        .possible_values(cargo_config::ConfigFormat::POSSIBLE_VALUES)
+                        .default_value("toml"),
+                )
+                .arg(opt(
+                    "show-origin",
+                    "Display where the config value is defined",
+                )),
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().unstable_options {
+        return Err(anyhow::format_err!(
+            "`cargo config` is unstable, pass `-Z unstable-options` to enable it"
+        )
+        .into());
+    }
+    match args.subcommand() {
+        ("get", Some(args)) => {
+            let opts = cargo_config::GetOptions {
+                key: args.value_of("key"),
+                format: args.value_of("format").unwrap().parse()?,
+                show_origin: args.is_present("show-origin"),
+            };
+            cargo_config::get(config, &opts)?;
+        }
+        (cmd, _) => panic!("unexpected command `{}`", cmd),
+    }
+    Ok(())
+}
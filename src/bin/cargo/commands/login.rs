@@ -16,9 +16,20 @@ pub fn cli() -> App {
                 .hidden(true),
         )
         .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
+        .arg(
+            opt(
+                "generate-key",
+                "Generate a key pair for signed tokens instead of saving a token (unstable)",
+            )
+            .conflicts_with("token"),
+        )
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if args.is_present("generate-key") {
+        ops::registry_generate_key(config, args.value_of("registry").map(String::from))?;
+        return Ok(());
+    }
     ops::registry_login(
         config,
         args.value_of("token").map(String::from),
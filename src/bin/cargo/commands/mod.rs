@@ -6,6 +6,7 @@ pub fn builtin() -> Vec<App> {
         build::cli(),
         check::cli(),
         clean::cli(),
+        config::cli(),
         doc::cli(),
         fetch::cli(),
         fix::cli(),
@@ -17,11 +18,13 @@ pub fn builtin() -> Vec<App> {
         login::cli(),
         metadata::cli(),
         new::cli(),
+        outdated::cli(),
         owner::cli(),
         package::cli(),
         pkgid::cli(),
         publish::cli(),
         read_manifest::cli(),
+        report::cli(),
         run::cli(),
         rustc::cli(),
         rustdoc::cli(),
@@ -42,6 +45,7 @@ pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> Cli
         "build" => build::exec,
         "check" => check::exec,
         "clean" => clean::exec,
+        "config" => config::exec,
         "doc" => doc::exec,
         "fetch" => fetch::exec,
         "fix" => fix::exec,
@@ -53,11 +57,13 @@ pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> Cli
         "login" => login::exec,
         "metadata" => metadata::exec,
         "new" => new::exec,
+        "outdated" => outdated::exec,
         "owner" => owner::exec,
         "package" => package::exec,
         "pkgid" => pkgid::exec,
         "publish" => publish::exec,
         "read-manifest" => read_manifest::exec,
+        "report" => report::exec,
         "run" => run::exec,
         "rustc" => rustc::exec,
         "rustdoc" => rustdoc::exec,
@@ -78,6 +84,7 @@ pub mod bench;
 pub mod build;
 pub mod check;
 pub mod clean;
+pub mod config;
 pub mod doc;
 pub mod fetch;
 pub mod fix;
@@ -89,11 +96,13 @@ pub mod locate_project;
 pub mod login;
 pub mod metadata;
 pub mod new;
+pub mod outdated;
 pub mod owner;
 pub mod package;
 pub mod pkgid;
 pub mod publish;
 pub mod read_manifest;
+pub mod report;
 pub mod run;
 pub mod rustc;
 pub mod rustdoc;
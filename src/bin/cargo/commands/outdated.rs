@@ -0,0 +1,51 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, OutdatedFormat, OutdatedOptions};
+
+pub fn cli() -> App {
+    subcommand("outdated")
+        .about("Display dependencies with newer versions available (unstable)")
+        .arg(
+            opt("format", "Output format")
+                .possible_values(OutdatedFormat::POSSIBLE_VALUES)
+                .default_value("human"),
+        )
+        .arg(opt(
+            "root-deps-only",
+            "Only display the direct dependencies of workspace members",
+        ))
+        .arg(opt(
+            "exit-code",
+            "Exit with status 1 if any dependency is outdated",
+        ))
+        .arg_manifest_path()
+        .after_help(
+            "\
+For every dependency in the lock file, this displays the locked version, the
+greatest version which is compatible with the version requirements, and the
+greatest version overall, if any of them is newer than the locked version.
+Dependencies from git repositories and paths are not displayed.
+
+With `--offline`, only the index data which is already cached is used.
+",
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().unstable_options {
+        return Err(anyhow::format_err!(
+            "`cargo outdated` is unstable, pass `-Z unstable-options` to enable it"
+        )
+        .into());
+    }
+    let ws = args.workspace(config)?;
+    let opts = OutdatedOptions {
+        format: args.value_of("format").unwrap().parse()?,
+        root_deps_only: args.is_present("root-deps-only"),
+    };
+    let any_outdated = ops::outdated(&ws, &opts)?;
+    if any_outdated && args.is_present("exit-code") {
+        return Err(CliError::code(1));
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use crate::command_prelude::*;
+
+use cargo::core::compiler::future_incompat::OnDiskReport;
+
+pub fn cli() -> App {
+    subcommand("report")
+        .about("Display a report saved by a previous build (unstable)")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("future-incompat")
+                .alias("future-incompatibilities")
+                .about("Display the future-incompat warnings reported during the last build")
+                .arg_manifest_path(),
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().future_incompat_report {
+        return Err(
+            anyhow::format_err!("`cargo report` requires `-Z future-incompat-report`").into(),
+        );
+    }
+    match args.subcommand() {
+        ("future-incompat", Some(args)) => report_future_incompat(config, args),
+        (cmd, _) => panic!("unexpected command `{}`", cmd),
+    }
+}
+
+fn report_future_incompat(config: &Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let report = OnDiskReport::load(&ws)?;
+    let color = config.shell().supports_color();
+    print!("{}", report.render(&ws, color));
+    Ok(())
+}
@@ -25,6 +25,7 @@ pub fn cli() -> App {
         .arg_manifest_path()
         .arg_message_format()
         .arg_unit_graph()
+        .arg_watch("Build and run again whenever the files read by the build change (unstable)")
         .after_help(
             "\
 If neither `--bin` nor `--example` are given, then if the package only has one
@@ -79,6 +80,18 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             };
         }
     };
+    if compile_opts.watch.is_some() {
+        let config = &*config;
+        let run_args = values_os(args, "args");
+        ops::watch(
+            ws,
+            &compile_opts,
+            || args.workspace(config),
+            |ws| ops::prepare_run(ws, &compile_opts, &run_args).map(Some),
+        )?;
+        return Ok(());
+    }
+
     match ops::run(&ws, &compile_opts, &values_os(args, "args"))? {
         None => Ok(()),
         Some(err) => {
@@ -1,7 +1,8 @@
 use crate::command_prelude::*;
 use anyhow::Error;
+use cargo::core::Workspace;
 use cargo::ops::{self, CompileFilter, FilterRule, LibRule};
-use cargo::util::errors;
+use cargo::util::errors::{self, CargoTestError};
 
 pub fn cli() -> App {
     subcommand("test")
@@ -41,6 +42,13 @@ pub fn cli() -> App {
         .arg(opt("doc", "Test only this library's documentation"))
         .arg(opt("no-run", "Compile, but don't run tests"))
         .arg(opt("no-fail-fast", "Run all tests regardless of failure"))
+        .arg(
+            opt(
+                "shard",
+                "Run only the tests of the N-th of M shards (unstable)",
+            )
+            .value_name("N/M"),
+        )
         .arg_package_spec(
             "Package to run tests for",
             "Test all packages in the workspace",
@@ -55,6 +63,7 @@ pub fn cli() -> App {
         .arg_manifest_path()
         .arg_message_format()
         .arg_unit_graph()
+        .arg_watch("Test again whenever the files read by the build change (unstable)")
         .after_help(
             "\
 The test filtering argument TESTNAME and all the arguments following the
@@ -154,24 +163,61 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         }
     }
 
+    let shard = match args.value_of("shard") {
+        Some(shard) => {
+            if !config.cli_unstable().unstable_options {
+                return Err(anyhow::format_err!(
+                    "the `--shard` flag is unstable, pass `-Z unstable-options` to enable it"
+                )
+                .into());
+            }
+            Some(shard.parse()?)
+        }
+        None => None,
+    };
+
     let ops = ops::TestOptions {
         no_run,
         no_fail_fast: args.is_present("no-fail-fast"),
         compile_opts,
+        shard,
     };
 
+    if ops.compile_opts.watch.is_some() {
+        let config = &*config;
+        ops::watch(
+            ws,
+            &ops.compile_opts,
+            || args.workspace(config),
+            |ws| match ops::run_tests(ws, &ops, &test_args)? {
+                None => Ok(None),
+                Some(err) => Err(test_failure(ws, &ops, err).0),
+            },
+        )?;
+        return Ok(());
+    }
+
     let err = ops::run_tests(&ws, &ops, &test_args)?;
     match err {
         None => Ok(()),
         Some(err) => {
-            let context = anyhow::format_err!("{}", err.hint(&ws, &ops.compile_opts));
-            let e = match err.exit.as_ref().and_then(|e| e.code()) {
-                // Don't show "process didn't exit successfully" for simple errors.
-                Some(i) if errors::is_simple_exit_code(i) => CliError::new(context, i),
-                Some(i) => CliError::new(Error::from(err).context(context), i),
-                None => CliError::new(Error::from(err).context(context), 101),
-            };
-            Err(e)
+            let (e, code) = test_failure(&ws, &ops, err);
+            Err(CliError::new(e, code))
         }
     }
 }
+
+/// The error to report for failed tests, along with the exit code for it.
+fn test_failure(
+    ws: &Workspace<'_>,
+    ops: &ops::TestOptions<'_>,
+    err: CargoTestError,
+) -> (Error, i32) {
+    let context = anyhow::format_err!("{}", err.hint(ws, &ops.compile_opts));
+    match err.exit.as_ref().and_then(|e| e.code()) {
+        // Don't show "process didn't exit successfully" for simple errors.
+        Some(i) if errors::is_simple_exit_code(i) => (context, i),
+        Some(i) => (Error::from(err).context(context), i),
+        None => (Error::from(err).context(context), 101),
+    }
+}
@@ -13,6 +13,14 @@ pub fn cli() -> App {
         ))
         .arg_dry_run("Don't actually write the lockfile")
         .arg(opt("precise", "Update a single dependency to exactly PRECISE").value_name("PRECISE"))
+        .arg(opt(
+            "direct-minimal-versions",
+            "Update the direct dependencies of workspace members to their minimal versions (unstable)",
+        ))
+        .arg(opt(
+            "breaking",
+            "Upgrade the version requirements of dependencies to incompatible versions (unstable)",
+        ))
         .arg_manifest_path()
         .after_help(
             "\
@@ -34,6 +42,17 @@ be updated to.
 If SPEC is not given, then all dependencies will be re-resolved and
 updated.
 
+If `--direct-minimal-versions` is given, the direct dependencies of the
+workspace members are updated to the lowest versions allowed by their version
+requirements, while other dependencies are updated to the highest. This checks
+that the lower bounds of the requirements actually work.
+
+If `--breaking` is given, the caret version requirements of the registry
+dependencies of the workspace members are upgraded in their `Cargo.toml` to
+match the latest versions, even if they are semver incompatible, and then
+these dependencies are updated in the lockfile. If SPEC is given, only the
+dependencies with these names are upgraded.
+
 For more information about package ID specifications, see `cargo help pkgid`.
 ",
         )
@@ -42,11 +61,30 @@ For more information about package ID specifications, see `cargo help pkgid`.
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let ws = args.workspace(config)?;
 
+    let direct_minimal_versions = args.is_present("direct-minimal-versions");
+    if direct_minimal_versions && !config.cli_unstable().unstable_options {
+        return Err(anyhow::format_err!(
+            "the `--direct-minimal-versions` flag is unstable, \
+             pass `-Z unstable-options` to enable it"
+        )
+        .into());
+    }
+
+    let breaking = args.is_present("breaking");
+    if breaking && !config.cli_unstable().unstable_options {
+        return Err(anyhow::format_err!(
+            "the `--breaking` flag is unstable, pass `-Z unstable-options` to enable it"
+        )
+        .into());
+    }
+
     let update_opts = UpdateOptions {
         aggressive: args.is_present("aggressive"),
         precise: args.value_of("precise"),
         to_update: values(args, "package"),
         dry_run: args.is_present("dry-run"),
+        direct_minimal_versions,
+        breaking,
         config,
     };
     ops::update_lockfile(&ws, &update_opts)?;
@@ -1,9 +1,12 @@
-use crate::core::compiler::{CompileKind, CompileTarget};
+use crate::core::compiler::{CompileKind, CompileTarget, TestRunner};
 use crate::core::interning::InternedString;
 use crate::util::ProcessBuilder;
 use crate::util::{CargoResult, Config, RustfixDiagnosticServer};
 use serde::ser;
 use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 /// Configuration information for a rustc build.
 #[derive(Debug)]
@@ -27,6 +30,12 @@ pub struct BuildConfig {
     /// An optional override of the rustc process for primary units
     pub primary_unit_rustc: Option<ProcessBuilder>,
     pub rustfix_diagnostic_server: RefCell<Option<RustfixDiagnosticServer>>,
+    /// Runs the test executables as soon as they are built, as part of the
+    /// build.
+    pub test_runner: RefCell<Option<Arc<TestRunner>>>,
+    /// With `--watch`, collects the files which the build read, so that the
+    /// build can be run again when they change.
+    pub watched_paths: RefCell<Option<BTreeSet<PathBuf>>>,
 }
 
 impl BuildConfig {
@@ -38,16 +47,19 @@ impl BuildConfig {
     /// * `target.$target.ar`
     /// * `target.$target.linker`
     /// * `target.$target.libfoo.metadata`
+    ///
+    /// With `-Z config-cfg`, this also applies the `cfg(…)` config tables
+    /// which match the requested target and profile.
     pub fn new(
         config: &Config,
         jobs: Option<u32>,
         requested_target: &Option<String>,
+        requested_profile: InternedString,
         mode: CompileMode,
     ) -> CargoResult<BuildConfig> {
-        let cfg = config.build_config()?;
         let requested_kind = match requested_target {
             Some(s) => CompileKind::Target(CompileTarget::new(s)?),
-            None => match &cfg.target {
+            None => match &config.build_config()?.target {
                 Some(val) => {
                     let value = if val.raw_value().ends_with(".json") {
                         let path = val.clone().resolve_path(config);
@@ -60,6 +72,7 @@ impl BuildConfig {
                 None => CompileKind::Host,
             },
         };
+        config.apply_conditional_tables(requested_kind, requested_profile)?;
 
         if jobs == Some(0) {
             anyhow::bail!("jobs must be at least 1")
@@ -71,12 +84,14 @@ impl BuildConfig {
                  its environment, ignoring the `-j` parameter",
             )?;
         }
-        let jobs = jobs.or(cfg.jobs).unwrap_or(::num_cpus::get() as u32);
+        let jobs = jobs
+            .or(config.build_config()?.jobs)
+            .unwrap_or(::num_cpus::get() as u32);
 
         Ok(BuildConfig {
             requested_kind,
             jobs,
-            requested_profile: InternedString::new("dev"),
+            requested_profile,
             mode,
             message_format: MessageFormat::Human,
             force_rebuild: false,
@@ -84,6 +99,8 @@ impl BuildConfig {
             unit_graph: false,
             primary_unit_rustc: None,
             rustfix_diagnostic_server: RefCell::new(None),
+            test_runner: RefCell::new(None),
+            watched_paths: RefCell::new(None),
         })
     }
 
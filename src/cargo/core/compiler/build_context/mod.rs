@@ -35,7 +35,7 @@ pub struct BuildContext<'a, 'cfg> {
     pub units: &'a UnitInterner<'a>,
 
     /// Information about rustc and the target platform.
-    pub target_data: RustcTargetData,
+    pub target_data: &'a RustcTargetData,
 }
 
 impl<'a, 'cfg> BuildContext<'a, 'cfg> {
@@ -47,7 +47,7 @@ impl<'a, 'cfg> BuildContext<'a, 'cfg> {
         profiles: Profiles,
         units: &'a UnitInterner<'a>,
         extra_compiler_args: HashMap<Unit<'a>, Vec<String>>,
-        target_data: RustcTargetData,
+        target_data: &'a RustcTargetData,
     ) -> CargoResult<BuildContext<'a, 'cfg>> {
         Ok(BuildContext {
             ws,
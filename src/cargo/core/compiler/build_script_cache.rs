@@ -0,0 +1,246 @@
+//! Caching of the runs of build scripts with `-Z build-script-cache`.
+//!
+//! A build script which declares its inputs with `rerun-if-changed` or
+//! `rerun-if-env-changed` has its output and the contents of its `OUT_DIR`
+//! stored once it has run, in the `build-script-cache` directory of the
+//! target directory. When it would run again with the same executable and
+//! the same environment set by Cargo, and none of its inputs have changed,
+//! the stored run is restored instead. The `OUT_DIR` isn't part of the key, so
+//! units which only differ by settings the build script doesn't see, like the
+//! level of debug info of the package, share their runs.
+//!
+//! The cache is laid out as:
+//!
+//! ```text
+//! build-script-cache/
+//!     <hash of the executable and the environment>/
+//!         <hash of the inputs>/
+//!             inputs.json   # The declared inputs and their hashes.
+//!             output        # The stdout of the build script.
+//!             stderr        # The stderr of the build script.
+//!             root-output   # The `OUT_DIR` the build script ran with.
+//!             out/          # The contents of the `OUT_DIR`.
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::core::compiler::BuildOutput;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{self, paths, ProcessBuilder, Sha256};
+
+/// The environment variables set by Cargo which aren't part of the key.
+const UNKEYED_ENV: &[&str] = &["NUM_JOBS", "OUT_DIR"];
+
+/// The stored runs of a build script with a given environment.
+pub struct BuildScriptCache {
+    /// The directory containing the runs.
+    dir: PathBuf,
+    /// The root of the package, which `rerun-if-changed` paths are relative
+    /// to.
+    pkg_root: PathBuf,
+}
+
+/// A run of a build script restored from the cache.
+pub struct CachedRun {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// The `OUT_DIR` the build script ran with, which its output may refer to.
+    pub out_dir: PathBuf,
+}
+
+/// The inputs a build script declared, and their state when it ran.
+#[derive(Serialize, Deserialize, PartialEq)]
+struct Inputs {
+    /// The hashes of the `rerun-if-changed` paths, or `None` for those which
+    /// don't exist.
+    files: BTreeMap<PathBuf, Option<String>>,
+    /// The values of the `rerun-if-env-changed` variables.
+    env: BTreeMap<String, Option<String>>,
+}
+
+impl BuildScriptCache {
+    /// Returns the cache of the runs of the build script `cmd`, in `root`.
+    pub fn new(
+        root: &Path,
+        cmd: &ProcessBuilder,
+        pkg_root: &Path,
+    ) -> CargoResult<BuildScriptCache> {
+        let mut hasher = Sha256::new();
+        hasher.update_path(cmd.get_program())?;
+        for (key, value) in cmd.get_envs() {
+            if UNKEYED_ENV.contains(&key.as_str()) {
+                continue;
+            }
+            hasher.update(key.as_bytes());
+            match value {
+                Some(value) => hasher
+                    .update(b"=")
+                    .update(value.to_string_lossy().as_bytes()),
+                None => hasher.update(b"!"),
+            };
+            hasher.update(b"\0");
+        }
+        Ok(BuildScriptCache {
+            dir: root.join(hasher.finish_hex()),
+            pkg_root: pkg_root.to_path_buf(),
+        })
+    }
+
+    /// Restores into `out_dir` a run whose inputs are unchanged, if any.
+    pub fn restore(&self, out_dir: &Path) -> CargoResult<Option<CachedRun>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(None),
+        };
+        for entry in entries {
+            let entry = entry?;
+            // Runs which are being stored start with a dot.
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let run = entry.path();
+            let inputs = match paths::read(&run.join("inputs.json"))
+                .ok()
+                .and_then(|inputs| serde_json::from_str::<Inputs>(&inputs).ok())
+            {
+                Some(inputs) => inputs,
+                None => continue,
+            };
+            if self.inputs(inputs.files.keys(), inputs.env.keys())? != inputs {
+                continue;
+            }
+            log::debug!("restoring build script run {:?}", run);
+            paths::remove_dir_all(out_dir)?;
+            paths::create_dir_all(out_dir)?;
+            copy_dir(&run.join("out"), out_dir)?;
+            return Ok(Some(CachedRun {
+                stdout: paths::read_bytes(&run.join("output"))?,
+                stderr: paths::read_bytes(&run.join("stderr"))?,
+                out_dir: util::bytes2path(&paths::read_bytes(&run.join("root-output"))?)?,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Stores a run of the build script which wrote `output` and left
+    /// `out_dir`, unless it didn't declare its inputs.
+    pub fn store(
+        &self,
+        output: &BuildOutput,
+        stdout: &[u8],
+        stderr: &[u8],
+        out_dir: &Path,
+    ) -> CargoResult<()> {
+        if output.rerun_if_changed.is_empty() && output.rerun_if_env_changed.is_empty() {
+            return Ok(());
+        }
+        let inputs = self.inputs(
+            output.rerun_if_changed.iter(),
+            output.rerun_if_env_changed.iter(),
+        )?;
+        let inputs = serde_json::to_string(&inputs)?;
+        let run = self
+            .dir
+            .join(Sha256::new().update(inputs.as_bytes()).finish_hex());
+        if run.exists() {
+            return Ok(());
+        }
+        paths::create_dir_all(&self.dir)?;
+        // The run is written to a temporary directory which is then renamed,
+        // so that other builds never see it partially written.
+        let tmp = tempfile::Builder::new()
+            .prefix(".")
+            .tempdir_in(&self.dir)?
+            .into_path();
+        let result = (|| {
+            paths::write(&tmp.join("inputs.json"), inputs.as_bytes())?;
+            paths::write(&tmp.join("output"), stdout)?;
+            paths::write(&tmp.join("stderr"), stderr)?;
+            paths::write(&tmp.join("root-output"), util::path2bytes(out_dir)?)?;
+            paths::create_dir_all(&tmp.join("out"))?;
+            copy_dir(out_dir, &tmp.join("out"))
+        })()
+        .and_then(|()| {
+            fs::rename(&tmp, &run)
+                .chain_err(|| format!("failed to rename `{}`", tmp.display()))
+                .map_err(Into::into)
+        });
+        if result.is_err() {
+            let _ = paths::remove_dir_all(&tmp);
+            // Another build may have stored the same run in the meantime.
+            if !run.exists() {
+                return result.chain_err(|| {
+                    format!(
+                        "failed to store the run of the build script in `{}`",
+                        self.dir.display()
+                    )
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the current state of the given inputs.
+    fn inputs<'a>(
+        &self,
+        files: impl Iterator<Item = &'a PathBuf>,
+        vars: impl Iterator<Item = &'a String>,
+    ) -> CargoResult<Inputs> {
+        let mut inputs = Inputs {
+            files: BTreeMap::new(),
+            env: BTreeMap::new(),
+        };
+        for file in files {
+            let hash = hash_path(&self.pkg_root.join(file))?;
+            inputs.files.insert(file.clone(), hash);
+        }
+        for var in vars {
+            inputs.env.insert(var.clone(), env::var(var).ok());
+        }
+        Ok(inputs)
+    }
+}
+
+/// Returns the hash of the contents of a file, or of the names and contents of
+/// the files in a directory, or `None` if `path` doesn't exist.
+fn hash_path(path: &Path) -> CargoResult<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut hasher = Sha256::new();
+    for entry in WalkDir::new(path).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+        let entry = entry?;
+        let name = entry.path().strip_prefix(path).unwrap();
+        hasher.update(util::path2bytes(name)?).update(b"\0");
+        if entry.path().is_file() {
+            hasher.update_path(entry.path())?;
+        }
+    }
+    Ok(Some(hasher.finish_hex()))
+}
+
+/// Copies the contents of the directory `from` into the directory `to`.
+fn copy_dir(from: &Path, to: &Path) -> CargoResult<()> {
+    for entry in WalkDir::new(from).min_depth(1) {
+        let entry = entry?;
+        let dest = to.join(entry.path().strip_prefix(from).unwrap());
+        if entry.file_type().is_dir() {
+            paths::create_dir_all(&dest)?;
+        } else if entry.path().is_file() {
+            fs::copy(entry.path(), &dest).chain_err(|| {
+                format!(
+                    "failed to copy `{}` to `{}`",
+                    entry.path().display(),
+                    dest.display()
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
@@ -15,7 +15,8 @@ use crate::util::{self, config, join_paths, process, CargoResult, Config, Proces
 pub struct Doctest {
     /// The package being doc-tested.
     pub package: Package,
-    /// The target being tested (currently always the package's lib).
+    /// The target being tested, the package's lib or a binary with
+    /// `-Z doctest-bins`.
     pub target: Target,
     /// Arguments needed to pass to rustdoc to run this test.
     pub args: Vec<OsString>,
@@ -30,7 +31,8 @@ pub struct Compilation<'cfg> {
     pub tests: Vec<(Package, Target, PathBuf)>,
 
     /// An array of all binaries created.
-    pub binaries: Vec<PathBuf>,
+    /// `(package, target, path_to_exe)`
+    pub binaries: Vec<(Package, Target, PathBuf)>,
 
     /// All directories for the output of native build commands.
     ///
@@ -167,6 +169,91 @@ impl<'cfg> Compilation<'cfg> {
         Ok(p)
     }
 
+    /// Returns a `ProcessBuilder` running `rustdoc --test` for a library,
+    /// with the given arguments for the test harness.
+    pub fn doctest_process(
+        &self,
+        doctest: &Doctest,
+        test_args: &[&str],
+    ) -> CargoResult<ProcessBuilder> {
+        let Doctest {
+            package,
+            target,
+            args,
+            unstable_opts,
+        } = doctest;
+        let mut p = self.rustdoc_process(package, target)?;
+        p.arg("--test")
+            .arg(target.src_path().path().unwrap())
+            .arg("--crate-name")
+            .arg(&target.crate_name());
+
+        // The unstable doctest-xcompile feature only enables
+        // per-target-ignores, doc tests are always compiled for the target
+        // and run with its runner, if any.
+        let doctest_xcompile = self.config.cli_unstable().doctest_xcompile;
+        if doctest_xcompile || self.host != self.target {
+            p.arg("--target").arg(&self.target);
+        }
+        if doctest_xcompile {
+            p.arg("-Zunstable-options");
+            p.arg("--enable-per-target-ignores");
+        }
+
+        if let Some((runtool, runtool_args)) = self.target_runner() {
+            p.arg("--test-runtool").arg(runtool);
+            for arg in runtool_args {
+                p.arg("--test-runtool-arg").arg(arg);
+            }
+        }
+
+        // Compile the doc tests of each crate into as few binaries as
+        // possible, instead of one binary per doc test.
+        if self.config.cli_unstable().merged_doctests {
+            p.arg("-Zunstable-options");
+            p.arg("--merge-doctests").arg("yes");
+        }
+
+        for &rust_dep in &[&self.deps_output] {
+            let mut arg = OsString::from("dependency=");
+            arg.push(rust_dep);
+            p.arg("-L").arg(arg);
+        }
+
+        for native_dep in self.native_dirs.iter() {
+            p.arg("-L").arg(native_dep);
+        }
+
+        for &host_rust_dep in &[&self.host_deps_output] {
+            let mut arg = OsString::from("dependency=");
+            arg.push(host_rust_dep);
+            p.arg("-L").arg(arg);
+        }
+
+        for arg in test_args {
+            p.arg("--test-args").arg(arg);
+        }
+
+        if let Some(cfgs) = self.cfgs.get(&package.package_id()) {
+            for cfg in cfgs.iter() {
+                p.arg("--cfg").arg(cfg);
+            }
+        }
+
+        for arg in args {
+            p.arg(arg);
+        }
+
+        if *unstable_opts {
+            p.arg("-Zunstable-options");
+        }
+
+        if let Some(flags) = self.rustdocflags.get(&package.package_id()) {
+            p.args(flags);
+        }
+        Ok(p)
+    }
+
     /// See `process`.
     pub fn host_process<T: AsRef<OsStr>>(
         &self,
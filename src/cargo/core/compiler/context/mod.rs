@@ -8,12 +8,13 @@ use jobserver::Client;
 
 use crate::core::compiler::{self, compilation, Unit};
 use crate::core::PackageId;
+use crate::sources::PathSource;
 use crate::util::errors::{CargoResult, CargoResultExt};
-use crate::util::{profile, Config};
+use crate::util::{profile, Config, ProcessBuilder};
 
 use super::build_plan::BuildPlan;
 use super::custom_build::{self, BuildDeps, BuildScriptOutputs, BuildScripts};
-use super::fingerprint::Fingerprint;
+use super::fingerprint::{self, Fingerprint};
 use super::job_queue::JobQueue;
 use super::layout::Layout;
 use super::unit_graph::{UnitDep, UnitGraph};
@@ -159,7 +160,9 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         }
 
         // Now that we've figured out everything that we're going to do, do it!
-        queue.execute(&mut self, &mut plan)?;
+        let result = queue.execute(&mut self, &mut plan);
+        self.collect_watched_paths(result.is_err())?;
+        result?;
 
         if build_plan {
             plan.set_inputs(self.build_plan_inputs()?);
@@ -184,7 +187,11 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                         output.path.clone(),
                     ));
                 } else if unit.target.is_executable() {
-                    self.compilation.binaries.push(bindst.clone());
+                    self.compilation.binaries.push((
+                        unit.pkg.clone(),
+                        unit.target.clone(),
+                        bindst.clone(),
+                    ));
                 }
             }
 
@@ -282,6 +289,91 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         Ok(None)
     }
 
+    /// Returns the processes which run the test executables of the given
+    /// `CompileMode::Test` unit, with the same environment they would get
+    /// from the final `Compilation`.
+    ///
+    /// This is used with `-Z parallel-tests` to run a test executable before
+    /// the rest of the build has finished.
+    pub fn test_processes(&self, unit: &Unit<'a>) -> CargoResult<Vec<(PathBuf, ProcessBuilder)>> {
+        let compilation = self.unit_compilation(unit)?;
+        let mut processes = Vec::new();
+        for output in self.outputs(unit)?.iter() {
+            if output.flavor == FileFlavor::DebugInfo || output.flavor == FileFlavor::Auxiliary {
+                continue;
+            }
+            let cmd = compilation.target_process(&output.path, unit.pkg)?;
+            processes.push((output.path.clone(), cmd));
+        }
+        Ok(processes)
+    }
+
+    /// Returns the `rustdoc --test` process for a `CompileMode::Doctest`
+    /// unit, like `cargo test` runs it after the build.
+    ///
+    /// This is used with `-Z merged-doctests` to run the doc tests of a crate
+    /// as soon as its dependencies have been built.
+    pub fn doctest_process(
+        &self,
+        unit: &Unit<'a>,
+        test_args: &[&str],
+    ) -> CargoResult<ProcessBuilder> {
+        let mut compilation = self.unit_compilation(unit)?;
+        let pkg_id = unit.pkg.package_id();
+        let mut cfgs: HashSet<String> = unit
+            .features
+            .iter()
+            .map(|feat| format!("feature=\"{}\"", feat))
+            .collect();
+        for (id, output) in self.build_script_outputs.lock().unwrap().iter() {
+            if id == pkg_id {
+                cfgs.extend(output.cfgs.iter().cloned());
+            }
+        }
+        compilation.cfgs.insert(pkg_id, cfgs);
+        compilation
+            .rustdocflags
+            .insert(pkg_id, self.bcx.rustdocflags_args(unit).to_vec());
+
+        let mut unstable_opts = false;
+        let args = compiler::extern_args(self, unit, &mut unstable_opts)?;
+        let doctest = compilation::Doctest {
+            package: unit.pkg.clone(),
+            target: unit.target.clone(),
+            args,
+            unstable_opts,
+        };
+        compilation.doctest_process(&doctest, test_args)
+    }
+
+    /// Builds a `Compilation` with the environment the processes run for
+    /// `unit` get, from the build scripts which have already run.
+    fn unit_compilation(&self, unit: &Unit<'a>) -> CargoResult<Compilation<'cfg>> {
+        let mut compilation = Compilation::new(self.bcx, self.bcx.build_config.requested_kind)?;
+        compilation.root_output = self.compilation.root_output.clone();
+        compilation.deps_output = self.compilation.deps_output.clone();
+        compilation.host_deps_output = self.compilation.host_deps_output.clone();
+
+        let pkg_id = unit.pkg.package_id();
+        let mut extra_env = Vec::new();
+        for dep in self.unit_deps(unit) {
+            if dep.unit.mode.is_run_custom_build() && dep.unit.pkg.package_id() == pkg_id {
+                let out_dir = self.files().build_script_out_dir(&dep.unit);
+                extra_env.push(("OUT_DIR".to_string(), out_dir.display().to_string()));
+            }
+        }
+        for (id, output) in self.build_script_outputs.lock().unwrap().iter() {
+            if id == pkg_id {
+                extra_env.extend(output.env.iter().cloned());
+            }
+            for dir in output.library_paths.iter() {
+                compilation.native_dirs.insert(dir.clone());
+            }
+        }
+        compilation.extra_env.insert(pkg_id, extra_env);
+        Ok(compilation)
+    }
+
     pub fn prepare_units(
         &mut self,
         export_dir: Option<PathBuf>,
@@ -396,6 +488,46 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         Ok(inputs.into_iter().collect())
     }
 
+    /// Records the files read by the build in `BuildConfig::watched_paths`,
+    /// when running with `--watch`. Only packages from path sources are
+    /// watched.
+    ///
+    /// The dep-info of the units which failed to build is missing or stale,
+    /// so all the files of the packages are watched when the build failed.
+    fn collect_watched_paths(&mut self, failed: bool) -> CargoResult<()> {
+        if self.bcx.build_config.watched_paths.borrow().is_none() {
+            return Ok(());
+        }
+        let units: Vec<_> = self.unit_dependencies.keys().cloned().collect();
+        let mut paths = BTreeSet::new();
+        let mut listed = HashSet::new();
+        for unit in units {
+            let source_id = unit.pkg.package_id().source_id();
+            if !source_id.is_path() {
+                continue;
+            }
+            paths.insert(unit.pkg.manifest_path().to_path_buf());
+            let files = if failed {
+                None
+            } else {
+                fingerprint::input_files(self, &unit)?
+            };
+            match files {
+                Some(files) => paths.extend(files),
+                None => {
+                    if listed.insert(unit.pkg.package_id()) {
+                        let source = PathSource::new(unit.pkg.root(), source_id, self.bcx.config);
+                        paths.extend(source.list_files(unit.pkg)?);
+                    }
+                }
+            }
+        }
+        if let Some(watched_paths) = &mut *self.bcx.build_config.watched_paths.borrow_mut() {
+            watched_paths.extend(paths);
+        }
+        Ok(())
+    }
+
     fn check_collistions(&self) -> CargoResult<()> {
         let mut output_collisions = HashMap::new();
         let describe_collision =
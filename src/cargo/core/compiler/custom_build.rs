@@ -1,4 +1,6 @@
+use super::build_script_cache::BuildScriptCache;
 use super::job::{Freshness, Job, Work};
+use super::sandbox::Sandbox;
 use super::{fingerprint, Context, Unit};
 use crate::core::compiler::context::Metadata;
 use crate::core::compiler::job_queue::JobState;
@@ -37,6 +39,11 @@ pub struct BuildOutput {
     pub rerun_if_env_changed: Vec<String>,
     /// Warnings generated by this build.
     pub warnings: Vec<String>,
+    /// Errors reported by this build with `cargo::error`, which make it fail.
+    pub errors: Vec<String>,
+    /// The `--check-cfg` flags to pass to the compiler, declaring the
+    /// expected cfgs.
+    pub check_cfgs: Vec<String>,
 }
 
 /// Map of packages to build script output.
@@ -162,10 +169,6 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
     let build_plan = bcx.build_config.build_plan;
     let invocation_name = unit.buildkey();
 
-    if let Some(deps) = unit.pkg.manifest().metabuild() {
-        prepare_metabuild(cx, build_script_unit, deps)?;
-    }
-
     // Building the command to execute
     let to_exec = script_dir.join(unit.target.name());
 
@@ -283,6 +286,23 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
     let build_scripts = cx.build_scripts.get(unit).cloned();
     let json_messages = bcx.build_config.emit_json();
     let extra_verbose = bcx.config.extra_verbose();
+    let directives = bcx.config.cli_unstable().build_script_directives;
+    let cache_dir = if bcx.config.cli_unstable().build_script_cache {
+        Some(
+            bcx.ws
+                .target_dir()
+                .join("build-script-cache")
+                .into_path_unlocked(),
+        )
+    } else {
+        None
+    };
+    let pkg_root = unit.pkg.root().to_path_buf();
+    let sandbox = if bcx.config.cli_unstable().sandbox_build_scripts && !build_plan {
+        Sandbox::for_build_script(cx, unit, &script_dir)?
+    } else {
+        None
+    };
     let (prev_output, prev_script_out_dir) = prev_build_output(cx, unit);
     let metadata_hash = cx.get_run_build_script_metadata(unit);
 
@@ -341,43 +361,69 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
             return Ok(());
         }
 
-        // And now finally, run the build command itself!
-        state.running(&cmd);
+        // With `-Z build-script-cache`, a previous run with the same inputs is
+        // restored instead of running the build script.
+        let cache = match &cache_dir {
+            Some(dir) => Some(BuildScriptCache::new(dir, &cmd, &pkg_root)?),
+            None => None,
+        };
+        let restored = match &cache {
+            Some(cache) => cache.restore(&script_out_dir)?,
+            None => None,
+        };
+        let is_restored = restored.is_some();
         let timestamp = paths::set_invocation_time(&script_run_dir)?;
-        let prefix = format!("[{} {}] ", id.name(), id.version());
-        let mut warnings_in_case_of_panic = Vec::new();
-        let output = cmd
-            .exec_with_streaming(
-                &mut |stdout| {
-                    if stdout.starts_with(CARGO_WARNING) {
-                        warnings_in_case_of_panic.push(stdout[CARGO_WARNING.len()..].to_owned());
-                    }
-                    if extra_verbose {
-                        state.stdout(format!("{}{}", prefix, stdout));
-                    }
-                    Ok(())
-                },
-                &mut |stderr| {
-                    if extra_verbose {
-                        state.stderr(format!("{}{}", prefix, stderr));
-                    }
-                    Ok(())
-                },
-                true,
-            )
-            .chain_err(|| format!("failed to run custom build command for `{}`", pkg_name));
-
-        if let Err(error) = output {
-            insert_warnings_in_build_outputs(
-                build_script_outputs,
-                id,
-                metadata_hash,
-                warnings_in_case_of_panic,
-            );
-            return Err(error);
-        }
+        let (stdout, stderr, generated_out_dir) = match restored {
+            Some(run) => {
+                state.restored(format!("build script output of `{}`", pkg_name));
+                (run.stdout, run.stderr, run.out_dir)
+            }
+            None => {
+                // And now finally, run the build command itself!
+                state.running(&cmd);
+                let prefix = format!("[{} {}] ", id.name(), id.version());
+                let mut warnings_in_case_of_panic = Vec::new();
+                let mut command = cmd.build_command();
+                if let Some(sandbox) = &sandbox {
+                    sandbox.apply(&mut command)?;
+                }
+                let output = cmd
+                    .exec_command_with_streaming(
+                        command,
+                        &mut |stdout| {
+                            if stdout.starts_with(CARGO_WARNING) {
+                                warnings_in_case_of_panic
+                                    .push(stdout[CARGO_WARNING.len()..].to_owned());
+                            }
+                            if extra_verbose {
+                                state.stdout(format!("{}{}", prefix, stdout));
+                            }
+                            Ok(())
+                        },
+                        &mut |stderr| {
+                            if extra_verbose {
+                                state.stderr(format!("{}{}", prefix, stderr));
+                            }
+                            Ok(())
+                        },
+                        true,
+                    )
+                    .chain_err(|| format!("failed to run custom build command for `{}`", pkg_name));
+
+                if let Err(error) = output {
+                    insert_warnings_in_build_outputs(
+                        build_script_outputs,
+                        id,
+                        metadata_hash,
+                        warnings_in_case_of_panic,
+                    );
+                    return Err(error);
+                }
 
-        let output = output.unwrap();
+                let output = output.unwrap();
+                (output.stdout, output.stderr, script_out_dir.clone())
+            }
+        };
 
         // After the build command has finished running, we need to be sure to
         // remember all of its output so we can later discover precisely what it
@@ -386,26 +432,43 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
         // This is also the location where we provide feedback into the build
         // state informing what variables were discovered via our script as
         // well.
-        paths::write(&output_file, &output.stdout)?;
+        paths::write(&output_file, &stdout)?;
         log::debug!(
             "rewinding custom script output mtime {:?} to {}",
             output_file,
             timestamp
         );
         filetime::set_file_times(output_file, timestamp, timestamp)?;
-        paths::write(&err_file, &output.stderr)?;
-        paths::write(&root_output_file, util::path2bytes(&script_out_dir)?)?;
-        let parsed_output =
-            BuildOutput::parse(&output.stdout, &pkg_name, &script_out_dir, &script_out_dir)?;
+        paths::write(&err_file, &stderr)?;
+        paths::write(&root_output_file, util::path2bytes(&generated_out_dir)?)?;
+        let parsed_output = BuildOutput::parse(
+            &stdout,
+            &pkg_name,
+            &generated_out_dir,
+            &script_out_dir,
+            directives,
+        )?;
+        if let Some(cache) = &cache {
+            if !is_restored && parsed_output.errors.is_empty() {
+                cache.store(&parsed_output, &stdout, &stderr, &script_out_dir)?;
+            }
+        }
 
         if json_messages {
             emit_build_output(state, &parsed_output, script_out_dir.as_path(), id);
         }
+        let errors = parsed_output.errors.len();
         build_script_outputs
             .lock()
             .unwrap()
             .insert(id, metadata_hash, parsed_output);
-        Ok(())
+        // The errors themselves are printed with the warnings of the build
+        // script, once this job has failed.
+        match errors {
+            0 => Ok(()),
+            1 => anyhow::bail!("build script of `{}` reported an error", pkg_name),
+            n => anyhow::bail!("build script of `{}` reported {} errors", pkg_name, n),
+        }
     });
 
     // Now that we've prepared our work-to-do, we need to prepare the fresh work
@@ -420,6 +483,7 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoRes
                 &pkg_name,
                 &prev_script_out_dir,
                 &script_out_dir,
+                directives,
             )?,
         };
 
@@ -469,6 +533,7 @@ impl BuildOutput {
         pkg_name: &str,
         script_out_dir_when_generated: &Path,
         script_out_dir: &Path,
+        directives: bool,
     ) -> CargoResult<BuildOutput> {
         let contents = paths::read_bytes(path)?;
         BuildOutput::parse(
@@ -476,16 +541,22 @@ impl BuildOutput {
             pkg_name,
             script_out_dir_when_generated,
             script_out_dir,
+            directives,
         )
     }
 
-    // Parses the output of a script.
-    // The `pkg_name` is used for error messages.
+    /// Parses the output of a build script.
+    ///
+    /// With `directives`, lines starting with `cargo::` are also understood,
+    /// for `-Z build-script-directives`. Unlike the `cargo:` lines, where any
+    /// unknown key is metadata for the dependents, an unknown `cargo::`
+    /// directive is an error.
     pub fn parse(
         input: &[u8],
         pkg_name: &str,
         script_out_dir_when_generated: &Path,
         script_out_dir: &Path,
+        directives: bool,
     ) -> CargoResult<BuildOutput> {
         let mut library_paths = Vec::new();
         let mut library_links = Vec::new();
@@ -496,6 +567,8 @@ impl BuildOutput {
         let mut rerun_if_changed = Vec::new();
         let mut rerun_if_env_changed = Vec::new();
         let mut warnings = Vec::new();
+        let mut errors = Vec::new();
+        let mut check_cfgs = Vec::new();
         let whence = format!("build script of `{}`", pkg_name);
 
         for line in input.split(|b| *b == b'\n') {
@@ -512,6 +585,11 @@ impl BuildOutput {
                 Some(val) => val,
                 None => continue,
             };
+            let (data, directive) = if directives && data.starts_with(':') {
+                (&data[1..], true)
+            } else {
+                (data, false)
+            };
 
             // getting the `key=value` part of the line
             let mut iter = data.splitn(2, '=');
@@ -529,6 +607,47 @@ impl BuildOutput {
                 script_out_dir.to_str().unwrap(),
             );
 
+            if directive {
+                match key {
+                    "error" => {
+                        errors.push(value.to_string());
+                        continue;
+                    }
+                    "rustc-check-cfg" => {
+                        check_cfgs.push(value.to_string());
+                        continue;
+                    }
+                    "metadata" => {
+                        let mut iter = value.splitn(2, '=');
+                        match (iter.next(), iter.next()) {
+                            (Some(k), Some(v)) => metadata.push((k.to_string(), v.to_string())),
+                            _ => anyhow::bail!(
+                                "invalid `cargo::metadata` in {}: `{}`, expected `KEY=VALUE`",
+                                whence,
+                                value
+                            ),
+                        }
+                        continue;
+                    }
+                    "rustc-flags"
+                    | "rustc-link-lib"
+                    | "rustc-link-search"
+                    | "rustc-cdylib-link-arg"
+                    | "rustc-cfg"
+                    | "rustc-env"
+                    | "warning"
+                    | "rerun-if-changed"
+                    | "rerun-if-env-changed" => {}
+                    _ => anyhow::bail!(
+                        "unknown directive `cargo::{}` in {}\n\
+                         Metadata for the dependents of the package is passed with \
+                         `cargo::metadata=KEY=VALUE`.",
+                        key,
+                        whence
+                    ),
+                }
+            }
+
             // Keep in sync with TargetConfig::new.
             match key {
                 "rustc-flags" => {
@@ -558,6 +677,8 @@ impl BuildOutput {
             rerun_if_changed,
             rerun_if_env_changed,
             warnings,
+            errors,
+            check_cfgs,
         })
     }
 
@@ -617,6 +738,14 @@ impl BuildOutput {
     }
 }
 
+/// Generates the build script of a package with `metabuild`, which runs the
+/// `metabuild` function of each package in order, then the handwritten build
+/// script of the package, if any.
+///
+/// Before each package runs, `CARGO_METABUILD_CONFIG` is set to the path of
+/// a JSON file in `OUT_DIR` with its `[package.metadata.<name>]` table, or
+/// unset if there is none. The tables are part of the generated source, so
+/// that the build script is rebuilt and runs again when they change.
 fn prepare_metabuild<'a, 'cfg>(
     cx: &Context<'a, 'cfg>,
     unit: &Unit<'a>,
@@ -624,6 +753,7 @@ fn prepare_metabuild<'a, 'cfg>(
 ) -> CargoResult<()> {
     let mut output = Vec::new();
     let available_deps = cx.unit_deps(unit);
+    let metadata = unit.pkg.manifest().custom_metadata();
     // Filter out optional dependencies, and look up the actual lib name.
     let meta_deps: Vec<_> = deps
         .iter()
@@ -631,24 +761,173 @@ fn prepare_metabuild<'a, 'cfg>(
             available_deps
                 .iter()
                 .find(|d| d.unit.pkg.name().as_str() == name.as_str())
-                .map(|d| d.unit.target.crate_name())
+                .map(|d| (name, d.unit.target.crate_name()))
         })
         .collect();
-    for dep in &meta_deps {
+    for (_, dep) in &meta_deps {
         output.push(format!("use {};\n", dep));
     }
     output.push("fn main() {\n".to_string());
-    for dep in &meta_deps {
+    for (name, dep) in &meta_deps {
+        let config = match metadata.and_then(|m| m.get(name.as_str())) {
+            Some(config) => format!("Some({:?})", serde_json::to_string(config)?),
+            None => "None".to_string(),
+        };
+        output.push(format!("    metabuild_config({:?}, {});\n", name, config));
         output.push(format!("    {}::metabuild();\n", dep));
     }
+    let script = unit.pkg.manifest().metabuild_script();
+    if script.is_some() {
+        output.push("    build_script::__metabuild_main();\n".to_string());
+    }
     output.push("}\n".to_string());
-    let output = output.join("");
+    if !meta_deps.is_empty() {
+        output.push(
+            r#"
+fn metabuild_config(name: &str, config: Option<&str>) {
+    match config {
+        Some(config) => {
+            let out_dir = std::env::var_os("OUT_DIR").unwrap();
+            let path = std::path::Path::new(&out_dir).join(format!("metabuild-{}.json", name));
+            std::fs::write(&path, config).unwrap();
+            std::env::set_var("CARGO_METABUILD_CONFIG", path);
+        }
+        None => std::env::remove_var("CARGO_METABUILD_CONFIG"),
+    }
+}
+"#
+            .to_string(),
+        );
+    }
     let path = unit.pkg.manifest().metabuild_path(cx.bcx.ws.target_dir());
     paths::create_dir_all(path.parent().unwrap())?;
+    if let Some(script) = script {
+        // Including the script in a module whose path is the directory of
+        // the script lets it declare modules of its own, and lets its `main`
+        // be called from a function of the same module. `include!` doesn't
+        // accept inner attributes, so those are moved to the module, and a
+        // copy of the rest of the script is included instead.
+        let (header, body) = split_inner_attributes(&paths::read(script)?);
+        let included = if header.is_empty() {
+            script.to_path_buf()
+        } else {
+            let copy = path.with_extension("build.rs");
+            paths::write_if_changed(&copy, &body)?;
+            copy
+        };
+        output.push(format!(
+            r#"
+#[path = {:?}]
+mod build_script {{
+{}
+    include!({:?});
+
+    pub fn __metabuild_main() {{
+        main()
+    }}
+}}
+"#,
+            script.parent().unwrap().display().to_string(),
+            header,
+            included.display().to_string(),
+        ));
+    }
+    let output = output.join("");
     paths::write_if_changed(path, &output)?;
     Ok(())
 }
 
+/// Splits the inner attributes and inner doc comments at the start of a
+/// build script from the rest of it, as `include!` doesn't accept them.
+///
+/// In the rest, they are replaced with spaces, so that the line and column
+/// numbers of diagnostics stay the same. A shebang line is removed too.
+fn split_inner_attributes(source: &str) -> (String, String) {
+    let mut header = String::new();
+    let mut removed = Vec::new();
+    let mut pos = 0;
+    loop {
+        let rest = &source[pos..];
+        let trimmed = rest.trim_start();
+        pos += rest.len() - trimmed.len();
+        let line_len = trimmed.find('\n').unwrap_or(trimmed.len());
+        let (len, hoist) = if trimmed.starts_with("//!") {
+            (line_len, true)
+        } else if trimmed.starts_with("/*!") {
+            match trimmed.find("*/") {
+                Some(end) => (end + 2, true),
+                None => break,
+            }
+        } else if trimmed.starts_with("//") {
+            (line_len, false)
+        } else if trimmed.starts_with("/*") {
+            match trimmed.find("*/") {
+                Some(end) => (end + 2, false),
+                None => break,
+            }
+        } else if trimmed.starts_with("#!") && trimmed[2..].trim_start().starts_with('[') {
+            match attribute_len(trimmed) {
+                Some(len) => (len, true),
+                None => break,
+            }
+        } else if pos == 0 && trimmed.starts_with("#!") {
+            removed.push(pos..pos + line_len);
+            pos += line_len;
+            continue;
+        } else {
+            break;
+        };
+        if hoist {
+            header.push_str(&trimmed[..len]);
+            header.push('\n');
+            removed.push(pos..pos + len);
+        }
+        pos += len;
+    }
+    let body = source
+        .char_indices()
+        .map(|(i, c)| {
+            if c != '\n' && removed.iter().any(|range| range.contains(&i)) {
+                ' '
+            } else {
+                c
+            }
+        })
+        .collect();
+    (header, body)
+}
+
+/// Returns the length of the inner attribute at the start of `source`, up to
+/// its closing bracket.
+fn attribute_len(source: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in source.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
 impl BuildDeps {
     pub fn new(output_file: &Path, output: Option<&BuildOutput>) -> BuildDeps {
         BuildDeps {
@@ -732,6 +1011,14 @@ pub fn build_map<'b, 'cfg>(cx: &mut Context<'b, 'cfg>, units: &[Unit<'b>]) -> Ca
             parse_previous_explicit_deps(cx, unit)?;
         }
 
+        // Generate the build script of a package with `metabuild` before any
+        // fingerprint looks at it.
+        if unit.target.is_custom_build() && !unit.mode.is_run_custom_build() {
+            if let Some(deps) = unit.pkg.manifest().metabuild() {
+                prepare_metabuild(cx, unit, deps)?;
+            }
+        }
+
         // We want to invoke the compiler deterministically to be cache-friendly
         // to rustc invocation caching schemes, so be sure to generate the same
         // set of build script dependency orderings via sorting the targets that
@@ -802,6 +1089,7 @@ fn prev_build_output<'a, 'cfg>(
             &unit.pkg.to_string(),
             &prev_script_out_dir,
             &script_out_dir,
+            cx.bcx.config.cli_unstable().build_script_directives,
         )
         .ok(),
         prev_script_out_dir,
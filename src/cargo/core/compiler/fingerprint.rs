@@ -79,13 +79,17 @@
 //! - A file with a 16 hex-digit hash. This is the Fingerprint hash, used for
 //!   quick loading and comparison.
 //! - A `.json` file that contains details about the Fingerprint. This is only
-//!   used to log details about *why* a fingerprint is considered dirty.
-//!   `CARGO_LOG=cargo::core::compiler::fingerprint=trace cargo build` can be
-//!   used to display this log information.
+//!   used to explain *why* a fingerprint is considered dirty. The explanation
+//!   is a `DirtyReason`, which is displayed with `-Z dirty-reasons -v` or
+//!   emitted as a `dirty-unit` JSON message. More detail is available with
+//!   `CARGO_LOG=cargo::core::compiler::fingerprint=trace cargo build`.
 //! - A "dep-info" file which contains a list of source filenames for the
 //!   target. This is produced by reading the output of `rustc
 //!   --emit=dep-info` and packing it into a condensed format. Cargo uses this
 //!   to check the mtime of every file to see if any of them have changed.
+//!   With `-Z checksum-freshness` it also records the size and checksum of
+//!   each file, so that files with a newer mtime but the same contents (for
+//!   example restored from a cache) don't cause a rebuild.
 //! - An `invoked.timestamp` file whose filesystem mtime is updated every time
 //!   the Unit is built. This is an experimental feature used for cleaning
 //!   unused artifacts.
@@ -190,12 +194,13 @@
 
 use std::collections::hash_map::{Entry, HashMap};
 use std::env;
+use std::fs;
 use std::hash::{self, Hasher};
 use std::path::{Path, PathBuf};
+use std::str;
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
-use anyhow::{bail, format_err};
 use filetime::FileTime;
 use log::{debug, info};
 use serde::de;
@@ -207,13 +212,10 @@ use crate::core::{InternedString, Package};
 use crate::util;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::paths;
-use crate::util::{internal, profile};
+use crate::util::{internal, profile, Sha256};
 
 use super::custom_build::BuildDeps;
-use super::job::{
-    Freshness::{Dirty, Fresh},
-    Job, Work,
-};
+use super::job::{Freshness::Fresh, Job, Work};
 use super::{BuildContext, Context, FileFlavor, Unit};
 
 /// Determines if a `unit` is up-to-date, and if not prepares necessary work to
@@ -251,8 +253,8 @@ pub fn prepare_target<'a, 'cfg>(
     // information about failed comparisons to aid in debugging.
     let fingerprint = calculate(cx, unit)?;
     let mtime_on_use = cx.bcx.config.cli_unstable().mtime_on_use;
-    let compare = compare_old_fingerprint(&loc, &*fingerprint, mtime_on_use);
-    log_compare(unit, &compare);
+    let dirty_reason = compare_old_fingerprint(&loc, &*fingerprint, mtime_on_use);
+    log_compare(unit, &dirty_reason);
 
     // If our comparison failed (e.g., we're going to trigger a rebuild of this
     // crate), then we also ensure the source of the crate passes all
@@ -264,7 +266,7 @@ pub fn prepare_target<'a, 'cfg>(
     // directory sources which will use this hook to perform an integrity check
     // on all files in the source to ensure they haven't changed. If they have
     // changed then an error is issued.
-    if compare.is_err() {
+    if dirty_reason.is_some() {
         let source_id = unit.pkg.package_id().source_id();
         let sources = bcx.packages.sources();
         let source = sources
@@ -273,9 +275,11 @@ pub fn prepare_target<'a, 'cfg>(
         source.verify(unit.pkg.package_id())?;
     }
 
-    if compare.is_ok() && !force {
-        return Ok(Job::new(Work::noop(), Fresh));
-    }
+    let dirty_reason = match dirty_reason {
+        Some(reason) => reason,
+        None if force => DirtyReason::Forced,
+        None => return Ok(Job::new(Work::noop(), Fresh)),
+    };
 
     let write_fingerprint = if unit.mode.is_run_custom_build() {
         // For build scripts the `local` field of the fingerprint may change
@@ -292,12 +296,20 @@ pub fn prepare_target<'a, 'cfg>(
         let metadata = cx.get_run_build_script_metadata(unit);
         let (gen_local, _overridden) = build_script_local_fingerprints(cx, unit);
         let output_path = cx.build_explicit_deps[unit].build_script_output.clone();
+        let pkg_root = unit.pkg.root().to_path_buf();
+        let checksum_freshness = bcx.config.cli_unstable().checksum_freshness;
         Work::new(move |_| {
             let outputs = build_script_outputs.lock().unwrap();
             let output = outputs
                 .get(pkg_id, metadata)
                 .expect("output must exist after running");
             let deps = BuildDeps::new(&output_path, Some(output));
+            write_rerun_if_changed_checksums(
+                &output_path,
+                &pkg_root,
+                &deps.rerun_if_changed,
+                checksum_freshness,
+            )?;
 
             // FIXME: it's basically buggy that we pass `None` to `call_box`
             // here. See documentation on `build_script_local_fingerprints`
@@ -313,7 +325,7 @@ pub fn prepare_target<'a, 'cfg>(
         Work::new(move |_| write_fingerprint(&loc, &fingerprint))
     };
 
-    Ok(Job::new(write_fingerprint, Dirty))
+    Ok(Job::new_dirty(write_fingerprint, dirty_reason))
 }
 
 /// Dependency edge information for fingerprints. This is generated for each
@@ -407,6 +419,18 @@ enum FsStatus {
     /// unit needs to subsequently be recompiled.
     Stale,
 
+    /// This unit is stale because one of its input or output files is
+    /// missing or has changed.
+    StaleItem(StaleFile),
+
+    /// This unit is stale because the outputs of a dependency are newer than
+    /// its own, meaning the dependency was rebuilt since.
+    StaleDependency { name: InternedString },
+
+    /// This unit is stale because a dependency is stale itself, and will be
+    /// rebuilt.
+    StaleDepFingerprint { name: InternedString },
+
     /// This unit is up-to-date. All outputs and their corresponding mtime are
     /// listed in the payload here for other dependencies to compare against.
     UpToDate { mtimes: HashMap<PathBuf, FileTime> },
@@ -414,10 +438,32 @@ enum FsStatus {
 
 impl FsStatus {
     fn up_to_date(&self) -> bool {
-        match self {
-            FsStatus::UpToDate { .. } => true,
-            FsStatus::Stale => false,
-        }
+        matches!(self, FsStatus::UpToDate { .. })
+    }
+
+    /// Explains why this status is stale, if it is.
+    fn dirty_reason(&self) -> Option<DirtyReason> {
+        let reason = match self {
+            FsStatus::UpToDate { .. } => return None,
+            FsStatus::Stale => DirtyReason::NothingObvious,
+            FsStatus::StaleItem(StaleFile::Missing(path)) => {
+                DirtyReason::FileMissing { path: path.clone() }
+            }
+            FsStatus::StaleItem(StaleFile::Changed {
+                reference,
+                reference_mtime,
+                stale,
+                stale_mtime,
+            }) => DirtyReason::FileChanged {
+                path: stale.clone(),
+                mtime: *stale_mtime,
+                reference: reference.clone(),
+                reference_mtime: *reference_mtime,
+            },
+            FsStatus::StaleDependency { name } => DirtyReason::DependencyRebuilt { name: *name },
+            FsStatus::StaleDepFingerprint { name } => DirtyReason::DependencyStale { name: *name },
+        };
+        Some(reason)
     }
 }
 
@@ -500,7 +546,8 @@ enum LocalFingerprint {
     ///
     /// The `dep_info` file, when present, also lists a number of other files
     /// for us to look at. If any of those files are newer than this file then
-    /// we need to recompile.
+    /// we need to recompile, unless the dep-info file recorded a checksum for
+    /// it which still matches its contents.
     CheckDepInfo { dep_info: PathBuf },
 
     /// This represents a nonempty set of `rerun-if-changed` annotations printed
@@ -511,7 +558,10 @@ enum LocalFingerprint {
     /// to `pkg.root()`
     ///
     /// This is considered up-to-date if all of the `paths` are older than
-    /// `output`, otherwise we need to recompile.
+    /// `output`, otherwise we need to recompile. With `-Z checksum-freshness`
+    /// the checksums of the `paths` are recorded next to `output` (see
+    /// `write_rerun_if_changed_checksums`), and newer files whose contents
+    /// did not change are also considered up-to-date.
     RerunIfChanged {
         output: PathBuf,
         paths: Vec<PathBuf>,
@@ -534,17 +584,205 @@ enum StaleFile {
     },
 }
 
+/// An explanation of why a unit is dirty and needs to be rebuilt.
+///
+/// This is calculated by comparing the fingerprint of a unit with the one
+/// saved by its previous build. With `-Z dirty-reasons` it's displayed in
+/// verbose mode and emitted as a `dirty-unit` JSON message.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum DirtyReason {
+    /// There is no fingerprint from a previous build.
+    FreshBuild,
+    /// The unit was forced to rebuild, for example by `cargo fix`.
+    Forced,
+    RustcChanged,
+    FeaturesChanged {
+        old: String,
+        new: String,
+    },
+    TargetConfigurationChanged,
+    PathToSourceChanged,
+    ProfileConfigurationChanged,
+    RustflagsChanged {
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    MetadataChanged,
+    LocalLengthsChanged,
+    PrecalculatedComponentsChanged {
+        old: String,
+        new: String,
+    },
+    DepInfoOutputChanged {
+        old: PathBuf,
+        new: PathBuf,
+    },
+    RerunIfChangedOutputFileChanged {
+        old: PathBuf,
+        new: PathBuf,
+    },
+    RerunIfChangedOutputPathsChanged {
+        old: Vec<PathBuf>,
+        new: Vec<PathBuf>,
+    },
+    EnvVarsChanged {
+        old: String,
+        new: String,
+    },
+    EnvVarChanged {
+        name: String,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    },
+    LocalFingerprintTypeChanged {
+        old: &'static str,
+        new: &'static str,
+    },
+    NumberOfDependenciesChanged {
+        old: usize,
+        new: usize,
+    },
+    UnitDependencyNameChanged {
+        old: InternedString,
+        new: InternedString,
+    },
+    UnitDependencyInfoChanged {
+        name: InternedString,
+        old_fingerprint: u64,
+        new_fingerprint: u64,
+    },
+    /// An input or output file of the unit is missing.
+    FileMissing {
+        path: PathBuf,
+    },
+    /// An input file of the unit is newer than the `reference` file recording
+    /// the previous build.
+    FileChanged {
+        path: PathBuf,
+        #[serde(serialize_with = "serialize_mtime")]
+        mtime: FileTime,
+        reference: PathBuf,
+        #[serde(serialize_with = "serialize_mtime")]
+        reference_mtime: FileTime,
+    },
+    /// A dependency was rebuilt after the previous build of this unit.
+    DependencyRebuilt {
+        name: InternedString,
+    },
+    /// A dependency is dirty itself.
+    DependencyStale {
+        name: InternedString,
+    },
+    NothingObvious,
+}
+
+impl DirtyReason {
+    /// Returns whether the unit was simply never built before, in which case
+    /// there is nothing interesting to explain.
+    pub fn is_fresh_build(&self) -> bool {
+        matches!(self, DirtyReason::FreshBuild)
+    }
+
+    /// Describes this reason for humans, with paths displayed relative to
+    /// `pkg_root` where possible.
+    pub fn describe(&self, pkg_root: &Path) -> String {
+        let path = |path: &Path| {
+            path.strip_prefix(pkg_root)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        };
+        match self {
+            DirtyReason::FreshBuild => "the unit has not been built before".to_string(),
+            DirtyReason::Forced => "the build was forced".to_string(),
+            DirtyReason::RustcChanged => "the toolchain changed".to_string(),
+            DirtyReason::FeaturesChanged { old, new } => {
+                format!("the list of features changed from {} to {}", old, new)
+            }
+            DirtyReason::TargetConfigurationChanged => {
+                "the target configuration changed".to_string()
+            }
+            DirtyReason::PathToSourceChanged => "the path to the source changed".to_string(),
+            DirtyReason::ProfileConfigurationChanged => {
+                "the profile configuration changed".to_string()
+            }
+            DirtyReason::RustflagsChanged { old, new } => {
+                format!("the rustflags changed from {:?} to {:?}", old, new)
+            }
+            DirtyReason::MetadataChanged => "the package metadata changed".to_string(),
+            DirtyReason::LocalLengthsChanged => "the local fingerprints changed".to_string(),
+            DirtyReason::PrecalculatedComponentsChanged { .. } => {
+                "the precalculated components changed".to_string()
+            }
+            DirtyReason::DepInfoOutputChanged { .. } => {
+                "the dependency info output changed".to_string()
+            }
+            DirtyReason::RerunIfChangedOutputFileChanged { .. } => {
+                "the rerun-if-changed instruction output file changed".to_string()
+            }
+            DirtyReason::RerunIfChangedOutputPathsChanged { .. } => {
+                "the rerun-if-changed instruction paths changed".to_string()
+            }
+            DirtyReason::EnvVarsChanged { .. } => {
+                "the rerun-if-env-changed instructions changed".to_string()
+            }
+            DirtyReason::EnvVarChanged {
+                name,
+                old_value,
+                new_value,
+            } => match (old_value, new_value) {
+                (None, Some(_)) => format!("the env variable `{}` was set", name),
+                (Some(_), None) => format!("the env variable `{}` was unset", name),
+                _ => format!("the env variable `{}` changed", name),
+            },
+            DirtyReason::LocalFingerprintTypeChanged { old, new } => {
+                format!("the local fingerprint type changed from {} to {}", old, new)
+            }
+            DirtyReason::NumberOfDependenciesChanged { old, new } => {
+                format!("the number of dependencies changed from {} to {}", old, new)
+            }
+            DirtyReason::UnitDependencyNameChanged { old, new } => {
+                format!("the name of dependency `{}` changed to `{}`", old, new)
+            }
+            DirtyReason::UnitDependencyInfoChanged { name, .. }
+            | DirtyReason::DependencyRebuilt { name }
+            | DirtyReason::DependencyStale { name } => {
+                format!("the dependency `{}` was rebuilt", name)
+            }
+            DirtyReason::FileMissing { path: p } => format!("the file `{}` is missing", path(p)),
+            DirtyReason::FileChanged { path: p, .. } => {
+                format!("the file `{}` has changed", path(p))
+            }
+            DirtyReason::NothingObvious => {
+                "the fingerprint comparison turned up nothing obvious".to_string()
+            }
+        }
+    }
+}
+
+/// Serializes an mtime as fractional seconds since the Unix epoch.
+fn serialize_mtime<S>(mtime: &FileTime, s: S) -> Result<S::Ok, S::Error>
+where
+    S: ser::Serializer,
+{
+    let secs = mtime.unix_seconds() as f64 + f64::from(mtime.nanoseconds()) / 1e9;
+    s.serialize_f64(secs)
+}
+
 impl LocalFingerprint {
     /// Checks dynamically at runtime if this `LocalFingerprint` has a stale
     /// file.
     ///
     /// This will use the absolute root paths passed in if necessary to guide
-    /// file accesses.
+    /// file accesses. Recorded checksums are only taken into account if
+    /// `checksum_freshness` is set.
     fn find_stale_file(
         &self,
         mtime_cache: &mut HashMap<PathBuf, FileTime>,
         pkg_root: &Path,
         target_root: &Path,
+        checksum_freshness: bool,
     ) -> CargoResult<Option<StaleFile>> {
         match self {
             // We need to parse `dep_info`, learn about all the files the crate
@@ -553,8 +791,11 @@ impl LocalFingerprint {
             // unit has never been compiled!
             LocalFingerprint::CheckDepInfo { dep_info } => {
                 let dep_info = target_root.join(dep_info);
-                if let Some(paths) = parse_dep_info(pkg_root, target_root, &dep_info)? {
-                    Ok(find_stale_file(mtime_cache, &dep_info, paths.iter()))
+                if let Some(files) = parse_dep_info_checksums(pkg_root, target_root, &dep_info)? {
+                    let files = files
+                        .into_iter()
+                        .map(|(path, checksum)| (path, checksum.filter(|_| checksum_freshness)));
+                    Ok(find_stale_file(mtime_cache, &dep_info, files))
                 } else {
                     Ok(Some(StaleFile::Missing(dep_info)))
                 }
@@ -562,11 +803,28 @@ impl LocalFingerprint {
 
             // We need to verify that no paths listed in `paths` are newer than
             // the `output` path itself, or the last time the build script ran.
-            LocalFingerprint::RerunIfChanged { output, paths } => Ok(find_stale_file(
-                mtime_cache,
-                &target_root.join(output),
-                paths.iter().map(|p| pkg_root.join(p)),
-            )),
+            LocalFingerprint::RerunIfChanged { output, paths } => {
+                let output = target_root.join(output);
+                let mut checksums = HashMap::new();
+                if checksum_freshness {
+                    let checksums_path = rerun_if_changed_checksums_path(&output);
+                    let files = parse_dep_info_checksums(pkg_root, target_root, &checksums_path)?;
+                    for (path, checksum) in files.unwrap_or_default() {
+                        if let Some(checksum) = checksum {
+                            checksums.insert(path, checksum);
+                        }
+                    }
+                }
+                Ok(find_stale_file(
+                    mtime_cache,
+                    &output,
+                    paths.iter().map(|p| {
+                        let path = pkg_root.join(p);
+                        let checksum = checksums.remove(&path);
+                        (path, checksum)
+                    }),
+                ))
+            }
 
             // These have no dependencies on the filesystem, and their values
             // are included natively in the `Fingerprint` hash so nothing
@@ -629,49 +887,50 @@ impl Fingerprint {
     /// Compares this fingerprint with an old version which was previously
     /// serialized to filesystem.
     ///
-    /// The purpose of this is exclusively to produce a diagnostic message
-    /// indicating why we're recompiling something. This function always returns
-    /// an error, it will never return success.
-    fn compare(&self, old: &Fingerprint) -> CargoResult<()> {
+    /// The purpose of this is exclusively to produce a diagnostic explaining
+    /// why we're recompiling something, it's only called once the two
+    /// fingerprints are known to differ.
+    fn compare(&self, old: &Fingerprint) -> DirtyReason {
         if self.rustc != old.rustc {
-            bail!("rust compiler has changed")
+            return DirtyReason::RustcChanged;
         }
         if self.features != old.features {
-            bail!(
-                "features have changed: {} != {}",
-                self.features,
-                old.features
-            )
+            return DirtyReason::FeaturesChanged {
+                old: old.features.clone(),
+                new: self.features.clone(),
+            };
         }
         if self.target != old.target {
-            bail!("target configuration has changed")
+            return DirtyReason::TargetConfigurationChanged;
         }
         if self.path != old.path {
-            bail!("path to the source has changed")
+            return DirtyReason::PathToSourceChanged;
         }
         if self.profile != old.profile {
-            bail!("profile configuration has changed")
+            return DirtyReason::ProfileConfigurationChanged;
         }
         if self.rustflags != old.rustflags {
-            bail!(
-                "RUSTFLAGS has changed: {:?} != {:?}",
-                self.rustflags,
-                old.rustflags
-            )
+            return DirtyReason::RustflagsChanged {
+                old: old.rustflags.clone(),
+                new: self.rustflags.clone(),
+            };
         }
         if self.metadata != old.metadata {
-            bail!("metadata changed")
+            return DirtyReason::MetadataChanged;
         }
         let my_local = self.local.lock().unwrap();
         let old_local = old.local.lock().unwrap();
         if my_local.len() != old_local.len() {
-            bail!("local lens changed");
+            return DirtyReason::LocalLengthsChanged;
         }
         for (new, old) in my_local.iter().zip(old_local.iter()) {
             match (new, old) {
                 (LocalFingerprint::Precalculated(a), LocalFingerprint::Precalculated(b)) => {
                     if a != b {
-                        bail!("precalculated components have changed: {} != {}", a, b)
+                        return DirtyReason::PrecalculatedComponentsChanged {
+                            old: b.clone(),
+                            new: a.clone(),
+                        };
                     }
                 }
                 (
@@ -679,7 +938,10 @@ impl Fingerprint {
                     LocalFingerprint::CheckDepInfo { dep_info: bdep },
                 ) => {
                     if adep != bdep {
-                        bail!("dep info output changed: {:?} != {:?}", adep, bdep)
+                        return DirtyReason::DepInfoOutputChanged {
+                            old: bdep.clone(),
+                            new: adep.clone(),
+                        };
                     }
                 }
                 (
@@ -693,14 +955,16 @@ impl Fingerprint {
                     },
                 ) => {
                     if aout != bout {
-                        bail!("rerun-if-changed output changed: {:?} != {:?}", aout, bout)
+                        return DirtyReason::RerunIfChangedOutputFileChanged {
+                            old: bout.clone(),
+                            new: aout.clone(),
+                        };
                     }
                     if apaths != bpaths {
-                        bail!(
-                            "rerun-if-changed output changed: {:?} != {:?}",
-                            apaths,
-                            bpaths,
-                        )
+                        return DirtyReason::RerunIfChangedOutputPathsChanged {
+                            old: bpaths.clone(),
+                            new: apaths.clone(),
+                        };
                     }
                 }
                 (
@@ -714,57 +978,60 @@ impl Fingerprint {
                     },
                 ) => {
                     if *akey != *bkey {
-                        bail!("env vars changed: {} != {}", akey, bkey);
+                        return DirtyReason::EnvVarsChanged {
+                            old: bkey.clone(),
+                            new: akey.clone(),
+                        };
                     }
                     if *avalue != *bvalue {
-                        bail!(
-                            "env var `{}` changed: previously {:?} now {:?}",
-                            akey,
-                            bvalue,
-                            avalue
-                        )
+                        return DirtyReason::EnvVarChanged {
+                            name: akey.clone(),
+                            old_value: bvalue.clone(),
+                            new_value: avalue.clone(),
+                        };
+                    }
+                }
+                (a, b) => {
+                    return DirtyReason::LocalFingerprintTypeChanged {
+                        old: b.kind(),
+                        new: a.kind(),
                     }
                 }
-                (a, b) => bail!(
-                    "local fingerprint type has changed ({} => {})",
-                    b.kind(),
-                    a.kind()
-                ),
             }
         }
 
         if self.deps.len() != old.deps.len() {
-            bail!("number of dependencies has changed")
+            return DirtyReason::NumberOfDependenciesChanged {
+                old: old.deps.len(),
+                new: self.deps.len(),
+            };
         }
         for (a, b) in self.deps.iter().zip(old.deps.iter()) {
             if a.name != b.name {
-                let e = format_err!("`{}` != `{}`", a.name, b.name)
-                    .context("unit dependency name changed");
-                return Err(e.into());
+                return DirtyReason::UnitDependencyNameChanged {
+                    old: b.name,
+                    new: a.name,
+                };
             }
 
             if a.fingerprint.hash() != b.fingerprint.hash() {
-                let e = format_err!(
-                    "new ({}/{:x}) != old ({}/{:x})",
-                    a.name,
-                    a.fingerprint.hash(),
-                    b.name,
-                    b.fingerprint.hash()
-                )
-                .context("unit dependency information changed");
-                return Err(e.into());
+                return DirtyReason::UnitDependencyInfoChanged {
+                    name: a.name,
+                    old_fingerprint: b.fingerprint.hash(),
+                    new_fingerprint: a.fingerprint.hash(),
+                };
             }
         }
 
-        if !self.fs_status.up_to_date() {
-            bail!("current filesystem status shows we're outdated");
+        if let Some(reason) = self.fs_status.dirty_reason() {
+            return reason;
         }
 
         // This typically means some filesystem modifications happened or
         // something transitive was odd. In general we should strive to provide
-        // a better error message than this, so if you see this message a lot it
+        // a better explanation than this, so if you see this reason a lot it
         // likely means this method needs to be updated!
-        bail!("two fingerprint comparison turned up nothing obvious");
+        DirtyReason::NothingObvious
     }
 
     /// Dynamically inspect the local filesystem to update the `fs_status` field
@@ -780,6 +1047,7 @@ impl Fingerprint {
         mtime_cache: &mut HashMap<PathBuf, FileTime>,
         pkg_root: &Path,
         target_root: &Path,
+        checksum_freshness: bool,
     ) -> CargoResult<()> {
         assert!(!self.fs_status.up_to_date());
 
@@ -797,6 +1065,7 @@ impl Fingerprint {
                 // exists, so leave ourselves as stale and bail out.
                 Err(e) => {
                     debug!("failed to get mtime of {:?}: {}", output, e);
+                    self.fs_status = FsStatus::StaleItem(StaleFile::Missing(output.clone()));
                     return Ok(());
                 }
             };
@@ -824,7 +1093,10 @@ impl Fingerprint {
             let dep_mtimes = match &dep.fingerprint.fs_status {
                 FsStatus::UpToDate { mtimes } => mtimes,
                 // If our dependency is stale, so are we, so bail out.
-                FsStatus::Stale => return Ok(()),
+                _ => {
+                    self.fs_status = FsStatus::StaleDepFingerprint { name: dep.name };
+                    return Ok(());
+                }
             };
 
             // If our dependency edge only requires the rmeta file to be present
@@ -862,6 +1134,7 @@ impl Fingerprint {
                     "dependency on `{}` is newer than we are {} > {} {:?}",
                     dep.name, dep_mtime, max_mtime, pkg_root
                 );
+                self.fs_status = FsStatus::StaleDependency { name: dep.name };
                 return Ok(());
             }
         }
@@ -871,8 +1144,11 @@ impl Fingerprint {
         // files for this package itself. If we do find something log a helpful
         // message and bail out so we stay stale.
         for local in self.local.get_mut().unwrap().iter() {
-            if let Some(file) = local.find_stale_file(mtime_cache, pkg_root, target_root)? {
+            if let Some(file) =
+                local.find_stale_file(mtime_cache, pkg_root, target_root, checksum_freshness)?
+            {
                 file.log();
+                self.fs_status = FsStatus::StaleItem(file);
                 return Ok(());
             }
         }
@@ -991,8 +1267,7 @@ impl StaleFile {
     /// Use the `log` crate to log a hopefully helpful message in diagnosing
     /// what file is considered stale and why. This is intended to be used in
     /// conjunction with `CARGO_LOG` to determine why Cargo is recompiling
-    /// something. Currently there's no user-facing usage of this other than
-    /// that.
+    /// something. The user-facing explanation is a `DirtyReason`.
     fn log(&self) {
         match self {
             StaleFile::Missing(path) => {
@@ -1044,7 +1319,13 @@ fn calculate<'a, 'cfg>(
     // After we built the initial `Fingerprint` be sure to update the
     // `fs_status` field of it.
     let target_root = target_root(cx);
-    fingerprint.check_filesystem(&mut cx.mtime_cache, unit.pkg.root(), &target_root)?;
+    let checksum_freshness = cx.bcx.config.cli_unstable().checksum_freshness;
+    fingerprint.check_filesystem(
+        &mut cx.mtime_cache,
+        unit.pkg.root(),
+        &target_root,
+        checksum_freshness,
+    )?;
 
     let fingerprint = Arc::new(fingerprint);
     cx.fingerprints.insert(*unit, Arc::clone(&fingerprint));
@@ -1383,6 +1664,45 @@ pub fn dep_info_loc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> Pa
         .join(&format!("dep-{}", filename(cx, unit)))
 }
 
+/// Returns the files that `unit` was last built from, for `--watch`.
+///
+/// These are the files of the dep-info of a compilation, or the
+/// `rerun-if-changed` paths of a build script. `None` is returned when they
+/// are not known, in which case all the files of the package may be inputs,
+/// as is the case for the fingerprint.
+pub fn input_files<'a, 'cfg>(
+    cx: &mut Context<'a, 'cfg>,
+    unit: &Unit<'a>,
+) -> CargoResult<Option<Vec<PathBuf>>> {
+    let pkg_root = unit.pkg.root();
+    if unit.mode.is_run_custom_build() {
+        // Overridden build scripts don't read anything.
+        if let Some(links) = unit.pkg.manifest().links() {
+            if cx.bcx.script_override(links, unit.kind).is_some() {
+                return Ok(Some(Vec::new()));
+            }
+        }
+        let metadata = cx.get_run_build_script_metadata(unit);
+        let build_script_outputs = cx.build_script_outputs.lock().unwrap();
+        let (files, env) = match build_script_outputs.get(unit.pkg.package_id(), metadata) {
+            Some(output) => (&output.rerun_if_changed, &output.rerun_if_env_changed),
+            None => match cx.build_explicit_deps.get(unit) {
+                Some(deps) => (&deps.rerun_if_changed, &deps.rerun_if_env_changed),
+                None => return Ok(None),
+            },
+        };
+        if files.is_empty() && env.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(files.iter().map(|file| pkg_root.join(file)).collect()))
+    } else if use_dep_info(unit) {
+        let dep_info = dep_info_loc(cx, unit);
+        parse_dep_info(pkg_root, &target_root(cx), &dep_info)
+    } else {
+        Ok(None)
+    }
+}
+
 /// Returns an absolute path that target directory.
 /// All paths are rewritten to be relative to this.
 fn target_root(cx: &Context<'_, '_>) -> PathBuf {
@@ -1393,7 +1713,21 @@ fn compare_old_fingerprint(
     loc: &Path,
     new_fingerprint: &Fingerprint,
     mtime_on_use: bool,
-) -> CargoResult<()> {
+) -> Option<DirtyReason> {
+    match _compare_old_fingerprint(loc, new_fingerprint, mtime_on_use) {
+        Ok(reason) => reason,
+        Err(e) => {
+            debug!("failed to load old fingerprint {:?}: {:?}", loc, e);
+            Some(DirtyReason::FreshBuild)
+        }
+    }
+}
+
+fn _compare_old_fingerprint(
+    loc: &Path,
+    new_fingerprint: &Fingerprint,
+    mtime_on_use: bool,
+) -> CargoResult<Option<DirtyReason>> {
     let old_fingerprint_short = paths::read(loc)?;
 
     if mtime_on_use {
@@ -1406,28 +1740,26 @@ fn compare_old_fingerprint(
     let new_hash = new_fingerprint.hash();
 
     if util::to_hex(new_hash) == old_fingerprint_short && new_fingerprint.fs_status.up_to_date() {
-        return Ok(());
+        return Ok(None);
     }
 
     let old_fingerprint_json = paths::read(&loc.with_extension("json"))?;
     let old_fingerprint: Fingerprint = serde_json::from_str(&old_fingerprint_json)
         .chain_err(|| internal("failed to deserialize json"))?;
     debug_assert_eq!(util::to_hex(old_fingerprint.hash()), old_fingerprint_short);
-    let result = new_fingerprint.compare(&old_fingerprint);
-    assert!(result.is_err());
-    result
+    Ok(Some(new_fingerprint.compare(&old_fingerprint)))
 }
 
-fn log_compare(unit: &Unit<'_>, compare: &CargoResult<()>) {
-    let ce = match compare {
-        Ok(..) => return,
-        Err(e) => e,
+fn log_compare(unit: &Unit<'_>, dirty_reason: &Option<DirtyReason>) {
+    let reason = match dirty_reason {
+        None => return,
+        Some(reason) => reason,
     };
     info!(
-        "fingerprint error for {}/{:?}/{:?}",
+        "fingerprint dirty for {}/{:?}/{:?}",
         unit.pkg, unit.mode, unit.target,
     );
-    info!("    err: {:?}", ce);
+    info!("    dirty: {:?}", reason);
 }
 
 // Parse the dep-info into a list of paths
@@ -1436,27 +1768,46 @@ pub fn parse_dep_info(
     target_root: &Path,
     dep_info: &Path,
 ) -> CargoResult<Option<Vec<PathBuf>>> {
+    let files = parse_dep_info_checksums(pkg_root, target_root, dep_info)?;
+    Ok(files.map(|files| files.into_iter().map(|(path, _)| path).collect()))
+}
+
+// Parse the dep-info into a list of paths, along with the size and checksum
+// recorded for them with `-Z checksum-freshness`.
+fn parse_dep_info_checksums(
+    pkg_root: &Path,
+    target_root: &Path,
+    dep_info: &Path,
+) -> CargoResult<Option<Vec<(PathBuf, Option<Checksum>)>>> {
     let data = match paths::read_bytes(dep_info) {
         Ok(data) => data,
         Err(_) => return Ok(None),
     };
-    let paths = data
+    let files = data
         .split(|&x| x == 0)
         .filter(|x| !x.is_empty())
         .map(|p| {
-            let ty = match DepInfoPathType::from_byte(p[0]) {
+            let ty = match DepInfoPathType::from_byte(p[0] & !DEP_INFO_CHECKSUM_FLAG) {
                 Some(ty) => ty,
                 None => return Err(internal("dep-info invalid")),
             };
-            let path = util::bytes2path(&p[1..])?;
-            match ty {
-                DepInfoPathType::PackageRootRelative => Ok(pkg_root.join(path)),
+            let (checksum, path) = if p[0] & DEP_INFO_CHECKSUM_FLAG != 0 {
+                let (checksum, path) =
+                    Checksum::decode(&p[1..]).ok_or_else(|| internal("dep-info invalid"))?;
+                (Some(checksum), path)
+            } else {
+                (None, &p[1..])
+            };
+            let path = util::bytes2path(path)?;
+            let path = match ty {
+                DepInfoPathType::PackageRootRelative => pkg_root.join(path),
                 // N.B. path might be absolute here in which case the join will have no effect
-                DepInfoPathType::TargetRootRelative => Ok(target_root.join(path)),
-            }
+                DepInfoPathType::TargetRootRelative => target_root.join(path),
+            };
+            Ok((path, checksum))
         })
         .collect::<Result<Vec<_>, _>>()?;
-    Ok(Some(paths))
+    Ok(Some(files))
 }
 
 fn pkg_fingerprint(bcx: &BuildContext<'_, '_>, pkg: &Package) -> CargoResult<String> {
@@ -1469,21 +1820,21 @@ fn pkg_fingerprint(bcx: &BuildContext<'_, '_>, pkg: &Package) -> CargoResult<Str
     source.fingerprint(pkg)
 }
 
-fn find_stale_file<I>(
+fn find_stale_file<I, P>(
     mtime_cache: &mut HashMap<PathBuf, FileTime>,
     reference: &Path,
     paths: I,
 ) -> Option<StaleFile>
 where
-    I: IntoIterator,
-    I::Item: AsRef<Path>,
+    I: IntoIterator<Item = (P, Option<Checksum>)>,
+    P: AsRef<Path>,
 {
     let reference_mtime = match paths::mtime(reference) {
         Ok(mtime) => mtime,
         Err(..) => return Some(StaleFile::Missing(reference.to_path_buf())),
     };
 
-    for path in paths {
+    for (path, checksum) in paths {
         let path = path.as_ref();
         let path_mtime = match mtime_cache.entry(path.to_path_buf()) {
             Entry::Occupied(o) => *o.get(),
@@ -1518,6 +1869,17 @@ where
             continue;
         }
 
+        // A newer file may still have the same contents as when the unit was
+        // built, for example if it was checked out again or restored from a
+        // cache. With `-Z checksum-freshness` we recorded its size and
+        // checksum, so compare those before giving up.
+        if let Some(checksum) = checksum {
+            if checksum.matches(path) {
+                debug!("checksum of {:?} unchanged despite newer mtime", path);
+                continue;
+            }
+        }
+
         return Some(StaleFile::Changed {
             reference: reference.to_path_buf(),
             reference_mtime,
@@ -1551,7 +1913,12 @@ fn filename<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> String {
     format!("{}{}-{}", flavor, kind, file_stem)
 }
 
+/// Set in the type byte of Cargo's dep-info entries which also record the
+/// size and checksum of the file, see `translate_dep_info`.
+const DEP_INFO_CHECKSUM_FLAG: u8 = 0x80;
+
 #[repr(u8)]
+#[derive(Clone, Copy)]
 enum DepInfoPathType {
     // src/, e.g. src/lib.rs
     PackageRootRelative = 1,
@@ -1570,6 +1937,117 @@ impl DepInfoPathType {
     }
 }
 
+/// The size and SHA-256 checksum of a file's contents, recorded with
+/// `-Z checksum-freshness`.
+struct Checksum {
+    size: u64,
+    hash: String,
+}
+
+impl Checksum {
+    fn compute(path: &Path) -> CargoResult<Checksum> {
+        let size = fs::metadata(path)
+            .chain_err(|| format!("failed to stat `{}`", path.display()))?
+            .len();
+        let hash = Sha256::new().update_path(path)?.finish_hex();
+        Ok(Checksum { size, hash })
+    }
+
+    /// Returns whether the file at `path` still has this size and checksum.
+    /// The size is compared first, to avoid reading files which obviously
+    /// changed.
+    fn matches(&self, path: &Path) -> bool {
+        match fs::metadata(path) {
+            Ok(meta) if meta.is_file() && meta.len() == self.size => {}
+            _ => return false,
+        }
+        match Sha256::new().update_path(path) {
+            Ok(hasher) => hasher.finish_hex() == self.hash,
+            Err(_) => false,
+        }
+    }
+
+    /// Decodes the `{size}:{hash}:` prefix of a dep-info entry, returning the
+    /// remaining path bytes.
+    fn decode(entry: &[u8]) -> Option<(Checksum, &[u8])> {
+        let mut parts = entry.splitn(3, |&b| b == b':');
+        let size = str::from_utf8(parts.next()?).ok()?.parse().ok()?;
+        let hash = str::from_utf8(parts.next()?).ok()?.to_string();
+        Some((Checksum { size, hash }, parts.next()?))
+    }
+}
+
+/// Appends an entry for `path` to the contents of a Cargo dep-info file.
+fn encode_dep_info_entry(
+    contents: &mut Vec<u8>,
+    ty: DepInfoPathType,
+    path: &Path,
+    checksum: Option<&Checksum>,
+) -> CargoResult<()> {
+    match checksum {
+        Some(checksum) => {
+            contents.push(ty as u8 | DEP_INFO_CHECKSUM_FLAG);
+            contents.extend(format!("{}:{}:", checksum.size, checksum.hash).as_bytes());
+        }
+        None => contents.push(ty as u8),
+    }
+    contents.extend(util::path2bytes(path)?);
+    contents.push(0);
+    Ok(())
+}
+
+/// Computes the checksum of `path` for a dep-info file, if it's a regular file
+/// which was last modified before `timestamp`.
+///
+/// Files modified after `timestamp`, the time the compilation (or build
+/// script) started, may have changed while it ran. The contents used then are
+/// unknown, so these files are only tracked by their mtime.
+fn dep_info_checksum(path: &Path, timestamp: FileTime) -> Option<Checksum> {
+    match paths::mtime(path) {
+        Ok(mtime) if mtime <= timestamp && path.is_file() => Checksum::compute(path).ok(),
+        _ => None,
+    }
+}
+
+/// The file recording the checksums of the `rerun-if-changed` paths of a build
+/// script whose output is saved in `output`.
+fn rerun_if_changed_checksums_path(output: &Path) -> PathBuf {
+    output.with_file_name("rerun-if-changed-checksums")
+}
+
+/// Records the checksums of the `rerun-if-changed` paths of a build script
+/// which just ran, in the same format as Cargo's dep-info files.
+///
+/// Without `-Z checksum-freshness` any previously recorded checksums are
+/// removed instead, as they may no longer match what the build script saw.
+fn write_rerun_if_changed_checksums(
+    output: &Path,
+    pkg_root: &Path,
+    rerun_if_changed: &[PathBuf],
+    checksum_freshness: bool,
+) -> CargoResult<()> {
+    let checksums_path = rerun_if_changed_checksums_path(output);
+    if !checksum_freshness {
+        if checksums_path.exists() {
+            paths::remove_file(&checksums_path)?;
+        }
+        return Ok(());
+    }
+    // The mtime of the output is rewound to when the build script started.
+    let timestamp = paths::mtime(output)?;
+    let mut contents = Vec::new();
+    for path in rerun_if_changed {
+        let checksum = dep_info_checksum(&pkg_root.join(path), timestamp);
+        encode_dep_info_entry(
+            &mut contents,
+            DepInfoPathType::PackageRootRelative,
+            path,
+            checksum.as_ref(),
+        )?;
+    }
+    paths::write(&checksums_path, &contents)
+}
+
 /// Parses the dep-info file coming out of rustc into a Cargo-specific format.
 ///
 /// This function will parse `rustc_dep_info` as a makefile-style dep info to
@@ -1590,6 +2068,10 @@ impl DepInfoPathType {
 /// the source never changes, and we don't want the cost of running `stat` on
 /// all those files).
 ///
+/// If `checksum_timestamp` is given (with `-Z checksum-freshness`), it is the
+/// time the compilation started, and the size and checksum of every file
+/// which was not modified since then are recorded as well.
+///
 /// The serialized Cargo format will contain a list of files, all of which are
 /// relative if they're under `root`. or absolute if they're elsewhere.
 pub fn translate_dep_info(
@@ -1599,6 +2081,7 @@ pub fn translate_dep_info(
     pkg_root: &Path,
     target_root: &Path,
     allow_package: bool,
+    checksum_timestamp: Option<FileTime>,
 ) -> CargoResult<()> {
     let target = parse_rustc_dep_info(rustc_dep_info)?;
     let deps = &target
@@ -1631,9 +2114,8 @@ pub fn translate_dep_info(
             // effect.
             (DepInfoPathType::TargetRootRelative, &*abs_file)
         };
-        new_contents.push(ty as u8);
-        new_contents.extend(util::path2bytes(path)?);
-        new_contents.push(0);
+        let checksum = checksum_timestamp.and_then(|t| dep_info_checksum(&canon_file, t));
+        encode_dep_info_entry(&mut new_contents, ty, path, checksum.as_ref())?;
     }
     paths::write(cargo_dep_info, &new_contents)?;
     Ok(())
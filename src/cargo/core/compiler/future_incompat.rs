@@ -0,0 +1,209 @@
+//! Support for reporting future-incompatible warnings.
+//!
+//! Some lints are scheduled to become hard errors in a future release of
+//! Rust. Warnings in dependencies are hidden by `--cap-lints`, so with
+//! `-Z future-incompat-report` Cargo passes `--json=future-incompat` to rustc,
+//! which then emits a separate `future_incompat_report` JSON message listing
+//! the offending diagnostics regardless of the lint cap.
+//!
+//! The `JobQueue` collects these messages per package, saves them to
+//! `target/.future-incompat-report.json`, and prints a short summary at the
+//! end of the build. `cargo report future-incompat` loads the saved report to
+//! display the diagnostics again.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::compiler::BuildContext;
+use crate::core::{Dependency, PackageId, Source, SourceId, Workspace};
+use crate::sources::SourceConfigMap;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, Config};
+
+/// The name of the file in the root of the target directory holding the
+/// report from the last build.
+pub const FUTURE_INCOMPAT_FILE: &str = ".future-incompat-report.json";
+
+/// The JSON message emitted by rustc with `--json=future-incompat`.
+#[derive(Deserialize)]
+pub struct FutureIncompatReport {
+    pub future_incompat_report: Vec<FutureBreakageItem>,
+}
+
+#[derive(Deserialize)]
+pub struct FutureBreakageItem {
+    pub diagnostic: Diagnostic,
+}
+
+#[derive(Deserialize)]
+pub struct Diagnostic {
+    pub rendered: String,
+}
+
+/// The report saved to disk at the end of the build.
+#[derive(Serialize, Deserialize, Default)]
+pub struct OnDiskReport {
+    /// The rendered diagnostics for each affected package.
+    packages: BTreeMap<PackageId, Vec<String>>,
+}
+
+impl OnDiskReport {
+    pub fn new(packages: BTreeMap<PackageId, Vec<String>>) -> OnDiskReport {
+        OnDiskReport { packages }
+    }
+
+    /// Saves the report in the target directory of `bcx`, and summarizes
+    /// which packages are affected.
+    pub fn save_and_display(&self, bcx: &BuildContext<'_, '_>) -> CargoResult<()> {
+        let path = bcx
+            .ws
+            .target_dir()
+            .as_path_unlocked()
+            .join(FUTURE_INCOMPAT_FILE);
+        paths::create_dir_all(path.parent().unwrap())?;
+        paths::write(&path, serde_json::to_string(self)?.as_bytes())?;
+
+        if self.packages.is_empty() {
+            return Ok(());
+        }
+        let package_list = self
+            .packages
+            .keys()
+            .map(|pkg_id| pkg_id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut shell = bcx.config.shell();
+        shell.warn(format!(
+            "the following packages contain code that will be rejected by a future \
+             version of Rust: {}",
+            package_list
+        ))?;
+        shell.note(
+            "to see what the problems were, run \
+             `cargo report future-incompat -Z future-incompat-report`",
+        )?;
+        Ok(())
+    }
+
+    /// Loads the report saved by the last build of `ws`.
+    pub fn load(ws: &Workspace<'_>) -> CargoResult<OnDiskReport> {
+        let path = ws
+            .target_dir()
+            .as_path_unlocked()
+            .join(FUTURE_INCOMPAT_FILE);
+        if !path.exists() {
+            anyhow::bail!(
+                "no future-incompat report found; run a build with \
+                 `-Z future-incompat-report` to generate one"
+            );
+        }
+        let contents = paths::read(&path)?;
+        serde_json::from_str(&contents)
+            .chain_err(|| format!("failed to parse report at `{}`", path.display()))
+    }
+
+    /// Renders the full report, including the diagnostics for each package
+    /// and whether newer versions of it are available.
+    pub fn render(&self, ws: &Workspace<'_>, color: bool) -> String {
+        let config = ws.config();
+        if self.packages.is_empty() {
+            return "No future incompatibility warnings were found in the last build.\n"
+                .to_string();
+        }
+        let updates = newer_versions(config, self.packages.keys().cloned());
+        let mut out = String::new();
+        for (pkg_id, diagnostics) in &self.packages {
+            writeln!(
+                out,
+                "The package `{}` currently triggers the following future \
+                 incompatibility lints:",
+                pkg_id
+            )
+            .unwrap();
+            for diagnostic in diagnostics {
+                let diagnostic = if color {
+                    diagnostic.clone()
+                } else {
+                    strip_ansi_escapes::strip(diagnostic)
+                        .map(|v| String::from_utf8(v).expect("utf8"))
+                        .expect("strip should never fail")
+                };
+                for line in diagnostic.lines() {
+                    writeln!(out, "> {}", line).unwrap();
+                }
+            }
+            if let Some(versions) = updates.get(pkg_id) {
+                writeln!(
+                    out,
+                    "\n`{}` has the following newer versions available: {}",
+                    pkg_id,
+                    versions.join(", ")
+                )
+                .unwrap();
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Finds newer versions of the registry packages in `pkg_ids` in the index.
+///
+/// The index is updated first unless Cargo is offline. Errors are ignored,
+/// as this information is only a hint.
+fn newer_versions(
+    config: &Config,
+    pkg_ids: impl Iterator<Item = PackageId>,
+) -> HashMap<PackageId, Vec<String>> {
+    let mut updates = HashMap::new();
+    let _lock = match config.acquire_package_cache_lock() {
+        Ok(lock) => lock,
+        Err(_) => return updates,
+    };
+    let map = match SourceConfigMap::new(config) {
+        Ok(map) => map,
+        Err(_) => return updates,
+    };
+    let mut sources: HashMap<SourceId, Box<dyn Source + '_>> = HashMap::new();
+    for pkg_id in pkg_ids.filter(|id| id.source_id().is_registry()) {
+        // Packages from a lock file are "locked" to their registry, which
+        // would prevent the update.
+        let source_id = pkg_id.source_id().with_precise(None);
+        let source = match sources.entry(source_id) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let mut source = match map.load(source_id, &HashSet::new()) {
+                    Ok(source) => source,
+                    Err(_) => continue,
+                };
+                if !config.offline() && source.update().is_err() {
+                    continue;
+                }
+                e.insert(source)
+            }
+        };
+        let dep = match Dependency::parse_no_deprecated(pkg_id.name(), None, source_id) {
+            Ok(dep) => dep,
+            Err(_) => continue,
+        };
+        let summaries = match source.query_vec(&dep) {
+            Ok(summaries) => summaries,
+            Err(_) => continue,
+        };
+        let mut versions = summaries
+            .iter()
+            .map(|summary| summary.version())
+            .filter(|version| *version > pkg_id.version())
+            .collect::<Vec<_>>();
+        if versions.is_empty() {
+            continue;
+        }
+        versions.sort();
+        let versions = versions.iter().map(|v| v.to_string()).collect();
+        updates.insert(pkg_id, versions);
+    }
+    updates
+}
@@ -1,12 +1,14 @@
 use std::fmt;
 use std::mem;
 
+use super::fingerprint::DirtyReason;
 use super::job_queue::JobState;
 use crate::util::CargoResult;
 
 pub struct Job {
     work: Work,
     fresh: Freshness,
+    dirty_reason: Option<DirtyReason>,
 }
 
 /// Each proc should send its description before starting.
@@ -42,7 +44,20 @@ impl Work {
 impl Job {
     /// Creates a new job representing a unit of work.
     pub fn new(work: Work, fresh: Freshness) -> Job {
-        Job { work, fresh }
+        Job {
+            work,
+            fresh,
+            dirty_reason: None,
+        }
+    }
+
+    /// Creates a new dirty job, recording why it needs to be rebuilt.
+    pub fn new_dirty(work: Work, dirty_reason: DirtyReason) -> Job {
+        Job {
+            work,
+            fresh: Freshness::Dirty,
+            dirty_reason: Some(dirty_reason),
+        }
     }
 
     /// Consumes this job by running it, returning the result of the
@@ -58,6 +73,11 @@ impl Job {
         self.fresh
     }
 
+    /// Returns the reason this job is dirty, if known.
+    pub fn dirty_reason(&self) -> Option<&DirtyReason> {
+        self.dirty_reason.as_ref()
+    }
+
     pub fn before(&mut self, next: Work) {
         let prev = mem::replace(&mut self.work, Work::noop());
         self.work = next.then(prev);
@@ -50,10 +50,12 @@
 //! improved.
 
 use std::cell::Cell;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::io;
 use std::marker;
 use std::mem;
+use std::path::PathBuf;
+use std::process::{Output, Stdio};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -63,15 +65,20 @@ use jobserver::{Acquired, Client, HelperThread};
 use log::{debug, info, trace};
 
 use super::context::OutputFile;
+use super::future_incompat::{FutureBreakageItem, OnDiskReport};
 use super::job::{
     Freshness::{self, Dirty, Fresh},
     Job,
 };
+use super::test_runner::{TestFailure, TestRunner};
 use super::timings::Timings;
-use super::{BuildContext, BuildPlan, CompileMode, Context, Unit};
+use super::{BuildContext, BuildPlan, CompileMode, Context, DirtyReason, Unit};
+use crate::core::shell::Verbosity;
 use crate::core::{PackageId, TargetKind};
 use crate::util;
 use crate::util::diagnostic_server::{self, DiagnosticPrinter};
+use crate::util::errors::process_error;
+use crate::util::machine_message::{self, Message as _};
 use crate::util::Queue;
 use crate::util::{internal, profile, CargoResult, CargoResultExt, ProcessBuilder};
 use crate::util::{Config, DependencyQueue};
@@ -153,6 +160,33 @@ struct DrainState<'a, 'cfg> {
 
     // How many jobs we've finished
     finished: usize,
+
+    /// Rendered future-incompat warnings reported by rustc, for each package.
+    future_incompat_reports: BTreeMap<PackageId, Vec<String>>,
+
+    /// The private dependencies whose types are exposed in the public API of
+    /// each package, as reported by the `exported_private_dependencies` lint.
+    leaked_private_dependencies: BTreeMap<PackageId, BTreeSet<String>>,
+
+    /// Runs the test executables as soon as they are built, with
+    /// `-Z parallel-tests`, and the doc tests with `-Z merged-doctests`.
+    test_runner: Option<Arc<TestRunner>>,
+
+    /// Tests which are ready to run but haven't started yet, each of which
+    /// needs a jobserver token like any other job.
+    pending_test_runs: Vec<TestRun<'a>>,
+
+    /// The tests which are currently running, keyed by the id of their job
+    /// in `active`.
+    test_runs: HashMap<JobId, TestRun<'a>>,
+}
+
+/// A test executable or `rustdoc --test` run as a job of the build.
+struct TestRun<'a> {
+    unit: Unit<'a>,
+    /// The test executable, or `None` for doc tests.
+    exe: Option<PathBuf>,
+    cmd: ProcessBuilder,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -204,12 +238,16 @@ enum Artifact {
 
 enum Message {
     Run(JobId, String),
+    Restored(JobId, String),
     BuildPlanMsg(String, ProcessBuilder, Arc<Vec<OutputFile>>),
     Stdout(String),
     Stderr(String),
     FixDiagnostic(diagnostic_server::Message),
     Token(io::Result<Acquired>),
     Finish(JobId, Artifact, CargoResult<()>),
+    FutureIncompatReport(JobId, Vec<FutureBreakageItem>),
+    LeakedPrivateDependency(JobId, String),
+    TestOutput(JobId, io::Result<Output>),
 
     // This client should get release_raw called on it with one of our tokens
     NeedsToken(JobId),
@@ -218,11 +256,34 @@ enum Message {
     ReleaseToken(JobId),
 }
 
+/// A helper struct with a `Drop` implementation to guarantee that a `Finish`
+/// message is sent even if a job panics. We shouldn't panic unless there's a
+/// bug in Cargo, so we just need to make sure nothing hangs by accident.
+struct FinishOnDrop<'a> {
+    messages: &'a Queue<Message>,
+    id: JobId,
+    result: CargoResult<()>,
+}
+
+impl Drop for FinishOnDrop<'_> {
+    fn drop(&mut self) {
+        let msg = mem::replace(&mut self.result, Ok(()));
+        self.messages
+            .push(Message::Finish(self.id, Artifact::All, msg));
+    }
+}
+
 impl<'a> JobState<'a> {
     pub fn running(&self, cmd: &ProcessBuilder) {
         self.messages.push(Message::Run(self.id, cmd.to_string()));
     }
 
+    /// Like `running`, for a job which restores what `description` refers
+    /// to from a cache instead of running a command.
+    pub fn restored(&self, description: String) {
+        self.messages.push(Message::Restored(self.id, description));
+    }
+
     pub fn build_plan(
         &self,
         module_name: String,
@@ -252,6 +313,21 @@ impl<'a> JobState<'a> {
             .push(Message::Finish(self.id, Artifact::Metadata, Ok(())));
     }
 
+    /// Forwards the future-incompat report from rustc to the coordinator
+    /// thread, which summarizes the reports at the end of the build.
+    pub fn future_incompat_report(&self, report: Vec<FutureBreakageItem>) {
+        self.messages
+            .push(Message::FutureIncompatReport(self.id, report));
+    }
+
+    /// Forwards the name of a private dependency whose types are exposed in
+    /// the public API of the crate being compiled to the coordinator thread,
+    /// which summarizes them at the end of the build.
+    pub fn leaked_private_dependency(&self, dep_name: String) {
+        self.messages
+            .push(Message::LeakedPrivateDependency(self.id, dep_name));
+    }
+
     /// The rustc underlying this Job is about to acquire a jobserver token (i.e., block)
     /// on the passed client.
     ///
@@ -381,6 +457,11 @@ impl<'a, 'cfg> JobQueue<'a, 'cfg> {
             pending_queue: Vec::new(),
             print: DiagnosticPrinter::new(cx.bcx.config),
             finished: 0,
+            future_incompat_reports: BTreeMap::new(),
+            leaked_private_dependencies: BTreeMap::new(),
+            test_runner: cx.bcx.build_config.test_runner.borrow().clone(),
+            pending_test_runs: Vec::new(),
+            test_runs: HashMap::new(),
         };
 
         // Create a helper thread for acquiring jobserver tokens
@@ -444,6 +525,13 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
             self.run(&unit, job, cx, scope)?;
         }
 
+        // Compilations which are ready to start take priority over running
+        // the test executables which have already been built.
+        while self.has_extra_tokens() && !self.pending_test_runs.is_empty() {
+            let test_run = self.pending_test_runs.remove(0);
+            self.run_test(test_run, scope);
+        }
+
         Ok(())
     }
 
@@ -502,6 +590,13 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
                     .verbose(|c| c.status("Running", &cmd))?;
                 self.timings.unit_start(id, self.active[&id]);
             }
+            Message::Restored(id, description) => {
+                cx.bcx
+                    .config
+                    .shell()
+                    .verbose(|c| c.status("Restored", &description))?;
+                self.timings.unit_start(id, self.active[&id]);
+            }
             Message::BuildPlanMsg(module_name, cmd, filenames) => {
                 plan.update(&module_name, &cmd, &filenames)?;
             }
@@ -516,6 +611,16 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
             Message::FixDiagnostic(msg) => {
                 self.print.print(&msg)?;
             }
+            Message::Finish(id, _, result) if self.test_runs.contains_key(&id) => {
+                self.active.remove(&id).unwrap();
+                self.test_runs.remove(&id);
+                if let Err(e) = result {
+                    return Ok(Some(e));
+                }
+            }
+            Message::TestOutput(id, output) => {
+                self.finish_test_run(id, output, cx)?;
+            }
             Message::Finish(id, artifact, result) => {
                 let unit = match artifact {
                     // If `id` has completely finished we remove it
@@ -547,7 +652,14 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
                 };
                 info!("end ({:?}): {:?}", unit, result);
                 match result {
-                    Ok(()) => self.finish(id, &unit, artifact, cx)?,
+                    Ok(()) => {
+                        self.finish(id, &unit, artifact, cx)?;
+                        if artifact == Artifact::All
+                            && (unit.mode == CompileMode::Test || unit.mode.is_doc_test())
+                        {
+                            self.enqueue_test_runs(&unit, cx, jobserver_helper)?;
+                        }
+                    }
                     Err(e) => {
                         let msg = "The following warnings were emitted during compilation:";
                         self.emit_warnings(Some(msg), &unit, cx)?;
@@ -565,6 +677,24 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
                     }
                 }
             }
+            Message::FutureIncompatReport(id, report) => {
+                let pkg_id = self.active[&id].pkg.package_id();
+                let diagnostics = self.future_incompat_reports.entry(pkg_id).or_default();
+                for item in report {
+                    // The same warning is reported for every unit built from
+                    // a package, such as both `check` and `test` of a lib.
+                    if !diagnostics.contains(&item.diagnostic.rendered) {
+                        diagnostics.push(item.diagnostic.rendered);
+                    }
+                }
+            }
+            Message::LeakedPrivateDependency(id, dep_name) => {
+                let pkg_id = self.active[&id].pkg.package_id();
+                self.leaked_private_dependencies
+                    .entry(pkg_id)
+                    .or_default()
+                    .insert(dep_name);
+            }
             Message::Token(acquired_token) => {
                 let token = acquired_token.chain_err(|| "failed to acquire jobserver token")?;
                 self.tokens.push(token);
@@ -711,6 +841,10 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
             );
             if !cx.bcx.build_config.build_plan {
                 cx.bcx.config.shell().status("Finished", message)?;
+                self.emit_leaked_private_dependencies(cx.bcx.config)?;
+                if cx.bcx.config.cli_unstable().future_incompat_report {
+                    OnDiskReport::new(self.future_incompat_reports).save_and_display(cx.bcx)?;
+                }
             }
             Ok(())
         } else {
@@ -787,7 +921,10 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
 
         if !cx.bcx.build_config.build_plan {
             // Print out some nice progress information.
-            self.note_working_on(cx.bcx.config, unit, fresh)?;
+            self.note_working_on(cx.bcx.config, unit, fresh, job.dirty_reason())?;
+            if let Some(reason) = job.dirty_reason() {
+                self.emit_dirty_reason(cx, unit, reason);
+            }
         }
 
         let doit = move || {
@@ -820,24 +957,6 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
             if state.rmeta_required.get() && sender.result.is_ok() {
                 messages.push(Message::Finish(id, Artifact::Metadata, Ok(())));
             }
-
-            // Use a helper struct with a `Drop` implementation to guarantee
-            // that a `Finish` message is sent even if our job panics. We
-            // shouldn't panic unless there's a bug in Cargo, so we just need
-            // to make sure nothing hangs by accident.
-            struct FinishOnDrop<'a> {
-                messages: &'a Queue<Message>,
-                id: JobId,
-                result: CargoResult<()>,
-            }
-
-            impl Drop for FinishOnDrop<'_> {
-                fn drop(&mut self) {
-                    let msg = mem::replace(&mut self.result, Ok(()));
-                    self.messages
-                        .push(Message::Finish(self.id, Artifact::All, msg));
-                }
-            }
         };
 
         match fresh {
@@ -876,11 +995,37 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
                     writeln!(bcx.config.shell().err())?;
                 }
             }
+            for error in output.errors.iter() {
+                bcx.config.shell().error(error)?;
+            }
         }
 
         Ok(())
     }
 
+    /// Summarizes the packages which expose types of their private
+    /// dependencies in their public API.
+    fn emit_leaked_private_dependencies(&self, config: &Config) -> CargoResult<()> {
+        if self.leaked_private_dependencies.is_empty() {
+            return Ok(());
+        }
+        for (pkg_id, deps) in &self.leaked_private_dependencies {
+            let deps = deps
+                .iter()
+                .map(|dep| format!("`{}`", dep))
+                .collect::<Vec<_>>()
+                .join(", ");
+            config.shell().warn(format!(
+                "`{}` exposes types from private dependencies in its public API: {}",
+                pkg_id, deps
+            ))?;
+        }
+        config.shell().note(
+            "dependencies which are meant to be part of the public API \
+             can be marked with `public = true`",
+        )
+    }
+
     fn finish(
         &mut self,
         id: JobId,
@@ -899,6 +1044,158 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
         Ok(())
     }
 
+    /// Queues up the test executables of a unit which has just been built,
+    /// or the doc tests of a unit whose dependencies have been built, if
+    /// they are run as part of the build.
+    fn enqueue_test_runs(
+        &mut self,
+        unit: &Unit<'a>,
+        cx: &Context<'a, '_>,
+        jobserver_helper: &HelperThread,
+    ) -> CargoResult<()> {
+        let runner = match &self.test_runner {
+            Some(runner) => runner.clone(),
+            None => return Ok(()),
+        };
+        let mut runs = Vec::new();
+        if unit.mode.is_doc_test() && runner.doc_tests {
+            let args = runner.args.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            let mut cmd = cx.doctest_process(unit, &args)?;
+            // `rustdoc` compiles the doc tests with `rustc`, which can use
+            // the tokens of our jobserver.
+            cmd.inherit_jobserver(&cx.jobserver);
+            runs.push((None, cmd));
+        } else if unit.mode == CompileMode::Test && runner.executables {
+            for (exe, mut cmd) in cx.test_processes(unit)? {
+                cmd.args(&runner.args);
+                if unit.target.harness() && cx.bcx.config.shell().verbosity() == Verbosity::Quiet {
+                    cmd.arg("--quiet");
+                }
+                runs.push((Some(exe), cmd));
+            }
+        }
+        for (exe, cmd) in runs {
+            self.pending_test_runs.push(TestRun {
+                unit: *unit,
+                exe,
+                cmd,
+            });
+            if self.active.len() + self.pending_queue.len() + self.pending_test_runs.len() > 1 {
+                jobserver_helper.request_token();
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts running a test executable, unless a test has already failed
+    /// and we're failing fast.
+    fn run_test(&mut self, test_run: TestRun<'a>, scope: &Scope<'_>) {
+        if self.test_runner.as_ref().map_or(false, |r| r.is_stopped()) {
+            return;
+        }
+        let id = JobId(self.next_id);
+        self.next_id = self.next_id.checked_add(1).unwrap();
+
+        info!("start test {}: {}", id, test_run.cmd);
+
+        assert!(self.active.insert(id, test_run.unit).is_none());
+        let mut cmd = test_run.cmd.build_command();
+        self.test_runs.insert(id, test_run);
+
+        let messages = self.messages.clone();
+        scope.spawn(move |_| {
+            let mut sender = FinishOnDrop {
+                messages: &messages,
+                id,
+                result: Err(format_err!("worker panicked")),
+            };
+            // The output is captured so that it can be printed at once, even
+            // if other tests are running at the same time.
+            let output = cmd.stdin(Stdio::null()).output();
+            messages.push(Message::TestOutput(id, output));
+            sender.result = Ok(());
+        });
+    }
+
+    /// Prints the output of a test executable or of `rustdoc --test` which
+    /// has exited, and records it as a failure if it didn't exit
+    /// successfully.
+    fn finish_test_run(
+        &mut self,
+        id: JobId,
+        output: io::Result<Output>,
+        cx: &Context<'a, '_>,
+    ) -> CargoResult<()> {
+        let test_run = &self.test_runs[&id];
+        let config = cx.bcx.config;
+        match &test_run.exe {
+            Some(exe) => {
+                let exe_display = exe.strip_prefix(config.cwd()).unwrap_or(exe);
+                config
+                    .shell()
+                    .concise(|shell| shell.status("Running", exe_display.display()))?;
+            }
+            None => config
+                .shell()
+                .status("Doc-tests", test_run.unit.target.name())?,
+        }
+        config
+            .shell()
+            .verbose(|shell| shell.status("Running", &test_run.cmd))?;
+
+        let error = match output {
+            Ok(output) => {
+                let mut shell = config.shell();
+                if !output.stdout.is_empty() {
+                    let stdout = String::from_utf8_lossy(&output.stdout);
+                    shell.stdout_println(stdout.trim_end_matches('\n'));
+                }
+                shell.err().write_all(&output.stderr)?;
+                drop(shell);
+                if output.status.success() {
+                    return Ok(());
+                }
+                process_error(
+                    &format!("process didn't exit successfully: {}", test_run.cmd),
+                    Some(output.status),
+                    None,
+                )
+            }
+            Err(e) => {
+                let msg = format!("could not execute process {}: {}", test_run.cmd, e);
+                process_error(&msg, None, None)
+            }
+        };
+        let runner = self.test_runner.as_ref().unwrap();
+        runner.record_failure(TestFailure {
+            doc: test_run.exe.is_none(),
+            kind: test_run.unit.target.kind().clone(),
+            name: test_run.unit.target.name().to_string(),
+            pkg_name: test_run.unit.pkg.name().to_string(),
+            error,
+        });
+        Ok(())
+    }
+
+    /// Emits a JSON message explaining why `unit` needs to be rebuilt.
+    fn emit_dirty_reason(&self, cx: &Context<'a, '_>, unit: &Unit<'a>, reason: &DirtyReason) {
+        let config = cx.bcx.config;
+        if !config.cli_unstable().dirty_reasons
+            || !cx.bcx.build_config.emit_json()
+            || reason.is_fresh_build()
+        {
+            return;
+        }
+        let msg = machine_message::DirtyUnit {
+            package_id: unit.pkg.package_id(),
+            target: unit.target,
+            mode: unit.mode,
+            dirty_reason: reason,
+        }
+        .to_json_string();
+        config.shell().stdout_println(msg);
+    }
+
     // This isn't super trivial because we don't want to print loads and
     // loads of information to the console, but we also want to produce a
     // faithful representation of what's happening. This is somewhat nuanced
@@ -913,6 +1210,7 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
         config: &Config,
         unit: &Unit<'a>,
         fresh: Freshness,
+        dirty_reason: Option<&DirtyReason>,
     ) -> CargoResult<()> {
         if (self.compiled.contains(&unit.pkg.package_id()) && !unit.mode.is_doc())
             || (self.documented.contains(&unit.pkg.package_id()) && unit.mode.is_doc())
@@ -924,6 +1222,16 @@ impl<'a, 'cfg> DrainState<'a, 'cfg> {
             // Any dirty stage which runs at least one command gets printed as
             // being a compiled package.
             Dirty => {
+                if let Some(reason) = dirty_reason {
+                    if config.cli_unstable().dirty_reasons
+                        && !reason.is_fresh_build()
+                        && !unit.mode.is_doc_test()
+                    {
+                        let msg =
+                            format!("{}: {}", unit.pkg.name(), reason.describe(unit.pkg.root()));
+                        config.shell().verbose(|c| c.status("Dirty", &msg))?;
+                    }
+                }
                 if unit.mode.is_doc() {
                     self.documented.insert(unit.pkg.package_id());
                     config.shell().status("Documenting", unit.pkg)?;
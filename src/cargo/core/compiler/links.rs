@@ -1,11 +1,24 @@
 use super::unit_graph::UnitGraph;
-use crate::core::{PackageId, Resolve};
+use super::Unit;
+use crate::core::{InternedString, PackageId, Resolve};
 use crate::util::errors::CargoResult;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Write;
+use std::rc::Rc;
+
+/// The packages linking to each native library, by the `links` value.
+type LinkedLibraries = BTreeMap<InternedString, BTreeSet<PackageId>>;
 
 /// Validate `links` field does not conflict between packages.
-pub fn validate_links(resolve: &Resolve, unit_graph: &UnitGraph<'_>) -> CargoResult<()> {
+///
+/// The native libraries in `allowed_duplicate_links` may be linked to by
+/// multiple packages, as long as no single artifact links to more than one of
+/// them.
+pub fn validate_links(
+    resolve: &Resolve,
+    unit_graph: &UnitGraph<'_>,
+    allowed_duplicate_links: &HashSet<InternedString>,
+) -> CargoResult<()> {
     // NOTE: This is the *old* links validator. Links are usually validated in
     // the resolver. However, the `links` field was added to the index in
     // early 2018 (see https://github.com/rust-lang/cargo/pull/4978). However,
@@ -19,7 +32,15 @@ pub fn validate_links(resolve: &Resolve, unit_graph: &UnitGraph<'_>) -> CargoRes
     let mut units: Vec<_> = unit_graph.keys().collect();
     // Sort primarily to make testing easier.
     units.sort_unstable();
-    for unit in units {
+    let describe_path = |pkgid: PackageId| -> String {
+        let dep_path = resolve.path_to_top(&pkgid);
+        let mut dep_path_desc = format!("package `{}`", dep_path[0]);
+        for dep in dep_path.iter().skip(1) {
+            write!(dep_path_desc, "\n    ... which is depended on by `{}`", dep).unwrap();
+        }
+        dep_path_desc
+    };
+    for unit in units.iter() {
         if !validated.insert(unit.pkg.package_id()) {
             continue;
         }
@@ -27,18 +48,11 @@ pub fn validate_links(resolve: &Resolve, unit_graph: &UnitGraph<'_>) -> CargoRes
             Some(lib) => lib,
             None => continue,
         };
+        if allowed_duplicate_links.contains(lib) {
+            continue;
+        }
         if let Some(&prev) = links.get(lib) {
             let pkg = unit.pkg.package_id();
-
-            let describe_path = |pkgid: PackageId| -> String {
-                let dep_path = resolve.path_to_top(&pkgid);
-                let mut dep_path_desc = format!("package `{}`", dep_path[0]);
-                for dep in dep_path.iter().skip(1) {
-                    write!(dep_path_desc, "\n    ... which is depended on by `{}`", dep).unwrap();
-                }
-                dep_path_desc
-            };
-
             anyhow::bail!(
                 "multiple packages link to native library `{}`, \
                  but a native library can be linked only once\n\
@@ -55,5 +69,67 @@ pub fn validate_links(resolve: &Resolve, unit_graph: &UnitGraph<'_>) -> CargoRes
         }
         links.insert(lib.to_string(), unit.pkg.package_id());
     }
+
+    if allowed_duplicate_links.is_empty() {
+        return Ok(());
+    }
+    let mut linked = HashMap::new();
+    for unit in units {
+        let libs = linked_libraries(unit, unit_graph, allowed_duplicate_links, &mut linked);
+        for (lib, pkgs) in libs.iter() {
+            let mut pkgs = pkgs.iter();
+            if let (Some(&prev), Some(&pkg)) = (pkgs.next(), pkgs.next()) {
+                anyhow::bail!(
+                    "multiple packages link to native library `{}` in the {} target \
+                     of package `{}`, but a native library can be linked only once \
+                     in each artifact\n\
+                     \n\
+                     {}\nlinks to native library `{}`\n\
+                     \n\
+                     {}\nalso links to native library `{}`",
+                    lib,
+                    unit.target.description_named(),
+                    unit.pkg.package_id(),
+                    describe_path(prev),
+                    lib,
+                    describe_path(pkg),
+                    lib
+                )
+            }
+        }
+    }
     Ok(())
 }
+
+/// Returns the packages linking to each of the `allowed_duplicate_links`
+/// which are linked into the artifact of `unit`.
+fn linked_libraries<'a>(
+    unit: &Unit<'a>,
+    unit_graph: &UnitGraph<'a>,
+    allowed_duplicate_links: &HashSet<InternedString>,
+    linked: &mut HashMap<Unit<'a>, Rc<LinkedLibraries>>,
+) -> Rc<LinkedLibraries> {
+    if let Some(libs) = linked.get(unit) {
+        return Rc::clone(libs);
+    }
+    let mut libs = LinkedLibraries::new();
+    if let Some(lib) = unit.pkg.manifest().links() {
+        let lib = InternedString::new(lib);
+        if allowed_duplicate_links.contains(&lib) {
+            libs.entry(lib).or_default().insert(unit.pkg.package_id());
+        }
+    }
+    for dep in &unit_graph[unit] {
+        // Build scripts are separate artifacts, along with their dependencies.
+        if dep.unit.target.is_custom_build() {
+            continue;
+        }
+        let dep_libs = linked_libraries(&dep.unit, unit_graph, allowed_duplicate_links, linked);
+        for (lib, pkgs) in dep_libs.iter() {
+            libs.entry(*lib).or_default().extend(pkgs);
+        }
+    }
+    let libs = Rc::new(libs);
+    linked.insert(*unit, Rc::clone(&libs));
+    libs
+}
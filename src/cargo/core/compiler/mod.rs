@@ -1,17 +1,21 @@
 mod build_config;
 mod build_context;
 mod build_plan;
+mod build_script_cache;
 mod compilation;
 mod compile_kind;
 mod context;
 mod custom_build;
 mod fingerprint;
+pub mod future_incompat;
 mod job;
 mod job_queue;
 mod layout;
 mod links;
 mod output_depinfo;
+mod sandbox;
 pub mod standard_lib;
+mod test_runner;
 mod timings;
 mod unit;
 pub mod unit_dependencies;
@@ -35,15 +39,17 @@ pub use self::compilation::{Compilation, Doctest};
 pub use self::compile_kind::{CompileKind, CompileTarget};
 pub use self::context::{Context, Metadata};
 pub use self::custom_build::{BuildOutput, BuildScriptOutputs, BuildScripts};
+pub use self::fingerprint::DirtyReason;
 pub use self::job::Freshness;
 use self::job::{Job, Work};
 use self::job_queue::{JobQueue, JobState};
 use self::output_depinfo::output_depinfo;
+pub use self::test_runner::{TestFailure, TestRunner};
 use self::unit_graph::UnitDep;
 pub use crate::core::compiler::unit::{Unit, UnitInterner};
 use crate::core::manifest::TargetSourcePath;
 use crate::core::profiles::{Lto, PanicStrategy, Profile};
-use crate::core::{Edition, Feature, InternedString, PackageId, Target};
+use crate::core::{Edition, Feature, InternedString, Package, PackageId, Target};
 use crate::util::errors::{self, CargoResult, CargoResultExt, ProcessError, VerboseError};
 use crate::util::machine_message::Message;
 use crate::util::{self, machine_message, ProcessBuilder};
@@ -121,7 +127,8 @@ fn compile<'a, 'cfg: 'a>(
     let job = if unit.mode.is_run_custom_build() {
         custom_build::prepare(cx, unit)?
     } else if unit.mode.is_doc_test() {
-        // We run these targets later, so this is just a no-op for now.
+        // We run these targets later, or once this no-op job has finished
+        // with `-Z merged-doctests`.
         Job::new(Work::noop(), Freshness::Fresh)
     } else if build_plan {
         Job::new(rustc(cx, unit, &exec.clone())?, Freshness::Dirty)
@@ -136,7 +143,11 @@ fn compile<'a, 'cfg: 'a>(
             };
             work.then(link_targets(cx, unit, false)?)
         } else {
-            let work = if cx.bcx.show_warnings(unit.pkg.package_id()) {
+            // Replay the cached messages, which also contain any
+            // future-incompat report for dependencies.
+            let replay = cx.bcx.show_warnings(unit.pkg.package_id())
+                || cx.bcx.config.cli_unstable().future_incompat_report;
+            let work = if replay {
                 replay_output_cache(
                     unit.pkg.package_id(),
                     unit.target,
@@ -193,6 +204,7 @@ fn rustc<'a, 'cfg>(
     // don't pass the `-l` flags.
     let pass_l_flag = unit.target.is_lib() || !unit.pkg.targets().iter().any(|t| t.is_lib());
     let pass_cdylib_link_args = unit.target.is_cdylib();
+    let feature_check_cfg = feature_check_cfg(unit.pkg);
     let do_rename = unit.target.allows_underscores() && !unit.mode.is_any_test();
     let real_name = unit.target.name().to_string();
     let crate_name = unit.target.crate_name();
@@ -227,6 +239,7 @@ fn rustc<'a, 'cfg>(
         .to_path_buf();
     let fingerprint_dir = cx.files().fingerprint_dir(unit);
     let script_metadata = cx.find_build_script_metadata(*unit);
+    let checksum_freshness = cx.bcx.config.cli_unstable().checksum_freshness;
 
     return Ok(Work::new(move |state| {
         // Only at runtime have we discovered what the extra -L and -l
@@ -246,6 +259,7 @@ fn rustc<'a, 'cfg>(
                     pass_l_flag,
                     pass_cdylib_link_args,
                     current_id,
+                    &feature_check_cfg,
                 )?;
                 add_plugin_deps(&mut rustc, &script_outputs, &build_scripts, &root_output)?;
             }
@@ -317,6 +331,11 @@ fn rustc<'a, 'cfg>(
                 &target_dir,
                 // Do not track source files in the fingerprint for registry dependencies.
                 current_id.source_id().is_path(),
+                if checksum_freshness {
+                    Some(timestamp)
+                } else {
+                    None
+                },
             )
             .chain_err(|| {
                 internal(format!(
@@ -340,6 +359,7 @@ fn rustc<'a, 'cfg>(
         pass_l_flag: bool,
         pass_cdylib_link_args: bool,
         current_id: PackageId,
+        feature_check_cfg: &str,
     ) -> CargoResult<()> {
         for key in build_scripts.to_link.iter() {
             let output = build_script_outputs.get(key.0, key.1).ok_or_else(|| {
@@ -355,6 +375,14 @@ fn rustc<'a, 'cfg>(
                 for cfg in &output.cfgs {
                     rustc.arg("--cfg").arg(cfg);
                 }
+                // Declaring any expected cfg makes rustc check all of them,
+                // so the features need to be declared as well.
+                if !output.check_cfgs.is_empty() {
+                    rustc.arg("--check-cfg").arg(feature_check_cfg);
+                }
+                for check_cfg in &output.check_cfgs {
+                    rustc.arg("--check-cfg").arg(check_cfg);
+                }
                 if pass_l_flag {
                     for name in output.library_links.iter() {
                         rustc.arg("-l").arg(name);
@@ -694,10 +722,35 @@ fn add_path_args(bcx: &BuildContext<'_, '_>, unit: &Unit<'_>, cmd: &mut ProcessB
     cmd.cwd(cwd);
 }
 
+/// Returns the `--check-cfg` flag declaring the features of a package, which
+/// are its explicit features and its optional dependencies.
+fn feature_check_cfg(pkg: &Package) -> String {
+    let mut features = pkg
+        .summary()
+        .features()
+        .keys()
+        .map(|f| f.to_string())
+        .collect::<Vec<_>>();
+    features.extend(
+        pkg.dependencies()
+            .iter()
+            .filter(|d| d.is_optional())
+            .map(|d| d.name_in_toml().to_string()),
+    );
+    features.sort();
+    features.dedup();
+    let values = features
+        .iter()
+        .map(|f| format!("\"{}\"", f))
+        .collect::<Vec<_>>();
+    format!("cfg(feature, values({}))", values.join(", "))
+}
+
 fn add_cap_lints(bcx: &BuildContext<'_, '_>, unit: &Unit<'_>, cmd: &mut ProcessBuilder) {
     // If this is an upstream dep we don't want warnings from, turn off all
-    // lints.
-    if !bcx.show_warnings(unit.pkg.package_id()) {
+    // lints. The same goes for a binary compiled as a library for its doc
+    // tests, its warnings are already reported when building the binary.
+    if !bcx.show_warnings(unit.pkg.package_id()) || unit.target.is_bin_doctest_lib() {
         cmd.arg("--cap-lints").arg("allow");
 
     // If this is an upstream dep but we *do* want warnings, make sure that they
@@ -733,6 +786,11 @@ fn add_error_format_and_color(
         }
         _ => {}
     }
+    if cx.bcx.config.cli_unstable().future_incompat_report {
+        // Ask rustc to report lints which will become errors in the future,
+        // even if they're hidden by `--cap-lints`.
+        json.push_str(",future-incompat");
+    }
     cmd.arg(json);
     Ok(())
 }
@@ -1157,6 +1215,33 @@ fn on_stderr_line(
     Ok(())
 }
 
+/// Returns the name of the dependency if `message` is a diagnostic of the
+/// `exported_private_dependencies` lint.
+fn leaked_private_dependency(message: &str) -> Option<String> {
+    #[derive(serde::Deserialize)]
+    struct DiagnosticCode {
+        code: String,
+    }
+    #[derive(serde::Deserialize)]
+    struct Diagnostic {
+        message: String,
+        code: Option<DiagnosticCode>,
+    }
+    let diagnostic = serde_json::from_str::<Diagnostic>(message).ok()?;
+    if diagnostic.code?.code != "exported_private_dependencies" {
+        return None;
+    }
+    // For example "type `dep::Foo` from private dependency 'dep' in public
+    // interface".
+    let name = diagnostic
+        .message
+        .split("private dependency '")
+        .nth(1)?
+        .split('\'')
+        .next()?;
+    Some(name.to_string())
+}
+
 /// Returns true if the line should be cached.
 fn on_stderr_line_inner(
     state: &JobState<'_>,
@@ -1188,6 +1273,21 @@ fn on_stderr_line_inner(
         }
     };
 
+    // Future-incompat reports are collected for a summary at the end of the
+    // build instead of being displayed as they come in.
+    if let Ok(report) =
+        serde_json::from_str::<future_incompat::FutureIncompatReport>(compiler_message.get())
+    {
+        state.future_incompat_report(report.future_incompat_report);
+        return Ok(true);
+    }
+
+    // Private dependencies exposed in the public API are summarized at the end
+    // of the build, in addition to the diagnostic itself.
+    if let Some(dep_name) = leaked_private_dependency(compiler_message.get()) {
+        state.leaked_private_dependency(dep_name);
+    }
+
     // Depending on what we're emitting from Cargo itself, we figure out what to
     // do with this JSON message.
     match options.format {
@@ -0,0 +1,629 @@
+//! Sandboxing of build scripts with `-Z sandbox-build-scripts`.
+//!
+//! On Linux a build script runs in new user, mount, PID and network
+//! namespaces. Its root directory is an empty, read-only tmpfs on which only
+//! what it may access is bind mounted: the system directories and the
+//! toolchain needed to run programs, the source of its package, the build
+//! script itself and the `OUT_DIR` of the build scripts of its `links`
+//! dependencies, all read-only, and its own `OUT_DIR`, writable. It also gets
+//! a few devices, a `/proc` of its own and an empty `/tmp`, which are
+//! discarded when it exits. The `[sandbox.allow.<package>]` tables of the
+//! configuration give a package more paths to read or write, or access to the
+//! network.
+//!
+//! The namespaces are set up between `fork` and `exec`, where only system
+//! calls on the data prepared beforehand are made. Elsewhere, or where the
+//! kernel doesn't let unprivileged users create namespaces, build scripts run
+//! without a sandbox, with a warning.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use crate::core::compiler::{CompileKind, Context, Unit};
+use crate::util::config::{OptValue, StringList};
+use crate::util::{paths, CargoResult};
+
+/// The system directories needed to run programs, which are readable in the
+/// sandbox.
+const SYSTEM_DIRS: &[&str] = &[
+    "/bin", "/etc", "/lib", "/lib32", "/lib64", "/libx32", "/sbin", "/usr",
+];
+
+lazy_static::lazy_static! {
+    /// Whether build scripts can be sandboxed, which is checked once.
+    static ref SUPPORTED: Mutex<Option<Result<(), String>>> = Mutex::new(None);
+}
+
+/// The paths a build script can access, and whether it can access the
+/// network.
+pub struct Sandbox {
+    /// The paths visible in the sandbox, mapped to whether they are writable.
+    /// Those which don't exist when the build script runs are skipped.
+    paths: BTreeMap<PathBuf, bool>,
+    /// Whether the build script can access the network.
+    network: bool,
+    /// The working directory of the build script.
+    cwd: PathBuf,
+    /// An empty directory on which the root of the sandbox is mounted.
+    base: PathBuf,
+}
+
+impl Sandbox {
+    /// Returns the sandbox to run the build script of `unit` in, or `None`
+    /// with a warning if build scripts can't be sandboxed here.
+    pub fn for_build_script<'a>(
+        cx: &Context<'a, '_>,
+        unit: &Unit<'a>,
+        script_dir: &Path,
+    ) -> CargoResult<Option<Sandbox>> {
+        let bcx = cx.bcx;
+        let config = bcx.config;
+        let base = cx.files().build_script_run_dir(unit).join("sandbox");
+        paths::create_dir_all(&base)?;
+        let supported = {
+            let mut supported = SUPPORTED.lock().unwrap();
+            match &*supported {
+                Some(result) => result.clone().map_err(|_| None),
+                None => {
+                    let result = sys::probe(&base);
+                    *supported = Some(result.clone());
+                    result.map_err(Some)
+                }
+            }
+        };
+        match supported {
+            Ok(()) => {}
+            Err(Some(reason)) => {
+                config.shell().warn(format!(
+                    "build scripts can't be sandboxed, and run without a sandbox: {}",
+                    reason
+                ))?;
+                return Ok(None);
+            }
+            // Only the first build script warns.
+            Err(None) => return Ok(None),
+        }
+
+        let mut sandbox = Sandbox {
+            paths: BTreeMap::new(),
+            network: false,
+            cwd: unit.pkg.root().to_path_buf(),
+            base,
+        };
+        sandbox.add(&bcx.target_data.info(CompileKind::Host).sysroot, false);
+        for program in &[
+            bcx.rustc().path.as_path(),
+            config.rustdoc()?,
+            config.cargo_exe()?,
+        ] {
+            if let Some(dir) = program_dir(program) {
+                sandbox.add(&dir, false);
+            }
+        }
+        if let Ok(rustup_home) = home::rustup_home() {
+            sandbox.add(&rustup_home, false);
+        }
+        sandbox.add(unit.pkg.root(), false);
+        sandbox.add(script_dir, false);
+        for dep in cx.unit_deps(unit) {
+            if dep.unit.mode.is_run_custom_build() {
+                sandbox.add(&cx.files().build_script_out_dir(&dep.unit), false);
+            }
+        }
+        sandbox.add(&cx.files().build_script_out_dir(unit), true);
+
+        let key = format!("sandbox.allow.{}", unit.pkg.name());
+        let network: OptValue<bool> = config.get(&format!("{}.network", key))?;
+        if let Some(network) = network {
+            sandbox.network = network.val;
+        }
+        for &(name, writable) in &[("read", false), ("write", true)] {
+            let paths: OptValue<StringList> = config.get(&format!("{}.{}", key, name))?;
+            if let Some(paths) = paths {
+                let root = paths.definition.root(config);
+                for path in paths.val.as_slice() {
+                    sandbox.add(&root.join(path), writable);
+                }
+            }
+        }
+        Ok(Some(sandbox))
+    }
+
+    /// Makes `path` visible in the sandbox, writable if `writable` is true.
+    fn add(&mut self, path: &Path, writable: bool) {
+        let path = paths::normalize_path(path);
+        *self.paths.entry(path).or_insert(writable) |= writable;
+    }
+
+    /// Sets up `cmd` to run in the sandbox.
+    pub fn apply(&self, cmd: &mut Command) -> CargoResult<()> {
+        // The `TMPDIR` of the user is hidden by the sandbox.
+        cmd.env("TMPDIR", "/tmp");
+        sys::apply(self, cmd)
+    }
+}
+
+/// Returns the directory containing `program`, searching `PATH` if it's a
+/// bare name.
+fn program_dir(program: &Path) -> Option<PathBuf> {
+    if program.components().count() > 1 {
+        return program.parent().map(Path::to_path_buf);
+    }
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find(|dir| dir.join(program).is_file())
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::ffi::CString;
+    use std::fs;
+    use std::io;
+    use std::mem;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::process::CommandExt;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::ptr;
+    use std::sync::Arc;
+
+    use anyhow::Context as _;
+    use libc::{c_char, c_int, c_ulong};
+
+    use super::{Sandbox, SYSTEM_DIRS};
+    use crate::util::CargoResult;
+
+    /// The devices which are available in the sandbox.
+    const DEVICES: &[&str] = &["full", "null", "random", "tty", "urandom", "zero"];
+
+    /// A step of setting up the file system of the sandbox.
+    enum Step {
+        /// Creates a directory, unless it exists.
+        Mkdir(CString),
+        /// Creates an empty file to mount a file on.
+        Touch(CString),
+        /// Creates a symbolic link at the second path pointing to the first.
+        Symlink(CString, CString),
+        /// Mounts the first path on the second.
+        Bind(CString, CString),
+        /// Makes a mount point read-only, with the flags of the mount which
+        /// must be kept.
+        ReadOnly(CString, c_ulong),
+        /// Mounts a tmpfs with the given options.
+        Tmpfs(CString, &'static [u8]),
+        /// Mounts a new procfs.
+        Proc(CString),
+    }
+
+    /// Everything needed to enter a sandbox, prepared before `fork`.
+    struct Plan {
+        /// The namespaces to create.
+        namespaces: c_int,
+        /// The contents of `/proc/self/uid_map`.
+        uid_map: Vec<u8>,
+        /// The contents of `/proc/self/gid_map`.
+        gid_map: Vec<u8>,
+        /// The directory on which the root of the sandbox is mounted.
+        base: CString,
+        /// The steps of setting up the root of the sandbox, in `/newroot`.
+        steps: Vec<Step>,
+        /// The working directory in the sandbox.
+        cwd: CString,
+    }
+
+    /// Checks that namespaces can be used by entering an empty sandbox.
+    pub fn probe(base: &Path) -> Result<(), String> {
+        let plan = Plan::new(base, Path::new("/"), true, Vec::new()).map_err(|e| e.to_string())?;
+        unsafe {
+            match libc::fork() {
+                -1 => Err(io::Error::last_os_error().to_string()),
+                0 => {
+                    let code = match enter(&plan) {
+                        Ok(()) => 0,
+                        Err(e) => e.raw_os_error().unwrap_or(libc::EINVAL),
+                    };
+                    libc::_exit(code)
+                }
+                pid => {
+                    let mut status = 0;
+                    if libc::waitpid(pid, &mut status, 0) == -1 {
+                        return Err(io::Error::last_os_error().to_string());
+                    }
+                    match libc::WIFEXITED(status) {
+                        true if libc::WEXITSTATUS(status) == 0 => Ok(()),
+                        true => {
+                            Err(io::Error::from_raw_os_error(libc::WEXITSTATUS(status)).to_string())
+                        }
+                        false => Err(format!("killed by signal {}", libc::WTERMSIG(status))),
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn apply(sandbox: &Sandbox, cmd: &mut Command) -> CargoResult<()> {
+        let mut paths: Vec<(&PathBuf, bool)> = sandbox
+            .paths
+            .iter()
+            .filter(|(path, _)| path.exists())
+            .map(|(path, writable)| (path, *writable))
+            .collect();
+        let system_dirs: Vec<PathBuf> = SYSTEM_DIRS.iter().map(PathBuf::from).collect();
+        let mut steps = Vec::new();
+        for dir in &system_dirs {
+            match fs::read_link(dir) {
+                // Keep the links of a merged `/usr`, like `/bin -> usr/bin`.
+                Ok(link) if link.is_relative() => {
+                    steps.push(Step::Symlink(cstring(&link)?, cstring(&new_path(dir))?));
+                }
+                _ if dir.exists() => paths.push((dir, false)),
+                _ => {}
+            }
+        }
+        // Parents are mounted before their children, which may be mounted
+        // differently.
+        paths.sort_by_key(|(path, _)| path.components().count());
+
+        let mount_points = mount_points();
+        let mut mounted: Vec<(&Path, bool)> = Vec::new();
+        for (path, writable) in paths {
+            let parent = mounted.iter().rev().find(|(m, _)| path.starts_with(m));
+            if parent.map_or(false, |&(_, w)| w == writable) {
+                continue;
+            }
+            mounted.push((path, writable));
+            let target = new_path(path);
+            mkdirs(&mut steps, &target, path.is_dir())?;
+            steps.push(Step::Bind(cstring(&old_path(path))?, cstring(&target)?));
+            if writable {
+                continue;
+            }
+            steps.push(Step::ReadOnly(cstring(&target)?, mount_flags(path)?));
+            // Mounts below `path` are mounted with it, but not made read-only.
+            let canonical = fs::canonicalize(path)
+                .with_context(|| format!("failed to canonicalize `{}`", path.display()))?;
+            for mount_point in &mount_points {
+                if let Ok(suffix) = mount_point.strip_prefix(&canonical) {
+                    if suffix.as_os_str().is_empty() {
+                        continue;
+                    }
+                    steps.push(Step::ReadOnly(
+                        cstring(&target.join(suffix))?,
+                        mount_flags(mount_point)?,
+                    ));
+                }
+            }
+        }
+
+        let plan = Arc::new(Plan::new(
+            &sandbox.base,
+            &sandbox.cwd,
+            sandbox.network,
+            steps,
+        )?);
+        unsafe {
+            cmd.pre_exec(move || enter(&plan));
+        }
+        Ok(())
+    }
+
+    impl Plan {
+        fn new(base: &Path, cwd: &Path, network: bool, user_steps: Vec<Step>) -> CargoResult<Plan> {
+            let mut namespaces = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWPID;
+            if !network {
+                namespaces |= libc::CLONE_NEWNET;
+            }
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+
+            // `/dev`, `/proc` and `/tmp` are mounted first, so that paths
+            // below them can be mounted on top.
+            let dev = new_path(Path::new("/dev"));
+            let mut steps = vec![
+                Step::Mkdir(cstring(&dev)?),
+                Step::Tmpfs(cstring(&dev)?, b"mode=0755\0"),
+                Step::Mkdir(cstring(&dev.join("shm"))?),
+            ];
+            for device in DEVICES {
+                let source = Path::new("/dev").join(device);
+                if source.exists() {
+                    steps.push(Step::Touch(cstring(&dev.join(device))?));
+                    steps.push(Step::Bind(
+                        cstring(&old_path(&source))?,
+                        cstring(&dev.join(device))?,
+                    ));
+                }
+            }
+            for &(link, target) in &[
+                ("fd", "/proc/self/fd"),
+                ("stdin", "/proc/self/fd/0"),
+                ("stdout", "/proc/self/fd/1"),
+                ("stderr", "/proc/self/fd/2"),
+            ] {
+                steps.push(Step::Symlink(
+                    cstring(Path::new(target))?,
+                    cstring(&dev.join(link))?,
+                ));
+            }
+            let proc = new_path(Path::new("/proc"));
+            steps.push(Step::Mkdir(cstring(&proc)?));
+            steps.push(Step::Proc(cstring(&proc)?));
+            let tmp = new_path(Path::new("/tmp"));
+            steps.push(Step::Mkdir(cstring(&tmp)?));
+            steps.push(Step::Tmpfs(cstring(&tmp)?, b"mode=1777\0"));
+            steps.extend(user_steps);
+
+            Ok(Plan {
+                namespaces,
+                uid_map: format!("{} {} 1", uid, uid).into_bytes(),
+                gid_map: format!("{} {} 1", gid, gid).into_bytes(),
+                base: cstring(base)?,
+                steps,
+                cwd: cstring(cwd)?,
+            })
+        }
+    }
+
+    /// Enters the sandbox described by `plan`.
+    ///
+    /// This runs in a child process between `fork` and `exec`, so it must not
+    /// allocate. It returns in the process which goes on to run the build
+    /// script, while the processes it forks on the way wait for it and exit
+    /// with its status.
+    fn enter(plan: &Plan) -> io::Result<()> {
+        unsafe {
+            check(libc::unshare(plan.namespaces))?;
+            write_file(b"/proc/self/setgroups\0", b"deny")?;
+            write_file(b"/proc/self/uid_map\0", &plan.uid_map)?;
+            write_file(b"/proc/self/gid_map\0", &plan.gid_map)?;
+            // The new PID namespace only applies to the children of this
+            // process, and the new procfs must be mounted from within it.
+            fork_and_wait()?;
+
+            check(libc::mount(
+                ptr::null(),
+                c(b"/\0"),
+                ptr::null(),
+                libc::MS_REC | libc::MS_PRIVATE,
+                ptr::null(),
+            ))?;
+            mount_tmpfs(plan.base.as_ptr(), b"mode=0755\0")?;
+            check(libc::chdir(plan.base.as_ptr()))?;
+            check(libc::mkdir(c(b"newroot\0"), 0o755))?;
+            check(libc::mkdir(c(b"oldroot\0"), 0o755))?;
+            // The old root stays reachable at `/oldroot` to mount from, and
+            // doesn't contain the tmpfs mounted on `base` anymore.
+            check(libc::syscall(libc::SYS_pivot_root, c(b".\0"), c(b"oldroot\0")) as c_int)?;
+            check(libc::chdir(c(b"/\0")))?;
+            mount_tmpfs(c(b"/newroot\0"), b"mode=0755\0")?;
+
+            for step in &plan.steps {
+                match step {
+                    Step::Mkdir(path) => {
+                        if libc::mkdir(path.as_ptr(), 0o755) == -1
+                            && io::Error::last_os_error().raw_os_error() != Some(libc::EEXIST)
+                        {
+                            return Err(io::Error::last_os_error());
+                        }
+                    }
+                    Step::Touch(path) => {
+                        let fd = check(libc::open(
+                            path.as_ptr(),
+                            libc::O_WRONLY | libc::O_CREAT | libc::O_CLOEXEC,
+                            0o644,
+                        ))?;
+                        libc::close(fd);
+                    }
+                    Step::Symlink(target, link) => {
+                        check(libc::symlink(target.as_ptr(), link.as_ptr()))?;
+                    }
+                    Step::Bind(source, target) => {
+                        check(libc::mount(
+                            source.as_ptr(),
+                            target.as_ptr(),
+                            ptr::null(),
+                            libc::MS_BIND | libc::MS_REC,
+                            ptr::null(),
+                        ))?;
+                    }
+                    Step::ReadOnly(path, flags) => remount_read_only(path.as_ptr(), *flags)?,
+                    Step::Tmpfs(path, options) => mount_tmpfs(path.as_ptr(), options)?,
+                    Step::Proc(path) => {
+                        check(libc::mount(
+                            c(b"proc\0"),
+                            path.as_ptr(),
+                            c(b"proc\0"),
+                            libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+                            ptr::null(),
+                        ))?;
+                    }
+                }
+            }
+            remount_read_only(c(b"/newroot\0"), libc::MS_NOSUID | libc::MS_NODEV)?;
+
+            check(libc::umount2(c(b"/oldroot\0"), libc::MNT_DETACH))?;
+            check(libc::chdir(c(b"/newroot\0")))?;
+            check(libc::syscall(libc::SYS_pivot_root, c(b".\0"), c(b".\0")) as c_int)?;
+            check(libc::umount2(c(b".\0"), libc::MNT_DETACH))?;
+            check(libc::chdir(plan.cwd.as_ptr()))?;
+
+            // The init process of a PID namespace ignores the signals it
+            // doesn't handle, so the build script must not be that process.
+            fork_and_wait()?;
+            // Also drop the capabilities the build script would get in the
+            // user namespace if it runs as root.
+            for cap in 0..64 {
+                libc::prctl(libc::PR_CAPBSET_DROP, cap, 0, 0, 0);
+            }
+            check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+        }
+        Ok(())
+    }
+
+    /// Forks, returning in the child, while the parent waits for the child
+    /// and exits with its status.
+    unsafe fn fork_and_wait() -> io::Result<()> {
+        let pid = check(libc::fork())?;
+        if pid == 0 {
+            return Ok(());
+        }
+        // The descriptors the parent inherited, like the pipe through which
+        // `Command::spawn` learns that `exec` succeeded, must not be kept
+        // open while the build script runs.
+        if libc::syscall(libc::SYS_close_range, 3, c_int::MAX, 0) == -1 {
+            for fd in 3..1024 {
+                libc::close(fd);
+            }
+        }
+        let mut status = 0;
+        while libc::waitpid(pid, &mut status, 0) == -1 {
+            if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                libc::_exit(127);
+            }
+        }
+        if libc::WIFEXITED(status) {
+            libc::_exit(libc::WEXITSTATUS(status));
+        }
+        let signal = libc::WTERMSIG(status);
+        libc::signal(signal, libc::SIG_DFL);
+        libc::kill(libc::getpid(), signal);
+        libc::_exit(128 + signal)
+    }
+
+    unsafe fn mount_tmpfs(path: *const c_char, options: &[u8]) -> io::Result<()> {
+        check(libc::mount(
+            c(b"tmpfs\0"),
+            path,
+            c(b"tmpfs\0"),
+            libc::MS_NOSUID | libc::MS_NODEV,
+            options.as_ptr().cast(),
+        ))?;
+        Ok(())
+    }
+
+    unsafe fn remount_read_only(path: *const c_char, flags: c_ulong) -> io::Result<()> {
+        check(libc::mount(
+            ptr::null(),
+            path,
+            ptr::null(),
+            libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY | flags,
+            ptr::null(),
+        ))?;
+        Ok(())
+    }
+
+    unsafe fn write_file(path: &[u8], contents: &[u8]) -> io::Result<()> {
+        let fd = check(libc::open(c(path), libc::O_WRONLY | libc::O_CLOEXEC))?;
+        let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+        libc::close(fd);
+        if written == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn check(ret: c_int) -> io::Result<c_int> {
+        if ret == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+
+    fn c(s: &[u8]) -> *const c_char {
+        s.as_ptr().cast()
+    }
+
+    fn cstring(path: &Path) -> CargoResult<CString> {
+        CString::new(path.as_os_str().as_bytes())
+            .with_context(|| format!("invalid path `{}`", path.display()))
+    }
+
+    /// Returns where `path` is mounted from.
+    fn old_path(path: &Path) -> PathBuf {
+        Path::new("/oldroot").join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    /// Returns where `path` is mounted while the sandbox is set up.
+    fn new_path(path: &Path) -> PathBuf {
+        Path::new("/newroot").join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    /// Adds the steps creating the directory `path`, or the directory
+    /// containing the file `path` and the file.
+    fn mkdirs(steps: &mut Vec<Step>, path: &Path, is_dir: bool) -> CargoResult<()> {
+        let dir = if is_dir { path } else { path.parent().unwrap() };
+        let mut ancestors: Vec<&Path> = dir
+            .ancestors()
+            .take_while(|dir| dir.starts_with("/newroot") && *dir != Path::new("/newroot"))
+            .collect();
+        ancestors.reverse();
+        for dir in ancestors {
+            steps.push(Step::Mkdir(cstring(dir)?));
+        }
+        if !is_dir {
+            steps.push(Step::Touch(cstring(path)?));
+        }
+        Ok(())
+    }
+
+    /// Returns the flags of the mount containing `path` which must be kept
+    /// when making it read-only.
+    fn mount_flags(path: &Path) -> CargoResult<c_ulong> {
+        let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+        let ret = unsafe { libc::statvfs(cstring(path)?.as_ptr(), &mut stat) };
+        check(ret).with_context(|| format!("failed to stat `{}`", path.display()))?;
+        let mut flags = 0;
+        for &(st, ms) in &[
+            (libc::ST_NOSUID, libc::MS_NOSUID),
+            (libc::ST_NODEV, libc::MS_NODEV),
+            (libc::ST_NOEXEC, libc::MS_NOEXEC),
+            (libc::ST_NOATIME, libc::MS_NOATIME),
+            (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+            (libc::ST_RELATIME, libc::MS_RELATIME),
+        ] {
+            if stat.f_flag & st != 0 {
+                flags |= ms;
+            }
+        }
+        if stat.f_flag & (libc::ST_NOATIME | libc::ST_RELATIME) == 0 {
+            flags |= libc::MS_STRICTATIME;
+        }
+        Ok(flags)
+    }
+
+    /// Returns the mount points of this process.
+    fn mount_points() -> Vec<PathBuf> {
+        let mountinfo = fs::read_to_string("/proc/self/mountinfo").unwrap_or_default();
+        mountinfo
+            .lines()
+            .filter_map(|line| line.split(' ').nth(4))
+            .map(|mount_point| {
+                PathBuf::from(
+                    mount_point
+                        .replace("\\040", " ")
+                        .replace("\\011", "\t")
+                        .replace("\\012", "\n")
+                        .replace("\\134", "\\"),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::path::Path;
+    use std::process::Command;
+
+    use super::Sandbox;
+    use crate::util::CargoResult;
+
+    pub fn probe(_base: &Path) -> Result<(), String> {
+        Err("sandboxes are only supported on Linux".to_string())
+    }
+
+    pub fn apply(_sandbox: &Sandbox, _cmd: &mut Command) -> CargoResult<()> {
+        Ok(())
+    }
+}
@@ -64,6 +64,7 @@ pub fn resolve_std<'cfg>(
         &Some(members),
         /*default_members*/ &None,
         /*exclude*/ &None,
+        /*allow_duplicate_links*/ &None,
     ));
     let virtual_manifest = crate::core::VirtualManifest::new(
         /*replace*/ Vec::new(),
@@ -0,0 +1,77 @@
+//! Running test executables as jobs of the build which compiles them, with
+//! `-Z parallel-tests`, and doc tests with `-Z merged-doctests`.
+//!
+//! Each test executable is run as soon as it has been linked, while other
+//! units may still be compiling, and uses a jobserver token like any other
+//! job. The same goes for `rustdoc --test` once the library it tests has
+//! been built, so the doc tests of several crates are compiled concurrently.
+//! The output of each process is captured and printed at once when it exits,
+//! so the output of tests running concurrently is not interleaved.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::core::TargetKind;
+use crate::util::ProcessError;
+
+/// The arguments and the results of the tests run during a build.
+#[derive(Debug)]
+pub struct TestRunner {
+    /// The arguments passed to each test executable.
+    pub args: Vec<String>,
+    /// Whether to keep running tests after one has failed.
+    pub no_fail_fast: bool,
+    /// Whether the test executables are run by the build.
+    pub executables: bool,
+    /// Whether the doc tests are run by the build.
+    pub doc_tests: bool,
+    /// Set when a test fails without `no_fail_fast`, so that the tests which
+    /// haven't started yet are skipped.
+    stopped: AtomicBool,
+    failures: Mutex<Vec<TestFailure>>,
+}
+
+/// A test executable or `rustdoc --test` which exited unsuccessfully.
+#[derive(Debug)]
+pub struct TestFailure {
+    /// Whether these are the doc tests of the target.
+    pub doc: bool,
+    pub kind: TargetKind,
+    /// The name of the test target.
+    pub name: String,
+    pub pkg_name: String,
+    pub error: ProcessError,
+}
+
+impl TestRunner {
+    pub fn new(args: &[&str], no_fail_fast: bool) -> TestRunner {
+        TestRunner {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            no_fail_fast,
+            executables: false,
+            doc_tests: false,
+            stopped: AtomicBool::new(false),
+            failures: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns whether the tests which haven't started yet should be skipped.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    pub fn record_failure(&self, failure: TestFailure) {
+        if !self.no_fail_fast {
+            self.stopped.store(true, Ordering::SeqCst);
+        }
+        self.failures.lock().unwrap().push(failure);
+    }
+
+    /// Takes the failures recorded so far, ordered by package and target
+    /// rather than by when the tests finished.
+    pub fn take_failures(&self) -> Vec<TestFailure> {
+        let mut failures = std::mem::take(&mut *self.failures.lock().unwrap());
+        failures.sort_by(|a, b| (&a.pkg_name, &a.name).cmp(&(&b.pkg_name, &b.name)));
+        failures
+    }
+}
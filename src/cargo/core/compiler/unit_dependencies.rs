@@ -72,7 +72,11 @@ pub fn build_unit_dependencies<'a, 'cfg>(
     let std_unit_deps = calc_deps_of_std(&mut state, std_roots)?;
 
     deps_of_roots(roots, &mut state)?;
-    super::links::validate_links(state.resolve(), &state.unit_dependencies)?;
+    super::links::validate_links(
+        state.resolve(),
+        &state.unit_dependencies,
+        &bcx.ws.allowed_duplicate_links(),
+    )?;
     // Hopefully there aren't any links conflicts with the standard library?
 
     if let Some(std_unit_deps) = std_unit_deps {
@@ -315,11 +319,30 @@ fn compute_deps<'a, 'cfg>(
     // the library of the same package. The call to `resolve.deps` above
     // didn't include `pkg` in the return values, so we need to special case
     // it here and see if we need to push `(pkg, pkg_lib_target)`.
-    if unit.target.is_lib() && unit.mode != CompileMode::Doctest {
+    if unit.target.is_lib()
+        && !unit.target.is_bin_doctest_lib()
+        && unit.mode != CompileMode::Doctest
+    {
         return Ok(ret);
     }
     ret.extend(maybe_lib(unit, state, unit_for)?);
 
+    // The doc tests of a binary link to the binary compiled as a library.
+    if unit.target.is_bin() && unit.mode == CompileMode::Doctest {
+        if let Some(t) = unit.pkg.manifest().bin_doctest_lib(unit.target) {
+            let unit_dep = new_unit_dep(
+                state,
+                unit,
+                unit.pkg,
+                t,
+                unit_for,
+                unit.kind.for_target(t),
+                CompileMode::Build,
+            )?;
+            ret.push(unit_dep);
+        }
+    }
+
     // If any integration tests/benches are being run, make sure that
     // binaries are built as well.
     if !unit.mode.is_check()
@@ -608,9 +631,11 @@ fn new_unit_dep_with_profile<'a>(
         pkg.package_id(),
         target,
     )?);
-    let public = state
-        .resolve()
-        .is_public_dep(parent.pkg.package_id(), pkg.package_id());
+    // The lib of a package is never private to its other targets.
+    let public = parent.pkg.package_id() == pkg.package_id()
+        || state
+            .resolve()
+            .is_public_dep(parent.pkg.package_id(), pkg.package_id());
     let features_for = unit_for.map_to_features_for();
     let features = state.activated_features(pkg.package_id(), features_for);
     let unit = state
@@ -206,6 +206,9 @@ features! {
 
         // Allow to specify profiles other than 'dev', 'release', 'test', etc.
         [unstable] named_profiles: bool,
+
+        // Allow multiple packages to link to the same native library.
+        [unstable] allow_duplicate_links: bool,
     }
 }
 
@@ -343,6 +346,21 @@ pub struct CliUnstable {
     pub jobserver_per_rustc: bool,
     pub features: Option<Vec<String>>,
     pub crate_versions: bool,
+    pub signed_tokens: bool,
+    pub future_incompat_report: bool,
+    pub dirty_reasons: bool,
+    pub checksum_freshness: bool,
+    pub patch_in_config: bool,
+    pub config_cfg: bool,
+    pub direct_minimal_versions: bool,
+    pub lockfile_diff: bool,
+    pub parallel_tests: bool,
+    pub doctest_bins: bool,
+    pub merged_doctests: bool,
+    pub build_script_directives: bool,
+    pub sandbox_build_scripts: bool,
+    pub build_script_cache: bool,
+    pub run_multiple: bool,
 }
 
 impl CliUnstable {
@@ -420,6 +438,21 @@ impl CliUnstable {
             "jobserver-per-rustc" => self.jobserver_per_rustc = parse_empty(k, v)?,
             "features" => self.features = Some(parse_features(v)),
             "crate-versions" => self.crate_versions = parse_empty(k, v)?,
+            "signed-tokens" => self.signed_tokens = parse_empty(k, v)?,
+            "future-incompat-report" => self.future_incompat_report = parse_empty(k, v)?,
+            "dirty-reasons" => self.dirty_reasons = parse_empty(k, v)?,
+            "checksum-freshness" => self.checksum_freshness = parse_empty(k, v)?,
+            "patch-in-config" => self.patch_in_config = parse_empty(k, v)?,
+            "config-cfg" => self.config_cfg = parse_empty(k, v)?,
+            "direct-minimal-versions" => self.direct_minimal_versions = parse_empty(k, v)?,
+            "lockfile-diff" => self.lockfile_diff = parse_empty(k, v)?,
+            "parallel-tests" => self.parallel_tests = parse_empty(k, v)?,
+            "doctest-bins" => self.doctest_bins = parse_empty(k, v)?,
+            "merged-doctests" => self.merged_doctests = parse_empty(k, v)?,
+            "build-script-directives" => self.build_script_directives = parse_empty(k, v)?,
+            "sandbox-build-scripts" => self.sandbox_build_scripts = parse_empty(k, v)?,
+            "build-script-cache" => self.build_script_cache = parse_empty(k, v)?,
+            "run-multiple" => self.run_multiple = parse_empty(k, v)?,
             _ => bail!("unknown `-Z` flag specified: {}", k),
         }
 
@@ -44,6 +44,11 @@ pub struct Manifest {
     im_a_teapot: Option<bool>,
     default_run: Option<String>,
     metabuild: Option<Vec<String>>,
+    /// The handwritten build script which runs after the `metabuild` packages.
+    metabuild_script: Option<PathBuf>,
+    /// The libraries built from the binaries with `doctest = true`, for
+    /// `-Z doctest-bins`. They're not part of `targets`.
+    bin_doctest_libs: Vec<Target>,
 }
 
 /// When parsing `Cargo.toml`, some warnings should silenced
@@ -241,6 +246,9 @@ pub struct Target {
     for_host: bool,
     proc_macro: bool,
     edition: Edition,
+    // Whether this is a library built from the source of a binary, so that
+    // the doc tests of the binary can link to it.
+    bin_doctest_lib: bool,
 }
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -384,6 +392,7 @@ compact_debug! {
                 for_host
                 proc_macro
                 edition
+                bin_doctest_lib
             )]
         }
     }
@@ -410,7 +419,13 @@ impl Manifest {
         default_run: Option<String>,
         original: Rc<TomlManifest>,
         metabuild: Option<Vec<String>>,
+        metabuild_script: Option<PathBuf>,
     ) -> Manifest {
+        let bin_doctest_libs = targets
+            .iter()
+            .filter(|t| t.is_bin() && t.doctested())
+            .map(Target::bin_doctest_lib_target)
+            .collect();
         Manifest {
             summary,
             targets,
@@ -432,6 +447,8 @@ impl Manifest {
             default_run,
             publish_lockfile,
             metabuild,
+            metabuild_script,
+            bin_doctest_libs,
         }
     }
 
@@ -462,6 +479,11 @@ impl Manifest {
     pub fn targets(&self) -> &[Target] {
         &self.targets
     }
+    /// Returns the library built from the source of the given binary for its
+    /// doc tests, if the binary has `doctest = true`.
+    pub fn bin_doctest_lib(&self, bin: &Target) -> Option<&Target> {
+        self.bin_doctest_libs.iter().find(|t| t.name == bin.name)
+    }
     pub fn targets_mut(&mut self) -> &mut [Target] {
         &mut self.targets
     }
@@ -548,6 +570,10 @@ impl Manifest {
         self.metabuild.as_ref()
     }
 
+    pub fn metabuild_script(&self) -> Option<&Path> {
+        self.metabuild_script.as_deref()
+    }
+
     pub fn metabuild_path(&self, target_dir: Filesystem) -> PathBuf {
         let hash = short_hash(&self.package_id());
         target_dir
@@ -619,6 +645,7 @@ impl Target {
             edition,
             tested: true,
             benched: true,
+            bin_doctest_lib: false,
         }
     }
 
@@ -656,6 +683,20 @@ impl Target {
         }
     }
 
+    /// Builds the library which is compiled from the source of a binary, so
+    /// that its doc tests can use the items of the binary.
+    fn bin_doctest_lib_target(bin: &Target) -> Target {
+        Target {
+            kind: TargetKind::Lib(vec![LibKind::Lib]),
+            tested: false,
+            benched: false,
+            doc: false,
+            doctest: false,
+            bin_doctest_lib: true,
+            ..bin.clone()
+        }
+    }
+
     /// Builds a `Target` corresponding to the `build = "build.rs"` entry.
     pub fn custom_build_target(name: &str, src_path: PathBuf, edition: Edition) -> Target {
         Target {
@@ -867,6 +908,11 @@ impl Target {
     pub fn is_custom_build(&self) -> bool {
         self.kind == TargetKind::CustomBuild
     }
+    /// Whether this is the library compiled from the source of a binary for
+    /// its doc tests, see `Manifest::bin_doctest_lib`.
+    pub fn is_bin_doctest_lib(&self) -> bool {
+        self.bin_doctest_lib
+    }
 
     /// Returns the arguments suitable for `--crate-type` to pass to rustc.
     pub fn rustc_crate_types(&self) -> Vec<&str> {
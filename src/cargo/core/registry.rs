@@ -6,8 +6,9 @@ use semver::VersionReq;
 use url::Url;
 
 use crate::core::PackageSet;
-use crate::core::{Dependency, PackageId, Source, SourceId, SourceMap, Summary};
+use crate::core::{Dependency, Deprecation, PackageId, Source, SourceId, SourceMap, Summary};
 use crate::sources::config::SourceConfigMap;
+use crate::util::config::Definition;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::{profile, CanonicalUrl, Config};
 
@@ -190,6 +191,29 @@ impl<'cfg> PackageRegistry<'cfg> {
         self.add_source(source, Kind::Override);
     }
 
+    /// Returns the deprecation advisory published for `pkg` by its source,
+    /// if any.
+    pub fn deprecation(&mut self, pkg: PackageId) -> CargoResult<Option<Deprecation>> {
+        match self.sources.get_mut(pkg.source_id()) {
+            Some(source) => source.deprecation(pkg),
+            None => Ok(None),
+        }
+    }
+
+    /// Queries all the versions of a package published by its source, even
+    /// those which were never downloaded with `--offline`.
+    ///
+    /// Unlike `Registry::query`, patches, overrides and locked versions are
+    /// not taken into account.
+    pub fn query_published(&mut self, dep: &Dependency) -> CargoResult<Vec<Summary>> {
+        self.ensure_loaded(dep.source_id(), Kind::Normal)?;
+        let mut ret = Vec::new();
+        if let Some(source) = self.sources.get_mut(dep.source_id()) {
+            source.query_published(dep, &mut |summary| ret.push(summary))?;
+        }
+        Ok(ret)
+    }
+
     pub fn add_to_yanked_whitelist(&mut self, iter: impl Iterator<Item = PackageId>) {
         let pkgs = iter.collect::<Vec<_>>();
         for (_, source) in self.sources.sources_mut() {
@@ -219,7 +243,8 @@ impl<'cfg> PackageRegistry<'cfg> {
     /// with the given list of dependencies. The `url` specified is the URL of
     /// the source to patch (for example this is `crates-io` in the manifest).
     /// The `deps` is an array of all the entries in the `[patch]` section of
-    /// the manifest.
+    /// the manifest, along with where they were defined in the configuration
+    /// if they come from there rather than from the manifest.
     ///
     /// Here the `deps` will be resolved to a precise version and stored
     /// internally for future calls to `query` below. It's expected that `deps`
@@ -229,7 +254,11 @@ impl<'cfg> PackageRegistry<'cfg> {
     /// Note that the patch list specified here *will not* be available to
     /// `query` until `lock_patches` is called below, which should be called
     /// once all patches have been added.
-    pub fn patch(&mut self, url: &Url, deps: &[Dependency]) -> CargoResult<()> {
+    pub fn patch(
+        &mut self,
+        url: &Url,
+        deps: &[(Dependency, Option<Definition>)],
+    ) -> CargoResult<()> {
         let canonical = CanonicalUrl::new(url)?;
 
         // First up we need to actually resolve each `deps` specification to
@@ -243,7 +272,7 @@ impl<'cfg> PackageRegistry<'cfg> {
         // of summaries which should be the same length as `deps` above.
         let unlocked_summaries = deps
             .iter()
-            .map(|dep| {
+            .map(|(dep, definition)| {
                 debug!(
                     "registering a patch for `{}` with `{}`",
                     url,
@@ -268,29 +297,36 @@ impl<'cfg> PackageRegistry<'cfg> {
                     .query_vec(dep)?
                     .into_iter();
 
+                let defined_in = match definition {
+                    Some(definition) => format!(" defined in `{}`", definition),
+                    None => String::new(),
+                };
                 let summary = match summaries.next() {
                     Some(summary) => summary,
                     None => anyhow::bail!(
-                        "patch for `{}` in `{}` did not resolve to any crates. If this is \
+                        "patch for `{}` in `{}`{} did not resolve to any crates. If this is \
                          unexpected, you may wish to consult: \
                          https://github.com/rust-lang/cargo/issues/4678",
                         dep.package_name(),
-                        url
+                        url,
+                        defined_in
                     ),
                 };
                 if summaries.next().is_some() {
                     anyhow::bail!(
-                        "patch for `{}` in `{}` resolved to more than one candidate",
+                        "patch for `{}` in `{}`{} resolved to more than one candidate",
                         dep.package_name(),
-                        url
+                        url,
+                        defined_in
                     )
                 }
                 if *summary.package_id().source_id().canonical_url() == canonical {
                     anyhow::bail!(
-                        "patch for `{}` in `{}` points to the same source, but \
+                        "patch for `{}` in `{}`{} points to the same source, but \
                          patches must point to different sources",
                         dep.package_name(),
-                        url
+                        url,
+                        defined_in
                     );
                 }
                 Ok(summary)
@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::num::NonZeroU64;
+use std::rc::Rc;
 
 use anyhow::format_err;
 use log::debug;
@@ -28,6 +29,8 @@ pub struct Context {
     pub resolve_features: im_rc::HashMap<PackageId, FeaturesSet>,
     /// get the package that will be linking to a native library by its links attribute
     pub links: im_rc::HashMap<InternedString, PackageId>,
+    /// the native libraries which any number of packages may link to
+    pub allowed_duplicate_links: Rc<HashSet<InternedString>>,
     /// for each package the list of names it can see,
     /// then for each name the exact version that name represents and weather the name is public.
     pub public_dependency: Option<PublicDependency>,
@@ -80,11 +83,15 @@ impl PackageId {
 }
 
 impl Context {
-    pub fn new(check_public_visible_dependencies: bool) -> Context {
+    pub fn new(
+        check_public_visible_dependencies: bool,
+        allowed_duplicate_links: &HashSet<InternedString>,
+    ) -> Context {
         Context {
             age: 0,
             resolve_features: im_rc::HashMap::new(),
             links: im_rc::HashMap::new(),
+            allowed_duplicate_links: Rc::new(allowed_duplicate_links.clone()),
             public_dependency: if check_public_visible_dependencies {
                 Some(PublicDependency::new())
             } else {
@@ -119,7 +126,13 @@ impl Context {
                 );
             }
             im_rc::hashmap::Entry::Vacant(v) => {
-                if let Some(link) = summary.links() {
+                // Links which are allowed to be duplicated are not tracked, so
+                // they never conflict.
+                let allowed_duplicate_links = &self.allowed_duplicate_links;
+                let link = summary
+                    .links()
+                    .filter(|link| !allowed_duplicate_links.contains(link));
+                if let Some(link) = link {
                     if self.links.insert(link, id).is_some() {
                         return Err(format_err!(
                             "Attempting to resolve a dependency with more then \
@@ -32,8 +32,9 @@ pub struct RegistryQueryer<'a> {
     /// versions first. That allows `cargo update -Z minimal-versions` which will
     /// specify minimum dependency versions to be used.
     minimal_versions: bool,
-    /// a cache of `Candidate`s that fulfil a `Dependency`
-    registry_cache: HashMap<Dependency, Rc<Vec<Summary>>>,
+    /// a cache of `Candidate`s that fulfil a `Dependency`, and whether only
+    /// the minimal version was requested
+    registry_cache: HashMap<(Dependency, bool), Rc<Vec<Summary>>>,
     /// a cache of `Dependency`s that are required for a `Summary`
     summary_cache: HashMap<
         (Option<PackageId>, Summary, ResolveOpts),
@@ -75,8 +76,15 @@ impl<'a> RegistryQueryer<'a> {
     /// any candidates are returned which match an override then the override is
     /// applied by performing a second query for what the override should
     /// return.
-    pub fn query(&mut self, dep: &Dependency) -> CargoResult<Rc<Vec<Summary>>> {
-        if let Some(out) = self.registry_cache.get(dep).cloned() {
+    ///
+    /// If `only_minimal` is set, only the candidate with the lowest version is
+    /// returned, for `-Z direct-minimal-versions`.
+    pub fn query(&mut self, dep: &Dependency, only_minimal: bool) -> CargoResult<Rc<Vec<Summary>>> {
+        if let Some(out) = self
+            .registry_cache
+            .get(&(dep.clone(), only_minimal))
+            .cloned()
+        {
             return Ok(out);
         }
 
@@ -174,7 +182,7 @@ impl<'a> RegistryQueryer<'a> {
             match previous_cmp {
                 Ordering::Equal => {
                     let cmp = a.version().cmp(b.version());
-                    if self.minimal_versions {
+                    if self.minimal_versions || only_minimal {
                         // Lower version ordered first.
                         cmp
                     } else {
@@ -186,9 +194,16 @@ impl<'a> RegistryQueryer<'a> {
             }
         });
 
+        // Higher versions aren't tried at all, so that a lower bound which
+        // doesn't work is reported rather than silently raised.
+        if only_minimal {
+            ret.truncate(1);
+        }
+
         let out = Rc::new(ret);
 
-        self.registry_cache.insert(dep.clone(), out.clone());
+        self.registry_cache
+            .insert((dep.clone(), only_minimal), out.clone());
 
         Ok(out)
     }
@@ -218,12 +233,16 @@ impl<'a> RegistryQueryer<'a> {
         // for our own dependencies.
         let (used_features, deps) = resolve_features(parent, candidate, opts)?;
 
+        // Only the roots of the resolve have no parent, so these are the
+        // direct dependencies of a workspace member.
+        let only_minimal = opts.direct_minimal_versions && parent.is_none();
+
         // Next, transform all dependencies into a list of possible candidates
         // which can satisfy that dependency.
         let mut deps = deps
             .into_iter()
             .map(|(dep, features)| {
-                let candidates = self.query(&dep).chain_err(|| {
+                let candidates = self.query(&dep, only_minimal).chain_err(|| {
                     anyhow::format_err!(
                         "failed to get `{}` as a dependency of {}",
                         dep.package_name(),
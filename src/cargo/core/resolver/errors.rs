@@ -1,9 +1,10 @@
 use std::fmt;
 
-use crate::core::{Dependency, PackageId, Registry, Summary};
+use crate::core::{Dependency, InternedString, PackageId, Registry, Summary};
 use crate::util::lev_distance::lev_distance;
 use crate::util::Config;
 use anyhow::Error;
+use serde::Serialize;
 
 use super::context::Context;
 use super::types::{ConflictMap, ConflictReason};
@@ -12,6 +13,7 @@ use super::types::{ConflictMap, ConflictReason};
 pub struct ResolveError {
     cause: Error,
     package_path: Vec<PackageId>,
+    report: Option<ConflictReport>,
 }
 
 impl ResolveError {
@@ -19,6 +21,7 @@ impl ResolveError {
         Self {
             cause: cause.into(),
             package_path,
+            report: None,
         }
     }
 
@@ -27,6 +30,50 @@ impl ResolveError {
     pub fn package_path(&self) -> &[PackageId] {
         &self.package_path
     }
+
+    /// Returns the structured description of the conflict, if the error is
+    /// caused by conflicting requirements.
+    pub fn conflict_report(&self) -> Option<&ConflictReport> {
+        self.report.as_ref()
+    }
+}
+
+/// A description of conflicting requirements in the dependency graph, for
+/// tools which explain or fix them.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictReport {
+    /// The name of the package which no version could be selected for.
+    pub package: InternedString,
+    /// The requirement which could not be satisfied.
+    pub requirement: String,
+    /// The packages from the root down to the one with the requirement.
+    pub path: Vec<PackageId>,
+    /// The versions which match the requirement, but all conflict.
+    pub candidates: Vec<String>,
+    pub conflicts: Vec<ConflictingPackage>,
+    /// What is most likely to resolve the conflict.
+    pub suggestion: Option<String>,
+}
+
+/// A previously selected package which conflicts with all the candidates of
+/// a `ConflictReport`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictingPackage {
+    /// One of `semver`, `links`, `missing-features`,
+    /// `required-dependency-as-features`, `public-dependency` or
+    /// `publicly-exports`.
+    pub reason: &'static str,
+    pub package: PackageId,
+    /// The packages from the root down to `package`.
+    pub path: Vec<PackageId>,
+    /// The requirement `package` was selected for, if it's not the root.
+    pub requirement: Option<String>,
+    /// The native library which is linked to more than once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<InternedString>,
+    /// The features which the candidates don't have.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<String>,
 }
 
 impl std::error::Error for ResolveError {
@@ -106,6 +153,7 @@ pub(super) fn activation_error(
                 .join(", "),
         );
 
+        let report = conflict_report(cx, parent, dep, conflicting_activations, candidates);
         let mut conflicting_activations: Vec<_> = conflicting_activations.iter().collect();
         conflicting_activations.sort_unstable();
         let (links_errors, mut other_errors): (Vec<_>, Vec<_>) = conflicting_activations
@@ -184,7 +232,14 @@ pub(super) fn activation_error(
         msg.push_str(&*dep.package_name());
         msg.push_str("` which could resolve this conflict");
 
-        return to_resolve_err(anyhow::format_err!("{}", msg));
+        if let Some(suggestion) = &report.suggestion {
+            msg.push_str("\n\nhelp: ");
+            msg.push_str(suggestion);
+        }
+
+        let mut err = to_resolve_err(anyhow::format_err!("{}", msg));
+        err.report = Some(report);
+        return err;
     }
 
     // We didn't actually find any candidates, so we need to
@@ -326,6 +381,151 @@ pub(super) fn activation_error(
     to_resolve_err(anyhow::format_err!("{}", msg))
 }
 
+/// Builds the `ConflictReport` for `dep` of `parent`, whose `candidates`
+/// all conflict with the `conflicting_activations`.
+fn conflict_report(
+    cx: &Context,
+    parent: &Summary,
+    dep: &Dependency,
+    conflicting_activations: &ConflictMap,
+    candidates: &[Summary],
+) -> ConflictReport {
+    // The paths of the parents graph go from a package up to the root.
+    let path_from_root = |id: &PackageId| {
+        let mut path = cx
+            .parents
+            .path_to_bottom(id)
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        path.reverse();
+        path
+    };
+    let requirement_of = |path: &[PackageId]| -> Option<String> {
+        let (id, parent) = match path {
+            [.., parent, id] => (id, parent),
+            _ => return None,
+        };
+        let deps = cx.parents.edge(id, parent)?;
+        let mut reqs = deps
+            .iter()
+            .filter(|dep| dep.package_name() == id.name())
+            .map(|dep| dep.version_req().to_string())
+            .collect::<Vec<_>>();
+        reqs.sort();
+        reqs.dedup();
+        Some(reqs.join(", "))
+    };
+
+    let mut conflicting_activations: Vec<_> = conflicting_activations.iter().collect();
+    conflicting_activations.sort_unstable();
+    let conflicts = conflicting_activations
+        .into_iter()
+        .rev()
+        .map(|(&id, reason)| {
+            let path = path_from_root(&id);
+            let (reason, links, features) = match reason {
+                ConflictReason::Semver => ("semver", None, None),
+                ConflictReason::Links(link) => ("links", Some(*link), None),
+                ConflictReason::MissingFeatures(features) => {
+                    ("missing-features", None, Some(features.clone()))
+                }
+                ConflictReason::RequiredDependencyAsFeatures(features) => (
+                    "required-dependency-as-features",
+                    None,
+                    Some(features.to_string()),
+                ),
+                ConflictReason::PublicDependency(_) => ("public-dependency", None, None),
+                ConflictReason::PubliclyExports(_) => ("publicly-exports", None, None),
+            };
+            ConflictingPackage {
+                reason,
+                package: id,
+                requirement: requirement_of(&path),
+                path,
+                links,
+                features,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let name = dep.package_name();
+    let requirement = dep.version_req().to_string();
+    let describe_req =
+        |req: &str, id: &PackageId| format!("`{}` requirement `{}` of `{}`", name, req, id);
+    // Links conflicts and missing features can't be resolved with another
+    // version of a package which is already selected, so they come first.
+    let suggestion = conflicts
+        .iter()
+        .find(|c| c.reason == "links")
+        .or_else(|| conflicts.iter().find(|c| c.features.is_some()))
+        .or_else(|| conflicts.iter().find(|c| c.reason == "semver"))
+        .map(|c| match c.reason {
+            "links" if c.package.name() == name => format!(
+                "only one version of `{}` may link to the native library `{}`, \
+                 consider relaxing the {} so that it allows `{}`",
+                name,
+                c.links.unwrap(),
+                describe_req(&requirement, &parent.package_id()),
+                c.package
+            ),
+            "links" => format!(
+                "`{}` and `{}` both link to the native library `{}`, \
+                 consider removing the dependency on one of them",
+                c.package,
+                name,
+                c.links.unwrap()
+            ),
+            "semver" => {
+                // Exact requirements are the most likely to be too strict,
+                // otherwise the new requirement has to allow the version
+                // which was already selected.
+                let selected_by = c.path.iter().rev().nth(1);
+                let versions = candidates
+                    .iter()
+                    .map(|c| c.version().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let relax_failing = format!(
+                    "{} so that it allows `{}`",
+                    describe_req(&requirement, &parent.package_id()),
+                    c.package
+                );
+                match (&c.requirement, selected_by) {
+                    (Some(req), Some(selected_by)) if req.starts_with('=') => format!(
+                        "consider relaxing the {} so that it allows one of \
+                         the versions {}",
+                        describe_req(req, selected_by),
+                        versions
+                    ),
+                    (Some(req), Some(selected_by)) => format!(
+                        "consider relaxing the {}, or the {} \
+                         so that it allows one of the versions {}",
+                        relax_failing,
+                        describe_req(req, selected_by),
+                        versions
+                    ),
+                    _ => format!("consider relaxing the {}", relax_failing),
+                }
+            }
+            _ => format!(
+                "consider removing the features `{}` from the `{}` dependency of `{}`",
+                c.features.as_ref().unwrap(),
+                name,
+                parent.package_id()
+            ),
+        });
+
+    ConflictReport {
+        package: name,
+        requirement,
+        path: path_from_root(&parent.package_id()),
+        candidates: candidates.iter().map(|c| c.version().to_string()).collect(),
+        conflicts,
+        suggestion,
+    }
+}
+
 /// Returns String representation of dependency chain for a particular `pkgid`.
 pub(super) fn describe_path(path: &[&PackageId]) -> String {
     use std::fmt::Write;
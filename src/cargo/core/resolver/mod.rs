@@ -54,8 +54,8 @@ use std::time::{Duration, Instant};
 
 use log::{debug, trace};
 
-use crate::core::PackageIdSpec;
 use crate::core::{Dependency, PackageId, Registry, Summary};
+use crate::core::{InternedString, PackageIdSpec};
 use crate::util::config::Config;
 use crate::util::errors::CargoResult;
 use crate::util::profile;
@@ -69,6 +69,7 @@ use self::types::{FeaturesSet, RcVecIter, RemainingDeps, ResolverProgress};
 pub use self::encode::Metadata;
 pub use self::encode::{EncodableDependency, EncodablePackageId, EncodableResolve};
 pub use self::errors::{ActivateError, ActivateResult, ResolveError};
+pub use self::errors::{ConflictReport, ConflictingPackage};
 pub use self::features::HasDevUnits;
 pub use self::resolve::{Resolve, ResolveVersion};
 pub use self::types::ResolveOpts;
@@ -119,6 +120,9 @@ mod types;
 ///
 ///     When we have a decision for how to implement is without breaking existing functionality
 ///     this flag can be removed.
+///
+/// * `allowed_duplicate_links` - the `links` values which may be shared by multiple packages in
+///     the graph. The compiler checks that no single artifact links to more than one of them.
 pub fn resolve(
     summaries: &[(Summary, ResolveOpts)],
     replacements: &[(PackageIdSpec, Dependency)],
@@ -126,8 +130,9 @@ pub fn resolve(
     try_to_use: &HashSet<PackageId>,
     config: Option<&Config>,
     check_public_visible_dependencies: bool,
+    allowed_duplicate_links: &HashSet<InternedString>,
 ) -> CargoResult<Resolve> {
-    let cx = Context::new(check_public_visible_dependencies);
+    let cx = Context::new(check_public_visible_dependencies, allowed_duplicate_links);
     let _p = profile::start("resolving");
     let minimal_versions = match config {
         Some(config) => config.cli_unstable().minimal_versions,
@@ -383,6 +388,7 @@ fn activate_deps_loop(
                     all_features: false,
                     uses_default_features: dep.uses_default_features(),
                 },
+                direct_minimal_versions: false,
             };
             trace!(
                 "{}[{}]>{} trying {}",
@@ -851,8 +857,10 @@ fn generalize_conflicting(
             // A dep is equivalent to one of the things it can resolve to.
             // Thus, if all the things it can resolve to have already ben determined
             // to be conflicting, then we can just say that we conflict with the parent.
+            // All of the candidates are considered here, even if only the minimal one
+            // was tried, as that only makes this conclusion less likely.
             if let Some(others) = registry
-                .query(critical_parents_dep)
+                .query(critical_parents_dep, false)
                 .expect("an already used dep now error!?")
                 .iter()
                 .rev() // the last one to be tried is the least likely to be in the cache, so start with that.
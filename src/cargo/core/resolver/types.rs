@@ -106,6 +106,12 @@ pub struct ResolveOpts {
     pub dev_deps: bool,
     /// Set of features requested on the command-line.
     pub features: RequestedFeatures,
+    /// Whether the direct dependencies of this package should be resolved to
+    /// their minimal versions, while its transitive dependencies are still
+    /// resolved to the maximal ones.
+    ///
+    /// This is set for workspace members by `-Z direct-minimal-versions`.
+    pub direct_minimal_versions: bool,
 }
 
 impl ResolveOpts {
@@ -114,6 +120,7 @@ impl ResolveOpts {
         ResolveOpts {
             dev_deps: true,
             features: RequestedFeatures::new_all(true),
+            direct_minimal_versions: false,
         }
     }
 
@@ -130,6 +137,7 @@ impl ResolveOpts {
                 all_features,
                 uses_default_features,
             ),
+            direct_minimal_versions: false,
         }
     }
 }
@@ -1,6 +1,8 @@
 use std::collections::hash_map::HashMap;
 use std::fmt;
 
+use serde::Deserialize;
+
 use crate::core::package::PackageSet;
 use crate::core::{Dependency, Package, PackageId, Summary};
 use crate::util::{CargoResult, Config};
@@ -36,6 +38,13 @@ pub trait Source {
     /// whereas an `Index` source may return dependencies that have the same canonicalization.
     fn fuzzy_query(&mut self, dep: &Dependency, f: &mut dyn FnMut(Summary)) -> CargoResult<()>;
 
+    /// Attempts to find all the versions of a package that the source has
+    /// published, like `fuzzy_query`, even those which are not available
+    /// with `--offline`.
+    fn query_published(&mut self, dep: &Dependency, f: &mut dyn FnMut(Summary)) -> CargoResult<()> {
+        self.fuzzy_query(dep, f)
+    }
+
     fn query_vec(&mut self, dep: &Dependency) -> CargoResult<Vec<Summary>> {
         let mut ret = Vec::new();
         self.query(dep, &mut |s| ret.push(s))?;
@@ -100,11 +109,36 @@ pub trait Source {
     /// Query if a package is yanked. Only registry sources can mark packages
     /// as yanked. This ignores the yanked whitelist.
     fn is_yanked(&mut self, _pkg: PackageId) -> CargoResult<bool>;
+
+    /// Returns the deprecation advisory the source has published for a
+    /// package, if any. Only registry sources can mark packages as
+    /// deprecated.
+    fn deprecation(&mut self, _pkg: PackageId) -> CargoResult<Option<Deprecation>> {
+        Ok(None)
+    }
+}
+
+/// An advisory published by a registry for a single version of a package.
+///
+/// Deprecated versions are still selected by the resolver as usual, Cargo
+/// only warns about them.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Deprecation {
+    /// Why the version should no longer be used.
+    pub message: String,
+    /// The name of a package to use instead.
+    pub replacement: Option<String>,
 }
 
 pub enum MaybePackage {
     Ready(Package),
-    Download { url: String, descriptor: String },
+    Download {
+        url: String,
+        descriptor: String,
+        /// Value of the `Authorization` header to send with the request, for
+        /// registries which require authenticated downloads.
+        authorization: Option<String>,
+    },
 }
 
 impl<'a, T: Source + ?Sized + 'a> Source for Box<T> {
@@ -138,6 +172,10 @@ impl<'a, T: Source + ?Sized + 'a> Source for Box<T> {
         (**self).fuzzy_query(dep, f)
     }
 
+    fn query_published(&mut self, dep: &Dependency, f: &mut dyn FnMut(Summary)) -> CargoResult<()> {
+        (**self).query_published(dep, f)
+    }
+
     /// Forwards to `Source::update`.
     fn update(&mut self) -> CargoResult<()> {
         (**self).update()
@@ -177,6 +215,10 @@ impl<'a, T: Source + ?Sized + 'a> Source for Box<T> {
     fn is_yanked(&mut self, pkg: PackageId) -> CargoResult<bool> {
         (**self).is_yanked(pkg)
     }
+
+    fn deprecation(&mut self, pkg: PackageId) -> CargoResult<Option<Deprecation>> {
+        (**self).deprecation(pkg)
+    }
 }
 
 impl<'a, T: Source + ?Sized + 'a> Source for &'a mut T {
@@ -204,6 +246,10 @@ impl<'a, T: Source + ?Sized + 'a> Source for &'a mut T {
         (**self).fuzzy_query(dep, f)
     }
 
+    fn query_published(&mut self, dep: &Dependency, f: &mut dyn FnMut(Summary)) -> CargoResult<()> {
+        (**self).query_published(dep, f)
+    }
+
     fn update(&mut self) -> CargoResult<()> {
         (**self).update()
     }
@@ -239,6 +285,10 @@ impl<'a, T: Source + ?Sized + 'a> Source for &'a mut T {
     fn is_yanked(&mut self, pkg: PackageId) -> CargoResult<bool> {
         (**self).is_yanked(pkg)
     }
+
+    fn deprecation(&mut self, pkg: PackageId) -> CargoResult<Option<Deprecation>> {
+        (**self).deprecation(pkg)
+    }
 }
 
 /// A `HashMap` of `SourceId` -> `Box<Source>`.
@@ -5,19 +5,21 @@ use std::path::{Path, PathBuf};
 use std::slice;
 
 use glob::glob;
+use lazycell::LazyCell;
 use log::debug;
 use url::Url;
 
 use crate::core::features::Features;
 use crate::core::registry::PackageRegistry;
 use crate::core::resolver::features::RequestedFeatures;
-use crate::core::{Dependency, PackageId, PackageIdSpec};
+use crate::core::{Dependency, InternedString, PackageId, PackageIdSpec};
 use crate::core::{EitherManifest, Package, SourceId, VirtualManifest};
 use crate::ops;
 use crate::sources::PathSource;
+use crate::util::config::Definition;
 use crate::util::errors::{CargoResult, CargoResultExt, ManifestError};
 use crate::util::paths;
-use crate::util::toml::{read_manifest, TomlProfiles};
+use crate::util::toml::{read_manifest, read_manifest_from_str, ConfigPatches, TomlProfiles};
 use crate::util::{Config, Filesystem};
 
 /// The core abstraction in Cargo for working with a workspace of crates.
@@ -83,6 +85,10 @@ pub struct Workspace<'cfg> {
     // If `true`, then the resolver will ignore any existing `Cargo.lock`
     // file. This is set for `cargo install` without `--locked`.
     ignore_lock: bool,
+
+    // The `[patch]` and `[replace]` tables of `.cargo/config`, loaded the
+    // first time they are needed.
+    config_patches: LazyCell<ConfigPatches>,
 }
 
 // Separate structure for tracking loaded packages (to avoid loading anything
@@ -121,6 +127,7 @@ pub struct WorkspaceRootConfig {
     members: Option<Vec<String>>,
     default_members: Option<Vec<String>>,
     exclude: Vec<String>,
+    allow_duplicate_links: Vec<String>,
 }
 
 /// An iterator over the member packages of a workspace, returned by
@@ -146,6 +153,30 @@ impl<'cfg> Workspace<'cfg> {
         Ok(ws)
     }
 
+    /// Loads the workspace again from the manifests on disk, with the same
+    /// options as this one, for example after they were edited.
+    pub fn reload(&self) -> CargoResult<Workspace<'cfg>> {
+        self.reload_with(&[])
+    }
+
+    /// Like `reload`, but with the contents of the given manifests used
+    /// instead of the files on disk, for example to resolve the workspace
+    /// with edits before writing them.
+    pub fn reload_with(&self, manifests: &[(PathBuf, String)]) -> CargoResult<Workspace<'cfg>> {
+        let mut ws = Workspace::new_default(self.current_manifest.clone(), self.config);
+        for (manifest_path, contents) in manifests {
+            ws.packages.insert(manifest_path, contents)?;
+        }
+        ws.target_dir = self.target_dir.clone();
+        ws.root_manifest = ws.find_root(&self.current_manifest)?;
+        ws.find_members()?;
+        ws.validate()?;
+        ws.is_ephemeral = self.is_ephemeral;
+        ws.require_optional_deps = self.require_optional_deps;
+        ws.ignore_lock = self.ignore_lock;
+        Ok(ws)
+    }
+
     fn new_default(current_manifest: PathBuf, config: &'cfg Config) -> Workspace<'cfg> {
         Workspace {
             config,
@@ -163,6 +194,7 @@ impl<'cfg> Workspace<'cfg> {
             require_optional_deps: true,
             loaded_packages: RefCell::new(HashMap::new()),
             ignore_lock: false,
+            config_patches: LazyCell::new(),
         }
     }
 
@@ -310,22 +342,77 @@ impl<'cfg> Workspace<'cfg> {
 
     /// Returns the root `[replace]` section of this workspace.
     ///
-    /// This may be from a virtual crate or an actual crate.
-    pub fn root_replace(&self) -> &[(PackageIdSpec, Dependency)] {
-        match self.root_maybe() {
+    /// This may be from a virtual crate or an actual crate, and includes the
+    /// `[replace]` table of the configuration with `-Z patch-in-config`.
+    pub fn root_replace(&self) -> CargoResult<Vec<(PackageIdSpec, Dependency)>> {
+        let mut replace = match self.root_maybe() {
             MaybePackage::Package(p) => p.manifest().replace(),
             MaybePackage::Virtual(vm) => vm.replace(),
         }
+        .to_vec();
+        for (spec, dep) in &self.config_patches()?.replace {
+            // The configuration takes precedence over the manifest.
+            replace.retain(|(s, _)| s != spec);
+            replace.push((spec.clone(), dep.clone()));
+        }
+        Ok(replace)
+    }
+
+    /// Returns the native libraries which multiple packages may link to, as
+    /// listed in `allow-duplicate-links` of the root `[workspace]` section.
+    pub fn allowed_duplicate_links(&self) -> HashSet<InternedString> {
+        match self.root_maybe().workspace_config() {
+            WorkspaceConfig::Root(root_config) => root_config
+                .allow_duplicate_links
+                .iter()
+                .map(|link| InternedString::new(link))
+                .collect(),
+            WorkspaceConfig::Member { .. } => HashSet::new(),
+        }
     }
 
     /// Returns the root `[patch]` section of this workspace.
     ///
-    /// This may be from a virtual crate or an actual crate.
-    pub fn root_patch(&self) -> &HashMap<Url, Vec<Dependency>> {
-        match self.root_maybe() {
+    /// This may be from a virtual crate or an actual crate, and includes the
+    /// `[patch]` tables of the configuration with `-Z patch-in-config`, along
+    /// with the location of their definition.
+    pub fn root_patch(&self) -> CargoResult<HashMap<Url, Vec<(Dependency, Option<Definition>)>>> {
+        let mut patch: HashMap<_, Vec<_>> = match self.root_maybe() {
             MaybePackage::Package(p) => p.manifest().patch(),
             MaybePackage::Virtual(vm) => vm.patch(),
         }
+        .iter()
+        .map(|(url, deps)| {
+            let deps = deps.iter().map(|dep| (dep.clone(), None)).collect();
+            (url.clone(), deps)
+        })
+        .collect();
+        for (url, deps) in &self.config_patches()?.patch {
+            let patches = patch.entry(url.clone()).or_insert_with(Vec::new);
+            for (dep, definition) in deps {
+                // The configuration takes precedence over the manifest.
+                patches.retain(|(p, _)| p.package_name() != dep.package_name());
+                patches.push((dep.clone(), Some(definition.clone())));
+            }
+        }
+        Ok(patch)
+    }
+
+    /// Returns where the `[patch]` entry matching `id` was defined, if it
+    /// comes from the configuration rather than the root manifest.
+    pub fn config_patch_definition(&self, id: PackageId) -> Option<&Definition> {
+        let patches = self.config_patches().ok()?;
+        patches
+            .patch
+            .values()
+            .flatten()
+            .find(|(dep, _)| dep.matches_id(id))
+            .map(|(_, definition)| definition)
+    }
+
+    fn config_patches(&self) -> CargoResult<&ConfigPatches> {
+        self.config_patches
+            .try_borrow_with(|| ConfigPatches::load(self.config))
     }
 
     /// Returns an iterator over all packages in this workspace
@@ -959,15 +1046,24 @@ impl<'cfg> Packages<'cfg> {
                 let source_id = SourceId::for_path(key)?;
                 let (manifest, _nested_paths) =
                     read_manifest(manifest_path, source_id, self.config)?;
-                Ok(v.insert(match manifest {
-                    EitherManifest::Real(manifest) => {
-                        MaybePackage::Package(Package::new(manifest, manifest_path))
-                    }
-                    EitherManifest::Virtual(vm) => MaybePackage::Virtual(vm),
-                }))
+                Ok(v.insert(MaybePackage::new(manifest, manifest_path)))
             }
         }
     }
+
+    /// Loads the manifest at `manifest_path` from `contents` rather than from
+    /// disk.
+    fn insert(&mut self, manifest_path: &Path, contents: &str) -> CargoResult<()> {
+        let key = manifest_path.parent().unwrap();
+        let source_id = SourceId::for_path(key)?;
+        let (manifest, _nested_paths) =
+            read_manifest_from_str(contents, manifest_path, source_id, self.config)?;
+        self.packages.insert(
+            key.to_path_buf(),
+            MaybePackage::new(manifest, manifest_path),
+        );
+        Ok(())
+    }
 }
 
 impl<'a, 'cfg> Iterator for Members<'a, 'cfg> {
@@ -991,6 +1087,15 @@ impl<'a, 'cfg> Iterator for Members<'a, 'cfg> {
 }
 
 impl MaybePackage {
+    fn new(manifest: EitherManifest, manifest_path: &Path) -> MaybePackage {
+        match manifest {
+            EitherManifest::Real(manifest) => {
+                MaybePackage::Package(Package::new(manifest, manifest_path))
+            }
+            EitherManifest::Virtual(vm) => MaybePackage::Virtual(vm),
+        }
+    }
+
     fn workspace_config(&self) -> &WorkspaceConfig {
         match *self {
             MaybePackage::Package(ref p) => p.manifest().workspace_config(),
@@ -1006,12 +1111,14 @@ impl WorkspaceRootConfig {
         members: &Option<Vec<String>>,
         default_members: &Option<Vec<String>>,
         exclude: &Option<Vec<String>>,
+        allow_duplicate_links: &Option<Vec<String>>,
     ) -> WorkspaceRootConfig {
         WorkspaceRootConfig {
             root_dir: root_dir.to_path_buf(),
             members: members.clone(),
             default_members: default_members.clone(),
             exclude: exclude.clone().unwrap_or_default(),
+            allow_duplicate_links: allow_duplicate_links.clone().unwrap_or_default(),
         }
     }
 
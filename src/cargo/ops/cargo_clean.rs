@@ -60,8 +60,13 @@ pub fn clean(ws: &Workspace<'_>, opts: &CleanOptions<'_>) -> CargoResult<()> {
     let (packages, resolve) = ops::resolve_ws(ws)?;
 
     let interner = UnitInterner::new();
-    let mut build_config = BuildConfig::new(config, Some(1), &opts.target, CompileMode::Build)?;
-    build_config.requested_profile = opts.requested_profile;
+    let build_config = BuildConfig::new(
+        config,
+        Some(1),
+        &opts.target,
+        opts.requested_profile,
+        CompileMode::Build,
+    )?;
     let target_data = RustcTargetData::new(ws, build_config.requested_kind)?;
     let bcx = BuildContext::new(
         ws,
@@ -71,7 +76,7 @@ pub fn clean(ws: &Workspace<'_>, opts: &CleanOptions<'_>) -> CargoResult<()> {
         profiles,
         &interner,
         HashMap::new(),
-        target_data,
+        &target_data,
     )?;
     let requested_features = RequestedFeatures::new_all(true);
     let specs = opts
@@ -81,7 +86,7 @@ pub fn clean(ws: &Workspace<'_>, opts: &CleanOptions<'_>) -> CargoResult<()> {
         .collect::<CargoResult<Vec<_>>>()?;
     let features = FeatureResolver::resolve(
         ws,
-        &bcx.target_data,
+        &target_data,
         &resolve,
         &requested_features,
         &specs,
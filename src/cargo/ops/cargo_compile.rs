@@ -36,12 +36,14 @@ use crate::core::compiler::{CompileKind, CompileMode, RustcTargetData, Unit};
 use crate::core::compiler::{DefaultExecutor, Executor, UnitInterner};
 use crate::core::profiles::{Profiles, UnitFor};
 use crate::core::resolver::features::{self, FeaturesFor};
-use crate::core::resolver::{HasDevUnits, Resolve, ResolveOpts};
-use crate::core::{LibKind, Package, PackageSet, Target};
+use crate::core::resolver::{HasDevUnits, Resolve, ResolveError, ResolveOpts};
+use crate::core::{InternedString, LibKind, Package, PackageSet, Target};
 use crate::core::{PackageId, PackageIdSpec, TargetKind, Workspace};
 use crate::ops;
 use crate::ops::resolve::WorkspaceResolve;
+use crate::ops::WatchCache;
 use crate::util::config::Config;
+use crate::util::machine_message::{self, Message};
 use crate::util::{closest_msg, profile, CargoResult};
 
 /// Contains information about how a package should be compiled.
@@ -77,13 +79,15 @@ pub struct CompileOptions<'a> {
     // Note that, although the cmd-line flag name is `out-dir`, in code we use
     // `export_dir`, to avoid confusion with out dir at `target/debug/deps`.
     pub export_dir: Option<PathBuf>,
+    /// What is kept in memory between builds with `--watch`.
+    pub watch: Option<WatchCache<'a>>,
 }
 
 impl<'a> CompileOptions<'a> {
     pub fn new(config: &'a Config, mode: CompileMode) -> CargoResult<CompileOptions<'a>> {
         Ok(CompileOptions {
             config,
-            build_config: BuildConfig::new(config, None, &None, mode)?,
+            build_config: BuildConfig::new(config, None, &None, InternedString::new("dev"), mode)?,
             features: Vec::new(),
             all_features: false,
             no_default_features: false,
@@ -96,6 +100,7 @@ impl<'a> CompileOptions<'a> {
             local_rustdoc_args: None,
             rustdoc_document_private_items: false,
             export_dir: None,
+            watch: None,
         })
     }
 }
@@ -279,6 +284,7 @@ pub fn compile_ws<'a>(
         ref local_rustdoc_args,
         rustdoc_document_private_items,
         ref export_dir,
+        ref watch,
     } = *options;
 
     match build_config.mode {
@@ -308,7 +314,16 @@ pub fn compile_ws<'a>(
         build_config.requested_profile,
         ws.features(),
     )?;
-    let target_data = RustcTargetData::new(ws, build_config.requested_kind)?;
+    // With `--watch`, the information about rustc and the resolve are reused
+    // from the previous build.
+    let (target_data, resolve) = match watch {
+        Some(watch) => watch.take(),
+        None => (None, None),
+    };
+    let target_data = match target_data {
+        Some(target_data) => target_data,
+        None => RustcTargetData::new(ws, build_config.requested_kind)?,
+    };
 
     let specs = spec.to_package_id_specs(ws)?;
     let dev_deps = ws.require_optional_deps() || filter.need_dev_deps(build_config.mode);
@@ -318,14 +333,25 @@ pub fn compile_ws<'a>(
     } else {
         HasDevUnits::No
     };
-    let resolve = ops::resolve_ws_with_opts(
-        ws,
-        &target_data,
-        build_config.requested_kind,
-        &opts,
-        &specs,
-        has_dev_units,
-    )?;
+    let resolve = match resolve {
+        Some(resolve) => resolve,
+        None => match ops::resolve_ws_with_opts(
+            ws,
+            &target_data,
+            build_config.requested_kind,
+            &opts,
+            &specs,
+            has_dev_units,
+        ) {
+            Ok(resolve) => resolve,
+            Err(e) => {
+                if build_config.emit_json() {
+                    emit_conflict_report(config, &e);
+                }
+                return Err(e);
+            }
+        },
+    };
     let WorkspaceResolve {
         mut pkg_set,
         workspace_resolve,
@@ -414,7 +440,7 @@ pub fn compile_ws<'a>(
         profiles,
         &interner,
         HashMap::new(),
-        target_data,
+        &target_data,
     )?;
 
     let units = generate_targets(
@@ -499,10 +525,26 @@ pub fn compile_ws<'a>(
     let ret = {
         let _p = profile::start("compiling");
         let cx = Context::new(config, &bcx, unit_dependencies, build_config.requested_kind)?;
-        cx.compile(&units, export_dir.clone(), exec)?
+        cx.compile(&units, export_dir.clone(), exec)
     };
 
-    Ok(ret)
+    if let Some(watch) = watch {
+        // The package set of `-Zbuild-std` has the standard library added to
+        // it, which is resolved again for each build.
+        let resolve = if std_resolve_features.is_none() {
+            Some(WorkspaceResolve {
+                pkg_set,
+                workspace_resolve,
+                targeted_resolve: resolve,
+                resolved_features,
+            })
+        } else {
+            None
+        };
+        watch.store(target_data, resolve);
+    }
+
+    ret
 }
 
 impl FilterRule {
@@ -802,6 +844,9 @@ fn generate_targets<'a>(
                             mode: CompileMode::Doctest,
                         });
                     }
+                    if bcx.config.cli_unstable().doctest_bins {
+                        proposals.extend(doctested_bins(pkg));
+                    }
                 }
             }
         }
@@ -829,6 +874,11 @@ fn generate_targets<'a>(
                         libs.push(proposal)
                     }
                 }
+                if bcx.build_config.mode.is_doc_test() && bcx.config.cli_unstable().doctest_bins {
+                    for pkg in packages {
+                        libs.extend(doctested_bins(pkg));
+                    }
+                }
                 if !all_targets && libs.is_empty() && *lib == LibRule::True {
                     let names = packages.iter().map(|pkg| pkg.name()).collect::<Vec<_>>();
                     if names.len() == 1 {
@@ -976,6 +1026,19 @@ fn resolve_all_features(
     features
 }
 
+/// Prints the `resolver-conflict` message for `err`, if it's caused by
+/// conflicting requirements in the dependency graph.
+fn emit_conflict_report(config: &Config, err: &anyhow::Error) {
+    let report = err
+        .chain()
+        .filter_map(|e| e.downcast_ref::<ResolveError>())
+        .find_map(|e| e.conflict_report());
+    if let Some(report) = report {
+        let msg = machine_message::ResolverConflict { report }.to_json_string();
+        config.shell().stdout_println(msg);
+    }
+}
+
 /// Given a list of all targets for a package, filters out only the targets
 /// that are automatically included when the user doesn't specify any targets.
 fn filter_default_targets(targets: &[Target], mode: CompileMode) -> Vec<&Target> {
@@ -1004,6 +1067,29 @@ fn filter_default_targets(targets: &[Target], mode: CompileMode) -> Vec<&Target>
     }
 }
 
+/// Returns the doc tests of the binaries of a package for `-Z doctest-bins`.
+///
+/// Like with `cargo doc`, a binary with the same name as the library is
+/// skipped.
+fn doctested_bins(pkg: &Package) -> impl Iterator<Item = Proposal<'_>> {
+    let targets = pkg.targets();
+    targets
+        .iter()
+        .filter(move |t| {
+            t.is_bin()
+                && t.doctested()
+                && !targets
+                    .iter()
+                    .any(|l| l.is_lib() && l.crate_name() == t.crate_name())
+        })
+        .map(move |target| Proposal {
+            pkg,
+            target,
+            requires_features: false,
+            mode: CompileMode::Doctest,
+        })
+}
+
 /// Returns a list of proposed targets based on command-line target selection flags.
 fn list_rule_targets<'a>(
     packages: &[&'a Package],
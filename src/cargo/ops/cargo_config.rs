@@ -0,0 +1,236 @@
+//! Implementation of the `cargo config` subcommand.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, format_err};
+use serde_json::json;
+
+use crate::util::config::{ConfigKey, ConfigValue as CV, Definition};
+use crate::util::errors::CargoResult;
+use crate::util::Config;
+
+/// The format to print the configuration in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// TOML, with the full key of every value.
+    Toml,
+    /// A JSON object nested like the TOML tables.
+    Json,
+    /// Only the JSON value of the requested key.
+    JsonValue,
+}
+
+impl ConfigFormat {
+    pub const POSSIBLE_VALUES: &'static [&'static str] = &["toml", "json", "json-value"];
+}
+
+impl FromStr for ConfigFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> CargoResult<ConfigFormat> {
+        match s {
+            "toml" => Ok(ConfigFormat::Toml),
+            "json" => Ok(ConfigFormat::Json),
+            "json-value" => Ok(ConfigFormat::JsonValue),
+            f => bail!("unknown config format `{}`", f),
+        }
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFormat::Toml => "toml".fmt(f),
+            ConfigFormat::Json => "json".fmt(f),
+            ConfigFormat::JsonValue => "json-value".fmt(f),
+        }
+    }
+}
+
+pub struct GetOptions<'a> {
+    /// The key to print, or everything if `None`.
+    pub key: Option<&'a str>,
+    pub format: ConfigFormat,
+    /// Annotates every value with where it was defined.
+    pub show_origin: bool,
+}
+
+/// Prints the merged configuration value of `opts.key`, with the values from
+/// all config files, include files, `--config` arguments and environment
+/// variables applied.
+pub fn get(config: &Config, opts: &GetOptions<'_>) -> CargoResult<()> {
+    if opts.show_origin && opts.format != ConfigFormat::Toml {
+        bail!(
+            "the `{}` format does not support --show-origin, try the `toml` format instead",
+            opts.format
+        );
+    }
+    let key = match opts.key {
+        Some(key) => ConfigKey::parse(key),
+        None => ConfigKey::new(),
+    };
+    if key.is_root() && opts.format == ConfigFormat::JsonValue {
+        bail!("the `json-value` format requires a config key");
+    }
+    let cv = if key.is_root() {
+        let values = config.values()?.clone();
+        CV::Table(values, Definition::Cli)
+    } else {
+        config
+            .get_cv_with_env(&key)?
+            .ok_or_else(|| format_err!("config value `{}` is not set", key))?
+    };
+
+    let mut printer = Printer {
+        config,
+        show_origin: opts.show_origin,
+        env_keys: HashSet::new(),
+    };
+    match opts.format {
+        ConfigFormat::Toml => printer.print_toml(&mut key.clone(), &cv)?,
+        ConfigFormat::Json => {
+            let mut value = printer.json(&mut key.clone(), &cv)?;
+            for part in key.parts().collect::<Vec<_>>().into_iter().rev() {
+                value = json!({ part: value });
+            }
+            println!("{}", serde_json::to_string(&value)?);
+        }
+        ConfigFormat::JsonValue => {
+            let value = printer.json(&mut key.clone(), &cv)?;
+            println!("{}", serde_json::to_string(&value)?);
+        }
+    }
+
+    // Environment variables can only be matched to the keys of values that
+    // are set in a file, so list the other ones which may be relevant.
+    if let CV::Table(..) = cv {
+        let prefix = format!("{}_", key.as_env_key());
+        let mut env = config
+            .env_vars()
+            .filter(|(env_key, _)| env_key.starts_with(&prefix))
+            .filter(|(env_key, _)| !printer.env_keys.contains(*env_key))
+            .collect::<Vec<_>>();
+        env.sort();
+        if !env.is_empty() {
+            match opts.format {
+                ConfigFormat::Toml => {
+                    println!("# The following environment variables may affect the loaded values.");
+                    for (env_key, env_value) in env {
+                        println!(
+                            "# {}={}",
+                            env_key,
+                            toml::Value::String(env_value.to_string())
+                        );
+                    }
+                }
+                ConfigFormat::Json | ConfigFormat::JsonValue => {
+                    let mut msg =
+                        "The following environment variables may affect the loaded values."
+                            .to_string();
+                    for (env_key, env_value) in env {
+                        msg.push_str(&format!("\n{}={}", env_key, env_value));
+                    }
+                    config.shell().note(msg)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+struct Printer<'a> {
+    config: &'a Config,
+    show_origin: bool,
+    /// The environment variables whose values were printed.
+    env_keys: HashSet<String>,
+}
+
+impl Printer<'_> {
+    /// Returns the value of `key` with its environment variable applied,
+    /// where `cv` is the value from the config files.
+    fn with_env(&mut self, key: &ConfigKey, cv: &CV) -> CargoResult<CV> {
+        if let CV::Table(..) = cv {
+            return Ok(cv.clone());
+        }
+        let cv = match self.config.get_cv_with_env(key)? {
+            Some(cv) => cv,
+            None => cv.clone(),
+        };
+        let from_env = match &cv {
+            CV::List(list, _) => list
+                .iter()
+                .any(|(_, def)| matches!(def, Definition::Environment(_))),
+            cv => matches!(cv.definition(), Definition::Environment(_)),
+        };
+        if from_env {
+            self.env_keys.insert(key.as_env_key().to_string());
+        }
+        Ok(cv)
+    }
+
+    fn origin(&self, def: &Definition) -> String {
+        if self.show_origin {
+            format!(" # {}", def)
+        } else {
+            String::new()
+        }
+    }
+
+    fn print_toml(&mut self, key: &mut ConfigKey, cv: &CV) -> CargoResult<()> {
+        match self.with_env(key, cv)? {
+            CV::Boolean(val, def) => println!("{} = {}{}", key, val, self.origin(&def)),
+            CV::Integer(val, def) => println!("{} = {}{}", key, val, self.origin(&def)),
+            CV::String(val, def) => println!(
+                "{} = {}{}",
+                key,
+                toml::Value::String(val),
+                self.origin(&def)
+            ),
+            CV::List(vals, _def) => {
+                if self.show_origin {
+                    println!("{} = [", key);
+                    for (val, def) in vals {
+                        println!("    {},{}", toml::Value::String(val), self.origin(&def));
+                    }
+                    println!("]");
+                } else {
+                    let vals = vals
+                        .into_iter()
+                        .map(|(val, _)| toml::Value::String(val))
+                        .collect();
+                    println!("{} = {}", key, toml::Value::Array(vals));
+                }
+            }
+            CV::Table(table, _def) => {
+                let mut entries = table.iter().collect::<Vec<_>>();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                for (table_key, val) in entries {
+                    key.push(table_key);
+                    self.print_toml(key, val)?;
+                    key.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn json(&mut self, key: &mut ConfigKey, cv: &CV) -> CargoResult<serde_json::Value> {
+        Ok(match self.with_env(key, cv)? {
+            CV::Boolean(val, _def) => json!(val),
+            CV::Integer(val, _def) => json!(val),
+            CV::String(val, _def) => json!(val),
+            CV::List(vals, _def) => json!(vals.into_iter().map(|(val, _)| val).collect::<Vec<_>>()),
+            CV::Table(table, _def) => {
+                let mut map = serde_json::Map::new();
+                for (table_key, val) in &table {
+                    key.push(table_key);
+                    map.insert(table_key.clone(), self.json(key, val)?);
+                    key.pop();
+                }
+                serde_json::Value::Object(map)
+            }
+        })
+    }
+}
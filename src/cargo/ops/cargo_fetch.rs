@@ -1,5 +1,5 @@
 use crate::core::compiler::{BuildConfig, CompileMode, TargetInfo};
-use crate::core::{PackageSet, Resolve, Workspace};
+use crate::core::{InternedString, PackageSet, Resolve, Workspace};
 use crate::ops;
 use crate::util::CargoResult;
 use crate::util::Config;
@@ -21,7 +21,13 @@ pub fn fetch<'a>(
 
     let jobs = Some(1);
     let config = ws.config();
-    let build_config = BuildConfig::new(config, jobs, &options.target, CompileMode::Build)?;
+    let build_config = BuildConfig::new(
+        config,
+        jobs,
+        &options.target,
+        InternedString::new("dev"),
+        CompileMode::Build,
+    )?;
     let rustc = config.load_global_rustc(Some(ws))?;
     let target_info = TargetInfo::new(
         config,
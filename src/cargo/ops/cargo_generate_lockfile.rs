@@ -1,15 +1,20 @@
 use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
 
-use log::debug;
-use termcolor::Color::{self, Cyan, Green, Red};
+use semver::{Version, VersionReq};
+use serde::{de, Deserialize};
+use termcolor::Color::Green;
+use toml::Spanned;
 
 use crate::core::registry::PackageRegistry;
 use crate::core::resolver::ResolveOpts;
-use crate::core::PackageId;
-use crate::core::{Resolve, SourceId, Workspace};
-use crate::ops;
+use crate::core::{Dependency, InternedString, Package, PackageId, PackageIdSpec, Registry};
+use crate::core::{Resolve, Workspace};
+use crate::ops::{self, LockfileDiff};
 use crate::util::config::Config;
-use crate::util::CargoResult;
+use crate::util::{paths, CargoResult, CargoResultExt};
 
 pub struct UpdateOptions<'a> {
     pub config: &'a Config,
@@ -17,20 +22,66 @@ pub struct UpdateOptions<'a> {
     pub precise: Option<&'a str>,
     pub aggressive: bool,
     pub dry_run: bool,
+    /// Resolves the direct dependencies of the workspace members to their
+    /// minimal versions.
+    pub direct_minimal_versions: bool,
+    /// Upgrades the version requirements of direct registry dependencies in
+    /// the manifests of the workspace members to the latest versions, even
+    /// when they are semver incompatible.
+    pub breaking: bool,
 }
 
 pub fn generate_lockfile(ws: &Workspace<'_>) -> CargoResult<()> {
+    generate_lockfile_with_opts(ws, &ResolveOpts::everything(), &[], false)
+}
+
+/// Generates the lock file, and writes the upgraded `manifests` along with
+/// it.
+fn generate_lockfile_with_opts(
+    ws: &Workspace<'_>,
+    opts: &ResolveOpts,
+    manifests: &[(PathBuf, String)],
+    dry_run: bool,
+) -> CargoResult<()> {
     let mut registry = PackageRegistry::new(ws.config())?;
-    let resolve = ops::resolve_with_previous(
-        &mut registry,
-        ws,
-        &ResolveOpts::everything(),
-        None,
-        None,
-        &[],
-        true,
-    )?;
-    ops::write_pkg_lockfile(ws, &resolve)?;
+    let resolve = ops::resolve_with_previous(&mut registry, ws, opts, None, None, &[], true)?;
+    if dry_run || ws.config().cli_unstable().lockfile_diff {
+        // The previous lock file is only displayed, so it's fine if it can't
+        // be loaded as it's replaced anyway.
+        let previous = ops::load_pkg_lockfile(ws)
+            .ok()
+            .and_then(|previous| previous);
+        LockfileDiff::new(previous.as_ref(), &resolve).print(ws.config())?;
+    }
+    ops::warn_deprecated(ws, &mut registry, &resolve)?;
+    write_changes(ws, &resolve, manifests, dry_run)
+}
+
+/// Writes the lock file and the upgraded `manifests`, unless this is a dry
+/// run.
+fn write_changes(
+    ws: &Workspace<'_>,
+    resolve: &Resolve,
+    manifests: &[(PathBuf, String)],
+    dry_run: bool,
+) -> CargoResult<()> {
+    if dry_run {
+        let files = if manifests.is_empty() {
+            "lockfile"
+        } else {
+            "manifests or lockfile"
+        };
+        return ws
+            .config()
+            .shell()
+            .warn(format!("not updating {} due to dry run", files));
+    }
+    // The lock file goes first, so that the manifests are left untouched
+    // when it can't be updated, like with `--locked`.
+    ops::write_pkg_lockfile(ws, resolve)?;
+    for (manifest_path, contents) in manifests {
+        paths::write(manifest_path, contents.as_bytes())?;
+    }
     Ok(())
 }
 
@@ -38,6 +89,9 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
     if opts.aggressive && opts.precise.is_some() {
         anyhow::bail!("cannot specify both aggressive and precise simultaneously")
     }
+    if opts.breaking && opts.precise.is_some() {
+        anyhow::bail!("cannot specify both breaking and precise simultaneously")
+    }
 
     if ws.members().count() == 0 {
         anyhow::bail!("you can't generate a lockfile for an empty workspace.")
@@ -51,11 +105,32 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
     // that we're synchronized against other Cargos.
     let _lock = ws.config().acquire_package_cache_lock()?;
 
+    // The requirements are upgraded first, and then the lock file is updated
+    // for the upgraded packages with the rewritten manifests. They are only
+    // written once the workspace has been resolved with them.
+    let upgraded_ws;
+    let mut upgraded = HashSet::new();
+    let mut manifests = Vec::new();
+    let ws = if opts.breaking {
+        let (packages, contents) = upgrade_manifests(ws, opts)?;
+        upgraded = packages;
+        manifests = contents;
+        upgraded_ws = ws.reload_with(&manifests)?;
+        &upgraded_ws
+    } else {
+        ws
+    };
+
+    let mut resolve_opts = ResolveOpts::everything();
+    resolve_opts.direct_minimal_versions = opts.direct_minimal_versions;
+
     let previous_resolve = match ops::load_pkg_lockfile(ws)? {
         Some(resolve) => resolve,
         None => {
             match opts.precise {
-                None => return generate_lockfile(ws),
+                None => {
+                    return generate_lockfile_with_opts(ws, &resolve_opts, &manifests, opts.dry_run)
+                }
 
                 // Precise option specified, so calculate a previous_resolve required
                 // by precise package update later.
@@ -64,7 +139,7 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
                     ops::resolve_with_previous(
                         &mut registry,
                         ws,
-                        &ResolveOpts::everything(),
+                        &resolve_opts,
                         None,
                         None,
                         &[],
@@ -77,7 +152,17 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
     let mut registry = PackageRegistry::new(opts.config)?;
     let mut to_avoid = HashSet::new();
 
-    if opts.to_update.is_empty() {
+    if opts.breaking {
+        for dep in previous_resolve.iter() {
+            if dep.source_id().is_registry() && upgraded.contains(&dep.name()) {
+                if opts.aggressive {
+                    fill_with_deps(&previous_resolve, dep, &mut to_avoid, &mut HashSet::new());
+                } else {
+                    to_avoid.insert(dep);
+                }
+            }
+        }
+    } else if opts.to_update.is_empty() {
         to_avoid.extend(previous_resolve.iter());
     } else {
         let mut sources = Vec::new();
@@ -110,7 +195,7 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
     let resolve = ops::resolve_with_previous(
         &mut registry,
         ws,
-        &ResolveOpts::everything(),
+        &resolve_opts,
         Some(&previous_resolve),
         Some(&to_avoid),
         &[],
@@ -118,37 +203,9 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
     )?;
 
     // Summarize what is changing for the user.
-    let print_change = |status: &str, msg: String, color: Color| {
-        opts.config.shell().status_with_color(status, msg, color)
-    };
-    for (removed, added) in compare_dependency_graphs(&previous_resolve, &resolve) {
-        if removed.len() == 1 && added.len() == 1 {
-            let msg = if removed[0].source_id().is_git() {
-                format!(
-                    "{} -> #{}",
-                    removed[0],
-                    &added[0].source_id().precise().unwrap()[..8]
-                )
-            } else {
-                format!("{} -> v{}", removed[0], added[0].version())
-            };
-            print_change("Updating", msg, Green)?;
-        } else {
-            for package in removed.iter() {
-                print_change("Removing", format!("{}", package), Red)?;
-            }
-            for package in added.iter() {
-                print_change("Adding", format!("{}", package), Cyan)?;
-            }
-        }
-    }
-    if opts.dry_run {
-        opts.config
-            .shell()
-            .warn("not updating lockfile due to dry run")?;
-    } else {
-        ops::write_pkg_lockfile(ws, &resolve)?;
-    }
+    LockfileDiff::new(Some(&previous_resolve), &resolve).print(opts.config)?;
+    ops::warn_deprecated(ws, &mut registry, &resolve)?;
+    write_changes(ws, &resolve, &manifests, opts.dry_run)?;
     return Ok(());
 
     fn fill_with_deps<'a>(
@@ -165,78 +222,275 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
             fill_with_deps(resolve, dep, set, visited);
         }
     }
+}
 
-    fn compare_dependency_graphs(
-        previous_resolve: &Resolve,
-        resolve: &Resolve,
-    ) -> Vec<(Vec<PackageId>, Vec<PackageId>)> {
-        fn key(dep: PackageId) -> (&'static str, SourceId) {
-            (dep.name().as_str(), dep.source_id())
-        }
+/// A version requirement in a manifest, as written by the user.
+struct ManifestRequirement {
+    /// The key of the dependency in the manifest.
+    name: String,
+    /// The location of the requirement, without the quotes.
+    span: Range<usize>,
+}
 
-        // Removes all package IDs in `b` from `a`. Note that this is somewhat
-        // more complicated because the equality for source IDs does not take
-        // precise versions into account (e.g., git shas), but we want to take
-        // that into account here.
-        fn vec_subtract(a: &[PackageId], b: &[PackageId]) -> Vec<PackageId> {
-            a.iter()
-                .filter(|a| {
-                    // If this package ID is not found in `b`, then it's definitely
-                    // in the subtracted set.
-                    let i = match b.binary_search(a) {
-                        Ok(i) => i,
-                        Err(..) => return true,
-                    };
-
-                    // If we've found `a` in `b`, then we iterate over all instances
-                    // (we know `b` is sorted) and see if they all have different
-                    // precise versions. If so, then `a` isn't actually in `b` so
-                    // we'll let it through.
-                    //
-                    // Note that we only check this for non-registry sources,
-                    // however, as registries contain enough version information in
-                    // the package ID to disambiguate.
-                    if a.source_id().is_registry() {
-                        return false;
-                    }
-                    b[i..]
-                        .iter()
-                        .take_while(|b| a == b)
-                        .all(|b| a.source_id().precise() != b.source_id().precise())
-                })
-                .cloned()
-                .collect()
+/// Upgrades the caret requirements of the direct registry dependencies of
+/// the workspace members, which are named in `opts.to_update` or all of them
+/// if it is empty, so that they match the latest published versions.
+///
+/// Only the requirement strings are changed in the contents of the manifests,
+/// which are not written. Returns the names of the packages whose
+/// requirements changed, and the new contents of the manifests which did.
+fn upgrade_manifests(
+    ws: &Workspace<'_>,
+    opts: &UpdateOptions<'_>,
+) -> CargoResult<(HashSet<InternedString>, Vec<(PathBuf, String)>)> {
+    let mut names = HashSet::new();
+    for spec in opts.to_update.iter() {
+        let spec = PackageIdSpec::parse(spec)?;
+        if spec.version().is_some() || spec.url().is_some() {
+            anyhow::bail!(
+                "the package specification `{}` with --breaking must only be a name",
+                spec
+            );
         }
+        names.insert(spec.name());
+    }
 
-        // Map `(package name, package source)` to `(removed versions, added versions)`.
-        let mut changes = BTreeMap::new();
-        let empty = (Vec::new(), Vec::new());
-        for dep in previous_resolve.iter() {
-            changes
-                .entry(key(dep))
-                .or_insert_with(|| empty.clone())
-                .0
-                .push(dep);
+    // The requested packages are checked before anything is upgraded.
+    let mut members = Vec::new();
+    let mut found = HashSet::new();
+    for member in ws.members() {
+        let contents = paths::read(member.manifest_path())?;
+        let requirements = find_requirements(&contents).chain_err(|| {
+            format!(
+                "failed to parse manifest at `{}`",
+                member.manifest_path().display()
+            )
+        })?;
+        let requirements = requirements
+            .into_iter()
+            .filter_map(|manifest_req| {
+                let dep = registry_dependency(member, &manifest_req.name)?;
+                if !names.is_empty() && !names.contains(&dep.package_name()) {
+                    return None;
+                }
+                found.insert(dep.package_name());
+                Some((manifest_req, dep))
+            })
+            .collect::<Vec<_>>();
+        members.push((member, contents, requirements));
+    }
+    let mut missing = names.difference(&found).collect::<Vec<_>>();
+    missing.sort();
+    if let Some(name) = missing.first() {
+        anyhow::bail!(
+            "package `{}` is not a registry dependency of any workspace member",
+            name
+        );
+    }
+
+    // Patches are not applied, as the requirements are upgraded to the
+    // versions published in the registries.
+    let mut registry = PackageRegistry::new(opts.config)?;
+    registry.lock_patches();
+    let mut upgraded = HashSet::new();
+    let mut manifests = Vec::new();
+    for (member, contents, requirements) in members {
+        let mut edits = Vec::new();
+        for (manifest_req, dep) in requirements {
+            let req = &contents[manifest_req.span.clone()];
+
+            let mut query = dep.clone();
+            query.set_version_req(VersionReq::any());
+            let latest = registry
+                .query_vec(&query, false)?
+                .into_iter()
+                .map(|summary| summary.version().clone())
+                .filter(|version| !version.is_prerelease())
+                .max();
+            let latest = match latest {
+                Some(latest) => latest,
+                None => continue,
+            };
+            match upgrade_requirement(req, &latest) {
+                Ok(Some(new_req)) => {
+                    opts.config.shell().status_with_color(
+                        "Upgrading",
+                        format!("{} {} -> {}", dep.package_name(), req, new_req),
+                        Green,
+                    )?;
+                    upgraded.insert(dep.package_name());
+                    edits.push((manifest_req.span, new_req));
+                }
+                Ok(None) => {}
+                Err(()) => {
+                    // Other requirements were deliberately chosen, so they
+                    // are only reported when the package was asked for.
+                    if names.contains(&dep.package_name()) {
+                        opts.config.shell().warn(format!(
+                            "not upgrading `{}` with requirement `{}` in `{}`, \
+                             only caret requirements are upgraded",
+                            dep.package_name(),
+                            req,
+                            member.manifest_path().display()
+                        ))?;
+                    }
+                }
+            }
         }
-        for dep in resolve.iter() {
-            changes
-                .entry(key(dep))
-                .or_insert_with(|| empty.clone())
-                .1
-                .push(dep);
+        if !edits.is_empty() {
+            let mut contents = contents;
+            for (span, new_req) in edits.into_iter().rev() {
+                contents.replace_range(span, &new_req);
+            }
+            manifests.push((member.manifest_path().to_path_buf(), contents));
         }
+    }
+    Ok((upgraded, manifests))
+}
+
+/// Returns the registry dependency of `member` with the key `name` in its
+/// manifest.
+fn registry_dependency<'a>(member: &'a Package, name: &str) -> Option<&'a Dependency> {
+    member
+        .dependencies()
+        .iter()
+        .find(|dep| dep.name_in_toml() == name && dep.source_id().is_registry())
+}
+
+/// Returns the requirement matching `latest` with the same precision as
+/// `req`, such as `2.0` for `1.2`, or `None` if `req` already matches it.
+///
+/// Returns an error if `req` is not a caret requirement.
+fn upgrade_requirement(req: &str, latest: &Version) -> Result<Option<String>, ()> {
+    let (caret, version) = match req.trim() {
+        r if r.starts_with('^') => ("^", r[1..].trim()),
+        r => ("", r),
+    };
+    let parts = version
+        .split('.')
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ())?;
+    if parts.is_empty() || parts.len() > 3 {
+        return Err(());
+    }
+    let current = VersionReq::parse(req).map_err(|_| ())?;
+    if current.matches(latest) {
+        return Ok(None);
+    }
+    let latest_parts = [latest.major, latest.minor, latest.patch];
+    if latest_parts[..parts.len()] < parts[..] {
+        return Ok(None);
+    }
+    let new_req = latest_parts[..parts.len()]
+        .iter()
+        .map(|part| part.to_string())
+        .collect::<Vec<_>>()
+        .join(".");
+    Ok(Some(format!("{}{}", caret, new_req)))
+}
 
-        for v in changes.values_mut() {
-            let (ref mut old, ref mut new) = *v;
-            old.sort();
-            new.sort();
-            let removed = vec_subtract(old, new);
-            let added = vec_subtract(new, old);
-            *old = removed;
-            *new = added;
+/// Finds the version requirements of the dependencies in a manifest.
+///
+/// The manifest is parsed keeping the location of each requirement, so that
+/// they can be rewritten without touching the rest of the file, such as
+/// comments and formatting.
+fn find_requirements(contents: &str) -> CargoResult<Vec<ManifestRequirement>> {
+    type Dependencies = BTreeMap<String, Spanned<ManifestDependency>>;
+
+    /// The dependency tables of a manifest, or of a `[target]` table.
+    #[derive(Deserialize)]
+    struct DependencyTables {
+        dependencies: Option<Dependencies>,
+        #[serde(rename = "dev-dependencies")]
+        dev_dependencies: Option<Dependencies>,
+        #[serde(rename = "dev_dependencies")]
+        dev_dependencies2: Option<Dependencies>,
+        #[serde(rename = "build-dependencies")]
+        build_dependencies: Option<Dependencies>,
+        #[serde(rename = "build_dependencies")]
+        build_dependencies2: Option<Dependencies>,
+        target: Option<BTreeMap<String, DependencyTables>>,
+    }
+
+    /// A dependency, along with the location of its `version` key if it is
+    /// written as a table.
+    enum ManifestDependency {
+        Simple,
+        Detailed(Option<Spanned<String>>),
+    }
+
+    impl<'de> de::Deserialize<'de> for ManifestDependency {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            struct Visitor;
+
+            impl<'de> de::Visitor<'de> for Visitor {
+                type Value = ManifestDependency;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    formatter.write_str("a version string or a table")
+                }
+
+                fn visit_str<E: de::Error>(self, _s: &str) -> Result<Self::Value, E> {
+                    Ok(ManifestDependency::Simple)
+                }
+
+                fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
+                where
+                    V: de::MapAccess<'de>,
+                {
+                    let mut version = None;
+                    while let Some(key) = map.next_key::<String>()? {
+                        if key == "version" {
+                            version = Some(map.next_value()?);
+                        } else {
+                            map.next_value::<de::IgnoredAny>()?;
+                        }
+                    }
+                    Ok(ManifestDependency::Detailed(version))
+                }
+            }
+
+            deserializer.deserialize_any(Visitor)
         }
-        debug!("{:#?}", changes);
+    }
 
-        changes.into_iter().map(|(_, v)| v).collect()
+    let manifest: DependencyTables = toml::from_str(contents)?;
+    let tables = Some(&manifest)
+        .into_iter()
+        .chain(manifest.target.iter().flat_map(|target| target.values()));
+    let mut requirements = Vec::new();
+    for tables in tables {
+        let deps = vec![
+            &tables.dependencies,
+            &tables.dev_dependencies,
+            &tables.dev_dependencies2,
+            &tables.build_dependencies,
+            &tables.build_dependencies2,
+        ];
+        for (name, dep) in deps.into_iter().flatten().flatten() {
+            let (start, end) = match dep.get_ref() {
+                ManifestDependency::Simple => (dep.start(), dep.end()),
+                ManifestDependency::Detailed(Some(version)) => (version.start(), version.end()),
+                ManifestDependency::Detailed(None) => continue,
+            };
+            // Leave out the quotes, which are tripled for multi-line strings.
+            let quotes = if contents[start..].starts_with("\"\"\"")
+                || contents[start..].starts_with("'''")
+            {
+                3
+            } else {
+                1
+            };
+            requirements.push(ManifestRequirement {
+                name: name.clone(),
+                span: start + quotes..end - quotes,
+            });
+        }
     }
+    requirements.sort_by_key(|req| req.span.start);
+    Ok(requirements)
 }
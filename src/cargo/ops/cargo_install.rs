@@ -325,7 +325,7 @@ fn install_one(
     let mut binaries: Vec<(&str, &Path)> = compile
         .binaries
         .iter()
-        .map(|bin| {
+        .map(|(_, _, bin)| {
             let name = bin.file_name().unwrap();
             if let Some(s) = name.to_str() {
                 Ok((s, bin.as_ref()))
@@ -0,0 +1,255 @@
+//! Implementation of the `cargo outdated` subcommand.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::bail;
+use semver::{Version, VersionReq};
+use serde::Serialize;
+
+use crate::core::dependency::DepKind;
+use crate::core::registry::PackageRegistry;
+use crate::core::{Dependency, InternedString, PackageId, Registry, SourceId, Workspace};
+use crate::ops;
+use crate::util::CargoResult;
+
+/// The format to print the report in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutdatedFormat {
+    /// A table for humans.
+    Human,
+    /// A single JSON object.
+    Json,
+}
+
+impl OutdatedFormat {
+    pub const POSSIBLE_VALUES: &'static [&'static str] = &["human", "json"];
+}
+
+impl FromStr for OutdatedFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> CargoResult<OutdatedFormat> {
+        match s {
+            "human" => Ok(OutdatedFormat::Human),
+            "json" => Ok(OutdatedFormat::Json),
+            f => bail!("unknown outdated format `{}`", f),
+        }
+    }
+}
+
+impl fmt::Display for OutdatedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutdatedFormat::Human => "human".fmt(f),
+            OutdatedFormat::Json => "json".fmt(f),
+        }
+    }
+}
+
+pub struct OutdatedOptions {
+    pub format: OutdatedFormat,
+    /// Only reports the dependencies of the workspace members, instead of
+    /// every dependency edge in the graph.
+    pub root_deps_only: bool,
+}
+
+/// A dependency edge with a newer version of the dependency available.
+#[derive(Serialize)]
+struct OutdatedDep {
+    name: InternedString,
+    /// The package which depends on it.
+    parent: PackageId,
+    /// The version requirements of the edge.
+    requirement: String,
+    kinds: BTreeSet<&'static str>,
+    locked: Version,
+    /// The greatest version matching the requirements.
+    compatible: Version,
+    /// The greatest version overall.
+    latest: Version,
+}
+
+/// Reports the dependencies of the workspace with newer versions in their
+/// registries than the locked versions.
+///
+/// Returns whether any dependency is outdated.
+pub fn outdated(ws: &Workspace<'_>, opts: &OutdatedOptions) -> CargoResult<bool> {
+    let config = ws.config();
+    let (packages, resolve) = ops::resolve_ws(ws)?;
+
+    // Patches are not applied, as the newest versions published in the
+    // registries are what's interesting here.
+    let _lock = config.acquire_package_cache_lock()?;
+    let mut registry = PackageRegistry::new(config)?;
+    registry.lock_patches();
+
+    let members = ws
+        .members()
+        .map(|pkg| pkg.package_id())
+        .collect::<HashSet<_>>();
+    let mut published: HashMap<(InternedString, SourceId), Vec<Version>> = HashMap::new();
+    let mut outdated = Vec::new();
+    for parent in resolve.iter() {
+        if opts.root_deps_only && !members.contains(&parent) {
+            continue;
+        }
+        // The dependencies in the resolve are locked to exact versions, so
+        // the requirements are taken from the parent itself.
+        let parent_deps = if parent.source_id().is_registry() {
+            let query = Dependency::parse_no_deprecated(
+                parent.name(),
+                Some(&format!("={}", parent.version())),
+                parent.source_id().with_precise(None),
+            )?;
+            match registry.query_vec(&query, false)?.into_iter().next() {
+                Some(summary) => summary.dependencies().to_vec(),
+                None => continue,
+            }
+        } else {
+            packages.get_one(parent)?.dependencies().to_vec()
+        };
+        for (dep_id, locked_deps) in resolve.deps(parent) {
+            // Other sources don't have multiple versions of a package.
+            if !dep_id.source_id().is_registry() {
+                continue;
+            }
+            let source_id = dep_id.source_id().with_precise(None);
+            let key = (dep_id.name(), source_id);
+            if let Entry::Vacant(entry) = published.entry(key) {
+                // The index is only read from the disk with `--offline`,
+                // and all the versions it lists are included then, even
+                // those which were never downloaded.
+                let query = Dependency::parse_no_deprecated(dep_id.name(), None, source_id)?;
+                let versions = registry
+                    .query_published(&query)?
+                    .into_iter()
+                    .filter(|summary| summary.name() == dep_id.name())
+                    .map(|summary| summary.version().clone())
+                    .collect();
+                entry.insert(versions);
+            }
+            let versions = &published[&key];
+            let deps = parent_deps
+                .iter()
+                .filter(|dep| {
+                    locked_deps.iter().any(|locked| {
+                        locked.name_in_toml() == dep.name_in_toml()
+                            && locked.kind() == dep.kind()
+                            && locked.platform() == dep.platform()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let locked = dep_id.version();
+            let reqs = deps
+                .iter()
+                .map(|dep| dep.version_req())
+                .collect::<Vec<&VersionReq>>();
+            let compatible = versions
+                .iter()
+                .filter(|version| reqs.iter().all(|req| req.matches(version)))
+                .chain(Some(locked))
+                .max()
+                .unwrap();
+            // Pre-releases are only considered if one is already used.
+            let latest = versions
+                .iter()
+                .filter(|version| !version.is_prerelease() || locked.is_prerelease())
+                .chain(Some(compatible))
+                .max()
+                .unwrap();
+            if latest == locked {
+                continue;
+            }
+
+            let mut requirement = reqs.iter().map(|req| req.to_string()).collect::<Vec<_>>();
+            requirement.sort();
+            requirement.dedup();
+            outdated.push(OutdatedDep {
+                name: dep_id.name(),
+                parent,
+                requirement: requirement.join(", "),
+                kinds: deps
+                    .iter()
+                    .map(|dep| match dep.kind() {
+                        DepKind::Normal => "normal",
+                        DepKind::Development => "dev",
+                        DepKind::Build => "build",
+                    })
+                    .collect(),
+                locked: locked.clone(),
+                compatible: compatible.clone(),
+                latest: latest.clone(),
+            });
+        }
+    }
+    outdated.sort_by_key(|dep| (dep.name, dep.parent));
+
+    match opts.format {
+        OutdatedFormat::Human => {
+            if outdated.is_empty() {
+                config.shell().note("all dependencies are up to date")?;
+            } else {
+                print_table(&outdated);
+            }
+        }
+        OutdatedFormat::Json => {
+            #[derive(Serialize)]
+            struct Report<'a> {
+                outdated: &'a [OutdatedDep],
+            }
+            let report = Report {
+                outdated: &outdated,
+            };
+            println!("{}", serde_json::to_string(&report)?);
+        }
+    }
+    Ok(!outdated.is_empty())
+}
+
+fn print_table(outdated: &[OutdatedDep]) {
+    // Versions which aren't newer than the previous column are not repeated.
+    let newer = |version: &Version, previous: &Version| {
+        if version == previous {
+            "-".to_string()
+        } else {
+            version.to_string()
+        }
+    };
+    let mut rows = vec![[
+        "Name".to_string(),
+        "Locked".to_string(),
+        "Compatible".to_string(),
+        "Latest".to_string(),
+        "Kind".to_string(),
+        "Dependent".to_string(),
+    ]];
+    for dep in outdated {
+        rows.push([
+            dep.name.to_string(),
+            dep.locked.to_string(),
+            newer(&dep.compatible, &dep.locked),
+            newer(&dep.latest, &dep.compatible),
+            dep.kinds.iter().cloned().collect::<Vec<_>>().join(", "),
+            format!("{} v{}", dep.parent.name(), dep.parent.version()),
+        ]);
+    }
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+}
@@ -13,7 +13,7 @@ use log::debug;
 use tar::{Archive, Builder, EntryType, Header};
 
 use crate::core::compiler::{BuildConfig, CompileMode, DefaultExecutor, Executor};
-use crate::core::{Feature, Shell, Verbosity, Workspace};
+use crate::core::{Feature, InternedString, Shell, Verbosity, Workspace};
 use crate::core::{Package, PackageId, PackageSet, Resolve, Source, SourceId};
 use crate::ops;
 use crate::sources::PathSource;
@@ -705,7 +705,13 @@ fn run_verify(ws: &Workspace<'_>, tar: &FileLock, opts: &PackageOpts<'_>) -> Car
         &ws,
         &ops::CompileOptions {
             config,
-            build_config: BuildConfig::new(config, opts.jobs, &opts.target, CompileMode::Build)?,
+            build_config: BuildConfig::new(
+                config,
+                opts.jobs,
+                &opts.target,
+                InternedString::new("dev"),
+                CompileMode::Build,
+            )?,
             features: opts.features.clone(),
             no_default_features: opts.no_default_features,
             all_features: opts.all_features,
@@ -718,6 +724,7 @@ fn run_verify(ws: &Workspace<'_>, tar: &FileLock, opts: &PackageOpts<'_>) -> Car
             local_rustdoc_args: None,
             rustdoc_document_private_items: false,
             export_dir: None,
+            watch: None,
         },
         &exec,
     )?;
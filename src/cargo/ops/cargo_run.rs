@@ -1,17 +1,86 @@
 use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::iter;
 use std::path::Path;
+use std::process::{Child, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
+use termcolor::{BufferWriter, Color, ColorSpec, WriteColor};
+
+use crate::core::shell::ColorChoice;
 use crate::core::{TargetKind, Workspace};
 use crate::ops;
-use crate::util::{CargoResult, ProcessError};
+use crate::util::errors::CargoResultExt;
+use crate::util::{process_error, CargoResult, Config, ProcessBuilder, ProcessError};
+
+/// The colors of the names of the executables run with `-Z run-multiple`.
+const COLORS: &[Color] = &[
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Blue,
+    Color::Green,
+    Color::Red,
+];
+
+/// How long the executables still running when another one fails get to exit
+/// after being terminated, before they are killed.
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
 
-pub fn run(
-    ws: &Workspace<'_>,
-    options: &ops::CompileOptions<'_>,
+pub fn run<'a>(
+    ws: &Workspace<'a>,
+    options: &ops::CompileOptions<'a>,
     args: &[OsString],
 ) -> CargoResult<Option<ProcessError>> {
     let config = ws.config();
+    let mut processes = build(ws, options, args)?;
+    if processes.len() > 1 {
+        assert!(config.cli_unstable().run_multiple);
+        return run_concurrently(config, processes);
+    }
+    assert_eq!(processes.len(), 1);
+    let (_, process) = processes.pop().unwrap();
+
+    config.shell().status("Running", process.to_string())?;
+
+    let result = process.exec_replace();
+
+    match result {
+        Ok(()) => Ok(None),
+        Err(e) => {
+            let err = e.downcast::<ProcessError>()?;
+            Ok(Some(err))
+        }
+    }
+}
+
+/// Builds the executable for `cargo run --watch`, and returns the process to
+/// run it, which is left to the caller.
+pub fn prepare_run<'a>(
+    ws: &Workspace<'a>,
+    options: &ops::CompileOptions<'a>,
+    args: &[OsString],
+) -> CargoResult<ProcessBuilder> {
+    let mut processes = build(ws, options, args)?;
+    if processes.len() > 1 {
+        anyhow::bail!(
+            "`cargo run --watch` can run at most one executable, but \
+             multiple were specified"
+        )
+    }
+    Ok(processes.pop().unwrap().1)
+}
+
+/// Builds the executables to run, and returns their names along with the
+/// processes to run them.
+fn build<'a>(
+    ws: &Workspace<'a>,
+    options: &ops::CompileOptions<'a>,
+    args: &[OsString],
+) -> CargoResult<Vec<(String, ProcessBuilder)>> {
+    let config = ws.config();
 
     // We compute the `bins` here *just for diagnosis*. The actual set of
     // packages to be run is determined by the `ops::compile` call below.
@@ -39,13 +108,14 @@ pub fn run(
         }
     }
 
-    if bins.len() == 1 {
-        let target = bins[0].1;
-        if let TargetKind::ExampleLib(..) = target.kind() {
-            anyhow::bail!(
-                "example target `{}` is a library and cannot be executed",
-                target.name()
-            )
+    if bins.len() == 1 || config.cli_unstable().run_multiple {
+        for (_, target) in &bins {
+            if let TargetKind::ExampleLib(..) = target.kind() {
+                anyhow::bail!(
+                    "example target `{}` is a library and cannot be executed",
+                    target.name()
+                )
+            }
         }
     }
 
@@ -62,7 +132,7 @@ pub fn run(
                  available binaries: {}",
                 names.join(", ")
             )
-        } else {
+        } else if !config.cli_unstable().run_multiple {
             anyhow::bail!(
                 "`cargo run` can run at most one executable, but \
                  multiple were specified"
@@ -71,26 +141,275 @@ pub fn run(
     }
 
     let compile = ops::compile(ws, options)?;
-    assert_eq!(compile.binaries.len(), 1);
-    let exe = &compile.binaries[0];
-    let exe = match exe.strip_prefix(config.cwd()) {
-        Ok(path) if path.file_name() == Some(path.as_os_str()) => Path::new(".").join(path),
-        Ok(path) => path.to_path_buf(),
-        Err(_) => exe.to_path_buf(),
-    };
-    let pkg = bins[0].0;
-    let mut process = compile.target_process(exe, pkg)?;
-    process.args(args).cwd(config.cwd());
+    compile
+        .binaries
+        .iter()
+        .map(|(pkg, target, exe)| {
+            let exe = match exe.strip_prefix(config.cwd()) {
+                Ok(path) if path.file_name() == Some(path.as_os_str()) => Path::new(".").join(path),
+                Ok(path) => path.to_path_buf(),
+                Err(_) => exe.to_path_buf(),
+            };
+            let mut process = compile.target_process(exe, pkg)?;
+            process.args(args).cwd(config.cwd());
+            Ok((target.name().to_string(), process))
+        })
+        .collect()
+}
 
-    config.shell().status("Running", process.to_string())?;
+/// A running executable of `-Z run-multiple`.
+struct Running {
+    process: ProcessBuilder,
+    child: Child,
+}
 
-    let result = process.exec_replace();
+/// Runs several executables at once, with each line of their output prefixed
+/// with their name, until they have all exited. When one of them fails, the
+/// others are terminated, then killed if they are still running after
+/// `KILL_TIMEOUT`, and its error is returned.
+///
+/// Cargo waits for them instead of exiting when it gets a signal to
+/// terminate, and forwards it to them.
+fn run_concurrently(
+    config: &Config,
+    processes: Vec<(String, ProcessBuilder)>,
+) -> CargoResult<Option<ProcessError>> {
+    let width = processes.iter().map(|(name, _)| name.len()).max().unwrap();
+    let choice = config.shell().color_choice();
+    let stdout = Arc::new(BufferWriter::stdout(color_choice(
+        choice,
+        atty::Stream::Stdout,
+    )));
+    let stderr = Arc::new(BufferWriter::stderr(color_choice(
+        choice,
+        atty::Stream::Stderr,
+    )));
 
-    match result {
-        Ok(()) => Ok(None),
-        Err(e) => {
-            let err = e.downcast::<ProcessError>()?;
-            Ok(Some(err))
+    // The statuses are printed before anything runs, as the output of the
+    // executables doesn't go through the shell.
+    for (_name, process) in processes.iter() {
+        config.shell().status("Running", process.to_string())?;
+    }
+
+    imp::forward_signals()?;
+    let mut running = Vec::new();
+    let mut forwarders = Vec::new();
+    for (i, (name, process)) in processes.into_iter().enumerate() {
+        let mut cmd = process.build_command();
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        imp::prepare(&mut cmd);
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                for r in running.iter_mut() {
+                    imp::terminate(r);
+                }
+                return Err(e).chain_err(|| {
+                    process_error(
+                        &format!("could not execute process {}", process),
+                        None,
+                        None,
+                    )
+                });
+            }
+        };
+        let prefix = format!("{:<width$} |", name, width = width);
+        let color = COLORS[i % COLORS.len()];
+        let out = child.stdout.take().unwrap();
+        let err = child.stderr.take().unwrap();
+        forwarders.push(forward(out, Arc::clone(&stdout), prefix.clone(), color));
+        forwarders.push(forward(err, Arc::clone(&stderr), prefix, color));
+        running.push(Running { process, child });
+    }
+
+    let mut failure = None;
+    let mut terminated = None;
+    while !running.is_empty() {
+        if let Some(signal) = imp::take_signal() {
+            for r in running.iter_mut() {
+                imp::signal(r, signal);
+            }
+        }
+        let mut i = 0;
+        while i < running.len() {
+            let status = match running[i].child.try_wait()? {
+                Some(status) => status,
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let r = running.remove(i);
+            if !status.success() && failure.is_none() {
+                failure = Some(process_error(
+                    &format!("process didn't exit successfully: {}", r.process),
+                    Some(status),
+                    None,
+                ));
+                for r in running.iter_mut() {
+                    imp::terminate(r);
+                }
+                terminated = Some(Instant::now());
+            }
+        }
+        if terminated.map_or(false, |at| at.elapsed() >= KILL_TIMEOUT) {
+            for r in running.iter_mut() {
+                imp::kill(r);
+            }
+            terminated = None;
         }
+        thread::sleep(Duration::from_millis(50));
+    }
+    for forwarder in forwarders {
+        let _ = forwarder.join();
+    }
+    Ok(failure)
+}
+
+/// Copies the lines read from `from` to `to`, prefixed with `prefix`.
+fn forward(
+    from: impl Read + Send + 'static,
+    to: Arc<BufferWriter>,
+    prefix: String,
+    color: Color,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut from = BufReader::new(from);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match from.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            if !line.ends_with(b"\n") {
+                line.push(b'\n');
+            }
+            let mut buffer = to.buffer();
+            let _ = buffer.set_color(ColorSpec::new().set_fg(Some(color)).set_bold(true));
+            let _ = write!(buffer, "{}", prefix);
+            let _ = buffer.reset();
+            let _ = buffer.write_all(b" ");
+            let _ = buffer.write_all(&line);
+            let _ = to.print(&buffer);
+        }
+    })
+}
+
+/// Converts the color choice of the shell to the one of `stream`.
+fn color_choice(choice: ColorChoice, stream: atty::Stream) -> termcolor::ColorChoice {
+    match choice {
+        ColorChoice::Always => termcolor::ColorChoice::Always,
+        ColorChoice::Never => termcolor::ColorChoice::Never,
+        ColorChoice::CargoAuto if atty::is(stream) => termcolor::ColorChoice::Auto,
+        ColorChoice::CargoAuto => termcolor::ColorChoice::Never,
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::Running;
+    use crate::util::CargoResult;
+
+    /// The signals which are forwarded.
+    const SIGNALS: &[libc::c_int] = &[libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGQUIT];
+
+    /// The last signal received, or 0.
+    static SIGNAL: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn handler(signal: libc::c_int) {
+        SIGNAL.store(signal as usize, Ordering::SeqCst);
+    }
+
+    pub fn forward_signals() -> CargoResult<()> {
+        for &signal in SIGNALS {
+            let handler = handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            if unsafe { libc::signal(signal, handler) } == libc::SIG_ERR {
+                return Err(std::io::Error::last_os_error().into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn take_signal() -> Option<libc::c_int> {
+        match SIGNAL.swap(0, Ordering::SeqCst) {
+            0 => None,
+            signal => Some(signal as libc::c_int),
+        }
+    }
+
+    /// Puts the executable in its own process group, so that the signals
+    /// sent to the group of Cargo, like when pressing Ctrl-C, only reach it
+    /// once, through Cargo.
+    pub fn prepare(cmd: &mut Command) {
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::setpgid(0, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    pub fn signal(running: &mut Running, signal: libc::c_int) {
+        unsafe {
+            libc::kill(-(running.child.id() as libc::pid_t), signal);
+        }
+    }
+
+    pub fn terminate(running: &mut Running) {
+        signal(running, libc::SIGTERM);
+    }
+
+    pub fn kill(running: &mut Running) {
+        signal(running, libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::process::Command;
+
+    use winapi::shared::minwindef::{BOOL, DWORD, FALSE, TRUE};
+    use winapi::um::consoleapi::SetConsoleCtrlHandler;
+
+    use super::Running;
+    use crate::util::{process_error, CargoResult};
+
+    unsafe extern "system" fn ctrlc_handler(_: DWORD) -> BOOL {
+        // Do nothing; the executables get Ctrl-C from the console as well.
+        TRUE
+    }
+
+    pub fn forward_signals() -> CargoResult<()> {
+        unsafe {
+            if SetConsoleCtrlHandler(Some(ctrlc_handler), TRUE) == FALSE {
+                return Err(process_error("Could not set Ctrl-C handler.", None, None).into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn take_signal() -> Option<()> {
+        None
+    }
+
+    pub fn prepare(_cmd: &mut Command) {}
+
+    pub fn signal(_running: &mut Running, _signal: ()) {}
+
+    pub fn terminate(running: &mut Running) {
+        let _ = running.child.kill();
+    }
+
+    pub fn kill(running: &mut Running) {
+        terminate(running);
     }
 }
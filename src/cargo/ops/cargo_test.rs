@@ -1,36 +1,151 @@
-use std::ffi::OsString;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::core::compiler::{Compilation, Doctest};
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+use crate::core::compiler::{Compilation, TestRunner};
 use crate::core::shell::Verbosity;
-use crate::core::Workspace;
+use crate::core::{Package, Target, TargetKind, Workspace};
 use crate::ops;
 use crate::util::errors::CargoResult;
-use crate::util::{CargoTestError, ProcessError, Test};
+use crate::util::{paths, CargoTestError, ProcessError, Test};
+
+/// The file in the target directory which records how long each test
+/// executable and doc test took to run, used to balance the shards.
+const TEST_DURATIONS_FILE: &str = ".test-durations.json";
+
+/// The file in the target directory which records how long the tests of one
+/// shard took to run. It is never read by Cargo, so that a shard doesn't
+/// change the durations the next shards are partitioned with.
+fn shard_durations_file(shard: TestShard) -> String {
+    format!(".test-durations.shard-{}.json", shard.index)
+}
 
 pub struct TestOptions<'a> {
     pub compile_opts: ops::CompileOptions<'a>,
     pub no_run: bool,
     pub no_fail_fast: bool,
+    /// Only runs the test executables and doc tests of one shard.
+    pub shard: Option<TestShard>,
 }
 
-pub fn run_tests(
-    ws: &Workspace<'_>,
-    options: &TestOptions<'_>,
+/// One of several shards which the tests are split into, with
+/// `cargo test --shard N/M`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TestShard {
+    /// The 1-based index of this shard.
+    pub index: usize,
+    /// The total number of shards.
+    pub count: usize,
+}
+
+impl FromStr for TestShard {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> CargoResult<TestShard> {
+        let mut parts = s.splitn(2, '/');
+        let index = parts.next().unwrap().parse::<usize>();
+        let count = parts.next().map(|count| count.parse::<usize>());
+        match (index, count) {
+            (Ok(index), Some(Ok(count))) if index >= 1 && index <= count => {
+                Ok(TestShard { index, count })
+            }
+            _ => bail!(
+                "invalid shard `{}`, expected `N/M` where `N` is between 1 and `M`",
+                s
+            ),
+        }
+    }
+}
+
+pub fn run_tests<'a>(
+    ws: &Workspace<'a>,
+    options: &TestOptions<'a>,
     test_args: &[&str],
 ) -> CargoResult<Option<CargoTestError>> {
+    // With `-Z parallel-tests` the test executables are run by the build
+    // itself, as soon as each of them has been linked, and so are the doc
+    // tests with `-Z merged-doctests`, as soon as the crate has been built.
+    //
+    // Sharding needs to know about all of the tests up front, so they are
+    // run after the build in that case.
+    let unstable = ws.config().cli_unstable();
+    let runner = if (unstable.parallel_tests || unstable.merged_doctests)
+        && !options.no_run
+        && options.shard.is_none()
+    {
+        let mut runner = TestRunner::new(test_args, options.no_fail_fast);
+        runner.executables = unstable.parallel_tests;
+        runner.doc_tests = unstable.merged_doctests;
+        let runner = Arc::new(runner);
+        *options.compile_opts.build_config.test_runner.borrow_mut() = Some(runner.clone());
+        Some(runner)
+    } else {
+        None
+    };
     let compilation = compile_tests(ws, options)?;
 
     if options.no_run {
         return Ok(None);
     }
-    let (test, mut errors) = run_unit_tests(options, test_args, &compilation)?;
+    let mut shard = match options.shard {
+        Some(shard) => Some(ShardPlan::new(ws, shard, &compilation)?),
+        None => None,
+    };
+    let result = run_shard(options, test_args, &compilation, runner, shard.as_mut());
+    if let Some(shard) = shard {
+        shard.save()?;
+    }
+    result
+}
+
+/// Runs the unit, integration and doc tests which were compiled, or only the
+/// ones in the given shard.
+fn run_shard(
+    options: &TestOptions<'_>,
+    test_args: &[&str],
+    compilation: &Compilation<'_>,
+    runner: Option<Arc<TestRunner>>,
+    mut shard: Option<&mut ShardPlan>,
+) -> CargoResult<Option<CargoTestError>> {
+    let (failures, doc_failures): (Vec<_>, Vec<_>) = match &runner {
+        Some(runner) => runner.take_failures().into_iter().partition(|f| !f.doc),
+        None => (Vec::new(), Vec::new()),
+    };
+    let doc_errors = doc_failures
+        .into_iter()
+        .map(|f| f.error)
+        .collect::<Vec<_>>();
+
+    // The doc tests run by the build may have failed before any test
+    // executable ran.
+    if !doc_errors.is_empty() && !options.no_fail_fast {
+        return Ok(Some(CargoTestError::new(Test::Doc, doc_errors)));
+    }
+
+    let (test, mut errors) = match &runner {
+        Some(runner) if runner.executables => unit_test_errors(
+            failures
+                .into_iter()
+                .map(|f| (f.kind, f.name, f.pkg_name, f.error))
+                .collect(),
+        ),
+        _ => run_unit_tests(options, test_args, compilation, shard.as_deref_mut())?,
+    };
 
     // If we have an error and want to fail fast, then return.
     if !errors.is_empty() && !options.no_fail_fast {
         return Ok(Some(CargoTestError::new(test, errors)));
     }
 
-    let (doctest, docerrors) = run_doc_tests(options, test_args, &compilation)?;
+    let (doctest, docerrors) = match &runner {
+        Some(runner) if runner.doc_tests => (Test::Doc, doc_errors),
+        _ => run_doc_tests(options, test_args, compilation, shard)?,
+    };
     let test = if docerrors.is_empty() { test } else { doctest };
     errors.extend(docerrors);
     if errors.is_empty() {
@@ -40,9 +155,9 @@ pub fn run_tests(
     }
 }
 
-pub fn run_benches(
-    ws: &Workspace<'_>,
-    options: &TestOptions<'_>,
+pub fn run_benches<'a>(
+    ws: &Workspace<'a>,
+    options: &TestOptions<'a>,
     args: &[&str],
 ) -> CargoResult<Option<CargoTestError>> {
     let compilation = compile_tests(ws, options)?;
@@ -54,7 +169,7 @@ pub fn run_benches(
     let mut args = args.to_vec();
     args.push("--bench");
 
-    let (test, errors) = run_unit_tests(options, &args, &compilation)?;
+    let (test, errors) = run_unit_tests(options, &args, &compilation, None)?;
 
     match errors.len() {
         0 => Ok(None),
@@ -78,6 +193,7 @@ fn run_unit_tests(
     options: &TestOptions<'_>,
     test_args: &[&str],
     compilation: &Compilation<'_>,
+    mut shard: Option<&mut ShardPlan>,
 ) -> CargoResult<(Test, Vec<ProcessError>)> {
     let config = options.compile_opts.config;
     let cwd = options.compile_opts.config.cwd();
@@ -85,6 +201,12 @@ fn run_unit_tests(
     let mut errors = Vec::new();
 
     for &(ref pkg, ref target, ref exe) in &compilation.tests {
+        let key = test_key(pkg, target);
+        if let Some(shard) = &shard {
+            if !shard.includes(&key) {
+                continue;
+            }
+        }
         let kind = target.kind();
         let test = target.name().to_string();
         let exe_display = exe.strip_prefix(cwd).unwrap_or(exe).display();
@@ -100,7 +222,11 @@ fn run_unit_tests(
             .shell()
             .verbose(|shell| shell.status("Running", &cmd))?;
 
+        let start = Instant::now();
         let result = cmd.exec();
+        if let Some(shard) = shard.as_mut() {
+            shard.record(key, start.elapsed());
+        }
 
         match result {
             Err(e) => {
@@ -114,21 +240,28 @@ fn run_unit_tests(
         }
     }
 
+    Ok(unit_test_errors(errors))
+}
+
+/// Determines which test to blame for the failures of test executables.
+fn unit_test_errors(
+    mut errors: Vec<(TargetKind, String, String, ProcessError)>,
+) -> (Test, Vec<ProcessError>) {
     if errors.len() == 1 {
         let (kind, name, pkg_name, e) = errors.pop().unwrap();
-        Ok((
+        (
             Test::UnitTest {
                 kind,
                 name,
                 pkg_name,
             },
             vec![e],
-        ))
+        )
     } else {
-        Ok((
+        (
             Test::Multiple,
             errors.into_iter().map(|(_, _, _, e)| e).collect(),
-        ))
+        )
     }
 }
 
@@ -136,95 +269,161 @@ fn run_doc_tests(
     options: &TestOptions<'_>,
     test_args: &[&str],
     compilation: &Compilation<'_>,
+    mut shard: Option<&mut ShardPlan>,
 ) -> CargoResult<(Test, Vec<ProcessError>)> {
     let mut errors = Vec::new();
     let config = options.compile_opts.config;
 
-    // The unstable doctest-xcompile feature enables both per-target-ignores and
-    // cross-compiling doctests. As a side effect, this feature also gates running
-    // doctests with runtools when target == host.
-    let doctest_xcompile = config.cli_unstable().doctest_xcompile;
-    let mut runtool: &Option<(std::path::PathBuf, Vec<String>)> = &None;
-    if doctest_xcompile {
-        runtool = compilation.target_runner();
-    } else if compilation.host != compilation.target {
-        return Ok((Test::Doc, errors));
-    }
-
-    for doctest_info in &compilation.to_doc_test {
-        let Doctest {
-            package,
-            target,
-            args,
-            unstable_opts,
-        } = doctest_info;
-        config.shell().status("Doc-tests", target.name())?;
-        let mut p = compilation.rustdoc_process(package, target)?;
-        p.arg("--test")
-            .arg(target.src_path().path().unwrap())
-            .arg("--crate-name")
-            .arg(&target.crate_name());
-
-        if doctest_xcompile {
-            p.arg("--target").arg(&compilation.target);
-            p.arg("-Zunstable-options");
-            p.arg("--enable-per-target-ignores");
+    for doctest in &compilation.to_doc_test {
+        let key = doc_test_key(&doctest.package, &doctest.target);
+        if let Some(shard) = &shard {
+            if !shard.includes(&key) {
+                continue;
+            }
         }
-
-        if let Some((runtool, runtool_args)) = runtool {
-            p.arg("--runtool").arg(runtool);
-            for arg in runtool_args {
-                p.arg("--runtool-arg").arg(arg);
+        config.shell().status("Doc-tests", doctest.target.name())?;
+        let p = compilation.doctest_process(doctest, test_args)?;
+        config
+            .shell()
+            .verbose(|shell| shell.status("Running", p.to_string()))?;
+        let start = Instant::now();
+        let result = p.exec();
+        if let Some(shard) = shard.as_mut() {
+            shard.record(key, start.elapsed());
+        }
+        if let Err(e) = result {
+            let e = e.downcast::<ProcessError>()?;
+            errors.push(e);
+            if !options.no_fail_fast {
+                return Ok((Test::Doc, errors));
             }
         }
+    }
+    Ok((Test::Doc, errors))
+}
 
-        for &rust_dep in &[&compilation.deps_output] {
-            let mut arg = OsString::from("dependency=");
-            arg.push(rust_dep);
-            p.arg("-L").arg(arg);
-        }
+/// The name of a test executable in the recorded durations, which doesn't
+/// depend on where the workspace is checked out.
+fn test_key(pkg: &Package, target: &Target) -> String {
+    format!(
+        "{} {} {}",
+        pkg.name(),
+        target.kind().description(),
+        target.name()
+    )
+}
 
-        for native_dep in compilation.native_dirs.iter() {
-            p.arg("-L").arg(native_dep);
-        }
+fn doc_test_key(pkg: &Package, target: &Target) -> String {
+    if target.is_bin() {
+        format!("{} doc bin {}", pkg.name(), target.name())
+    } else {
+        format!("{} doc {}", pkg.name(), target.name())
+    }
+}
 
-        for &host_rust_dep in &[&compilation.host_deps_output] {
-            let mut arg = OsString::from("dependency=");
-            arg.push(host_rust_dep);
-            p.arg("-L").arg(arg);
-        }
+/// How long each test executable and doc test took to run the last time it
+/// was run.
+#[derive(Default, Serialize, Deserialize)]
+struct TestDurations {
+    /// Durations in seconds, by `test_key` or `doc_test_key`.
+    durations: BTreeMap<String, f64>,
+}
 
-        for arg in test_args {
-            p.arg("--test-args").arg(arg);
-        }
+/// The test executables and doc tests selected by `--shard`.
+///
+/// All the tests are sorted from the slowest to the fastest according to the
+/// durations of the previous runs, and each is assigned in turn to the shard
+/// with the least total duration so far. Tests which have never been run are
+/// assumed to take the average duration. The partitioning only depends on
+/// the list of tests and on the recorded durations, so every shard computes
+/// the same one as long as they all see the same durations file.
+struct ShardPlan {
+    /// Where the durations of the tests of this shard are written.
+    path: PathBuf,
+    /// The durations of the tests of this shard which were run.
+    durations: TestDurations,
+    selected: HashSet<String>,
+}
 
-        if let Some(cfgs) = compilation.cfgs.get(&package.package_id()) {
-            for cfg in cfgs.iter() {
-                p.arg("--cfg").arg(cfg);
-            }
-        }
+impl ShardPlan {
+    fn new(
+        ws: &Workspace<'_>,
+        shard: TestShard,
+        compilation: &Compilation<'_>,
+    ) -> CargoResult<Self> {
+        let target_dir = ws.target_dir().into_path_unlocked();
+        // A missing or corrupt file only makes the shards less balanced.
+        let durations: TestDurations = paths::read(&target_dir.join(TEST_DURATIONS_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
 
-        for arg in args {
-            p.arg(arg);
-        }
+        let mut keys = compilation
+            .tests
+            .iter()
+            .map(|(pkg, target, _)| test_key(pkg, target))
+            .chain(
+                compilation
+                    .to_doc_test
+                    .iter()
+                    .map(|doctest| doc_test_key(&doctest.package, &doctest.target)),
+            )
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
 
-        if *unstable_opts {
-            p.arg("-Zunstable-options");
-        }
+        let known = keys
+            .iter()
+            .filter_map(|key| durations.durations.get(key))
+            .collect::<Vec<_>>();
+        let default = if known.is_empty() {
+            1.0
+        } else {
+            known.iter().copied().sum::<f64>() / known.len() as f64
+        };
+        let mut tests = keys
+            .into_iter()
+            .map(|key| {
+                let duration = durations.durations.get(&key).copied().unwrap_or(default);
+                (duration, key)
+            })
+            .collect::<Vec<_>>();
+        tests.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap().then_with(|| a.1.cmp(&b.1)));
 
-        if let Some(flags) = compilation.rustdocflags.get(&package.package_id()) {
-            p.args(flags);
-        }
-        config
-            .shell()
-            .verbose(|shell| shell.status("Running", p.to_string()))?;
-        if let Err(e) = p.exec() {
-            let e = e.downcast::<ProcessError>()?;
-            errors.push(e);
-            if !options.no_fail_fast {
-                return Ok((Test::Doc, errors));
+        let mut loads = vec![0.0; shard.count];
+        let mut selected = HashSet::new();
+        for (duration, key) in tests {
+            let lightest = (0..shard.count)
+                .min_by(|a, b| loads[*a].partial_cmp(&loads[*b]).unwrap())
+                .unwrap();
+            loads[lightest] += duration;
+            if lightest + 1 == shard.index {
+                selected.insert(key);
             }
         }
+
+        Ok(ShardPlan {
+            path: target_dir.join(shard_durations_file(shard)),
+            durations: TestDurations::default(),
+            selected,
+        })
+    }
+
+    fn includes(&self, key: &str) -> bool {
+        self.selected.contains(key)
+    }
+
+    fn record(&mut self, key: String, duration: Duration) {
+        self.durations.durations.insert(key, duration.as_secs_f64());
+    }
+
+    /// Writes the durations of the tests of this shard, leaving the ones the
+    /// shards are partitioned with untouched.
+    fn save(&self) -> CargoResult<()> {
+        paths::create_dir_all(self.path.parent().unwrap())?;
+        paths::write(
+            &self.path,
+            serde_json::to_string(&self.durations)?.as_bytes(),
+        )
     }
-    Ok((Test::Doc, errors))
 }
@@ -0,0 +1,238 @@
+//! Running a command again whenever the files it read change, with `--watch`.
+//!
+//! The files which are watched are exactly those the build depends on: the
+//! files listed in the dep-info of each unit, the `rerun-if-changed` paths of
+//! build scripts, the manifests and the lock file. They are polled for
+//! changes, and the command runs again once they have stopped changing for a
+//! little while. The `Config`, the information about `rustc` and the resolve
+//! are kept in memory between builds, until a manifest or the lock file
+//! changes.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use filetime::FileTime;
+
+use crate::core::compiler::RustcTargetData;
+use crate::core::Workspace;
+use crate::ops::resolve::WorkspaceResolve;
+use crate::ops::CompileOptions;
+use crate::util::{process_error, CargoResult, Config, ProcessBuilder};
+
+/// How often the watched files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long the watched files must stay unchanged before the command runs
+/// again, so that a burst of changes (like a `git checkout`) runs it once.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// What is kept in memory between builds with `--watch`.
+#[derive(Default)]
+pub struct WatchCache<'cfg> {
+    target_data: RefCell<Option<RustcTargetData>>,
+    resolve: RefCell<Option<WorkspaceResolve<'cfg>>>,
+}
+
+impl<'cfg> WatchCache<'cfg> {
+    /// Takes what the previous build left, if anything.
+    pub(crate) fn take(&self) -> (Option<RustcTargetData>, Option<WorkspaceResolve<'cfg>>) {
+        (
+            self.target_data.borrow_mut().take(),
+            self.resolve.borrow_mut().take(),
+        )
+    }
+
+    /// Keeps what a build used for the next one.
+    pub(crate) fn store(
+        &self,
+        target_data: RustcTargetData,
+        resolve: Option<WorkspaceResolve<'cfg>>,
+    ) {
+        *self.target_data.borrow_mut() = Some(target_data);
+        *self.resolve.borrow_mut() = resolve;
+    }
+}
+
+impl fmt::Debug for WatchCache<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WatchCache").finish()
+    }
+}
+
+/// Runs `build` each time that one of the files it read changes, until Cargo
+/// is interrupted.
+///
+/// `build` may return a process to run once it is done, as `cargo run` does.
+/// It is killed when the files change. Errors are displayed, and the files
+/// are watched all the same. When a manifest or the lock file changes, the
+/// workspace is loaded again with `load_ws`.
+pub fn watch<'cfg>(
+    mut ws: Workspace<'cfg>,
+    options: &CompileOptions<'cfg>,
+    mut load_ws: impl FnMut() -> CargoResult<Workspace<'cfg>>,
+    mut build: impl FnMut(&Workspace<'cfg>) -> CargoResult<Option<ProcessBuilder>>,
+) -> CargoResult<()> {
+    let config = ws.config();
+    let cache = options
+        .watch
+        .as_ref()
+        .expect("`--watch` should be enabled in the compile options");
+    let mut paths = BTreeSet::new();
+    let mut run_build = true;
+    loop {
+        let mut running = None;
+        let start = FileTime::from_system_time(SystemTime::now());
+        if run_build {
+            config.reset_creation_time();
+            *options.build_config.watched_paths.borrow_mut() = Some(BTreeSet::new());
+            let result = build(&ws);
+            let read = options.build_config.watched_paths.borrow_mut().take();
+            paths = watched_paths(&ws, read.unwrap_or_default());
+            match result {
+                Ok(Some(process)) => running = spawn(config, process)?,
+                Ok(None) => {}
+                Err(e) => crate::display_error(&e, &mut config.shell()),
+            }
+        }
+
+        // Files which changed since the build started run it again right
+        // away, except for the lock file which is written by the build itself.
+        let states = file_states(&paths);
+        let mut changed: Vec<_> = paths
+            .iter()
+            .zip(&states)
+            .filter(|(path, state)| {
+                !is_lock_file(path) && state.map_or(false, |(mtime, _)| mtime > start)
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        if changed.is_empty() {
+            config
+                .shell()
+                .status("Watching", format!("{} files for changes", paths.len()))?;
+            changed = wait_for_changes(config, &paths, states, &mut running)?;
+        }
+        if let Some((_, mut child)) = running {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        config.shell().status("Changed", describe(&ws, &changed))?;
+
+        run_build = true;
+        if changed
+            .iter()
+            .any(|path| is_lock_file(path) || path.ends_with("Cargo.toml"))
+        {
+            cache.take();
+            match load_ws() {
+                Ok(new_ws) => ws = new_ws,
+                Err(e) => {
+                    crate::display_error(&e, &mut config.shell());
+                    run_build = false;
+                }
+            }
+        }
+    }
+}
+
+/// The files to watch for a build which read `read`: those not in the target
+/// directory, along with the manifests and the lock file of the workspace,
+/// which may not have been read if the build failed early.
+fn watched_paths(ws: &Workspace<'_>, read: BTreeSet<PathBuf>) -> BTreeSet<PathBuf> {
+    let target_dir = ws.target_dir().into_path_unlocked();
+    let mut paths: BTreeSet<_> = read
+        .into_iter()
+        .filter(|path| !path.starts_with(&target_dir))
+        .collect();
+    paths.insert(ws.root().join("Cargo.toml"));
+    paths.insert(ws.root().join("Cargo.lock"));
+    for member in ws.members() {
+        paths.insert(member.manifest_path().to_path_buf());
+    }
+    paths
+}
+
+fn is_lock_file(path: &Path) -> bool {
+    path.ends_with("Cargo.lock")
+}
+
+/// The modification time and the size of a file, or `None` if it doesn't
+/// exist.
+fn file_state(path: &Path) -> Option<(FileTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((FileTime::from_last_modification_time(&meta), meta.len()))
+}
+
+fn file_states(paths: &BTreeSet<PathBuf>) -> Vec<Option<(FileTime, u64)>> {
+    paths.iter().map(|path| file_state(path)).collect()
+}
+
+/// Starts the process returned by the build.
+fn spawn(config: &Config, process: ProcessBuilder) -> CargoResult<Option<(ProcessBuilder, Child)>> {
+    config.shell().status("Running", process.to_string())?;
+    match process.build_command().spawn() {
+        Ok(child) => Ok(Some((process, child))),
+        Err(e) => {
+            let e =
+                anyhow::Error::from(e).context(format!("could not execute process {}", process));
+            crate::display_error(&e, &mut config.shell());
+            Ok(None)
+        }
+    }
+}
+
+/// Waits until some of `paths` changed from their `last` states and then
+/// stayed unchanged for `DEBOUNCE`, and returns those which changed. The
+/// failure of the `running` process is reported in the meantime.
+fn wait_for_changes(
+    config: &Config,
+    paths: &BTreeSet<PathBuf>,
+    mut last: Vec<Option<(FileTime, u64)>>,
+    running: &mut Option<(ProcessBuilder, Child)>,
+) -> CargoResult<Vec<PathBuf>> {
+    let mut changed = BTreeSet::new();
+    let mut last_change = None;
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        if let Some((process, child)) = running {
+            if let Some(status) = child.try_wait()? {
+                if !status.success() {
+                    let msg = format!("process didn't exit successfully: {}", process);
+                    let e = process_error(&msg, Some(status), None);
+                    crate::display_error(&e.into(), &mut config.shell());
+                }
+                *running = None;
+            }
+        }
+
+        let current = file_states(paths);
+        if current != last {
+            for ((path, old), new) in paths.iter().zip(&last).zip(&current) {
+                if old != new {
+                    changed.insert(path.clone());
+                }
+            }
+            last = current;
+            last_change = Some(Instant::now());
+        } else if last_change.map_or(false, |time| time.elapsed() >= DEBOUNCE) {
+            return Ok(changed.into_iter().collect());
+        }
+    }
+}
+
+/// Describes the changed files, relative to the workspace root.
+fn describe(ws: &Workspace<'_>, changed: &[PathBuf]) -> String {
+    let first = changed[0].strip_prefix(ws.root()).unwrap_or(&changed[0]);
+    match changed.len() {
+        1 => first.display().to_string(),
+        2 => format!("{} and 1 other file", first.display()),
+        n => format!("{} and {} other files", first.display(), n - 1),
+    }
+}
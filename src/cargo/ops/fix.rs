@@ -76,7 +76,7 @@ pub struct FixOptions<'a> {
     pub broken_code: bool,
 }
 
-pub fn fix(ws: &Workspace<'_>, opts: &mut FixOptions<'_>) -> CargoResult<()> {
+pub fn fix<'a>(ws: &Workspace<'a>, opts: &mut FixOptions<'a>) -> CargoResult<()> {
     check_version_control(opts)?;
 
     // Spin up our lock server, which our subprocesses will use to synchronize fixes.
@@ -1,9 +1,14 @@
+use std::collections::BTreeMap;
 use std::io::prelude::*;
 
-use crate::core::{resolver, Resolve, ResolveVersion, Workspace};
+use log::debug;
+use termcolor::Color::{Cyan, Green, Red, Yellow};
+
+use crate::core::{resolver, InternedString, PackageId, Resolve, ResolveVersion, Workspace};
+use crate::util::config::Definition;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::toml as cargo_toml;
-use crate::util::Filesystem;
+use crate::util::{Config, Filesystem};
 
 pub fn load_pkg_lockfile(ws: &Workspace<'_>) -> CargoResult<Option<Resolve>> {
     if !ws.root().join("Cargo.lock").exists() {
@@ -130,7 +135,29 @@ fn resolve_to_string_orig(
 
     if let Some(patch) = toml.get("patch") {
         let list = patch["unused"].as_array().unwrap();
-        for entry in list {
+        for (entry, id) in list.iter().zip(resolve.unused_patches()) {
+            // Unused patches from the configuration would be hard to track
+            // down otherwise.
+            //
+            // The lock file is shared, so paths outside of the workspace,
+            // such as the one of the user's config, aren't written, and the
+            // others are written the same way on every platform.
+            if let Some(definition) = ws.config_patch_definition(*id) {
+                let definition = match definition {
+                    Definition::Path(path) => match path.strip_prefix(ws.root()) {
+                        Ok(path) => {
+                            let parts = path
+                                .iter()
+                                .map(|part| part.to_string_lossy())
+                                .collect::<Vec<_>>();
+                            format!("`{}`", parts.join("/"))
+                        }
+                        Err(_) => "a config file outside of the workspace".to_string(),
+                    },
+                    Definition::Environment(_) | Definition::Cli => definition.to_string(),
+                };
+                out.push_str(&format!("# defined in {}\n", definition));
+            }
             out.push_str("[[patch.unused]]\n");
             emit_package(entry.as_table().unwrap(), &mut out);
             out.push_str("\n");
@@ -218,3 +245,163 @@ fn emit_package(dep: &toml::value::Table, out: &mut String) {
         out.push_str(&format!("replace = {}\n\n", &dep["replace"]));
     }
 }
+
+/// A change to a package between two versions of a lock file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockfileChange {
+    Added(PackageId),
+    Removed(PackageId),
+    /// The only version of a package from a source was replaced by a newer
+    /// one.
+    Upgraded(PackageId, PackageId),
+    /// The only version of a package from a source was replaced by an older
+    /// one.
+    Downgraded(PackageId, PackageId),
+    /// The only version of a package changed its source, or its revision in
+    /// a git repository.
+    SourceChanged(PackageId, PackageId),
+    /// The checksum of a package was added, removed or changed.
+    ChecksumChanged(PackageId),
+}
+
+/// The changes to the packages in a lock file when a `Resolve` replaces the
+/// previous one.
+#[derive(Debug, Default)]
+pub struct LockfileDiff {
+    pub changes: Vec<LockfileChange>,
+}
+
+impl LockfileDiff {
+    /// Compares `previous`, or an empty lock file if it's `None`, with
+    /// `resolve`.
+    pub fn new(previous: Option<&Resolve>, resolve: &Resolve) -> LockfileDiff {
+        // Map package names to `(removed versions, added versions)`.
+        let mut changes: BTreeMap<InternedString, (Vec<PackageId>, Vec<PackageId>)> =
+            BTreeMap::new();
+        for id in previous.into_iter().flat_map(|previous| previous.iter()) {
+            changes.entry(id.name()).or_default().0.push(id);
+        }
+        for id in resolve.iter() {
+            changes.entry(id.name()).or_default().1.push(id);
+        }
+
+        let mut diff = LockfileDiff::default();
+        for (old, new) in changes.values_mut() {
+            old.sort();
+            new.sort();
+            let removed = vec_subtract(old, new);
+            let added = vec_subtract(new, old);
+            if let ([removed], [added]) = (&removed[..], &added[..]) {
+                let change = if removed.source_id() != added.source_id()
+                    || removed.version() == added.version()
+                {
+                    LockfileChange::SourceChanged(*removed, *added)
+                } else if removed.version() < added.version() {
+                    LockfileChange::Upgraded(*removed, *added)
+                } else {
+                    LockfileChange::Downgraded(*removed, *added)
+                };
+                diff.changes.push(change);
+            } else {
+                diff.changes
+                    .extend(removed.into_iter().map(LockfileChange::Removed));
+                diff.changes
+                    .extend(added.into_iter().map(LockfileChange::Added));
+            }
+
+            if let Some(previous) = previous {
+                for id in new.iter().filter(|id| old.contains(id)) {
+                    // A checksum which isn't listed and one which couldn't be
+                    // calculated are both written as no checksum.
+                    let old_checksum = previous.checksums().get(id).and_then(|c| c.as_ref());
+                    let new_checksum = resolve.checksums().get(id).and_then(|c| c.as_ref());
+                    if old_checksum != new_checksum {
+                        diff.changes.push(LockfileChange::ChecksumChanged(*id));
+                    }
+                }
+            }
+        }
+        debug!("{:#?}", diff);
+        return diff;
+
+        // Removes all package IDs in `b` from `a`. Note that this is somewhat
+        // more complicated because the equality for source IDs does not take
+        // precise versions into account (e.g., git shas), but we want to take
+        // that into account here.
+        fn vec_subtract(a: &[PackageId], b: &[PackageId]) -> Vec<PackageId> {
+            a.iter()
+                .filter(|a| {
+                    // If this package ID is not found in `b`, then it's definitely
+                    // in the subtracted set.
+                    let i = match b.binary_search(a) {
+                        Ok(i) => i,
+                        Err(..) => return true,
+                    };
+
+                    // If we've found `a` in `b`, then we iterate over all instances
+                    // (we know `b` is sorted) and see if they all have different
+                    // precise versions. If so, then `a` isn't actually in `b` so
+                    // we'll let it through.
+                    //
+                    // Note that we only check this for non-registry sources,
+                    // however, as registries contain enough version information in
+                    // the package ID to disambiguate.
+                    if a.source_id().is_registry() {
+                        return false;
+                    }
+                    b[i..]
+                        .iter()
+                        .take_while(|b| a == b)
+                        .all(|b| a.source_id().precise() != b.source_id().precise())
+                })
+                .cloned()
+                .collect()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Displays the changes with a status line for each package.
+    pub fn print(&self, config: &Config) -> CargoResult<()> {
+        let mut shell = config.shell();
+        for change in self.changes.iter() {
+            match *change {
+                LockfileChange::Added(id) => {
+                    shell.status_with_color("Adding", id, Cyan)?;
+                }
+                LockfileChange::Removed(id) => {
+                    shell.status_with_color("Removing", id, Red)?;
+                }
+                LockfileChange::Upgraded(old, new) => {
+                    let msg = format!("{} -> {}", old, describe_new(old, new));
+                    shell.status_with_color("Updating", msg, Green)?;
+                }
+                LockfileChange::Downgraded(old, new) => {
+                    let msg = format!("{} -> {}", old, describe_new(old, new));
+                    shell.status_with_color("Downgrading", msg, Yellow)?;
+                }
+                LockfileChange::SourceChanged(old, new) => {
+                    let msg = format!("{} -> {}", old, describe_new(old, new));
+                    shell.status_with_color("Updating", msg, Green)?;
+                }
+                LockfileChange::ChecksumChanged(id) => {
+                    shell.status_with_color("Updating", format!("checksum of {}", id), Green)?;
+                }
+            }
+        }
+        return Ok(());
+
+        /// Describes `new` after `old`, without repeating what they share.
+        fn describe_new(old: PackageId, new: PackageId) -> String {
+            if old.source_id() != new.source_id() {
+                new.to_string()
+            } else if new.source_id().is_git() {
+                format!("#{}", &new.source_id().precise().unwrap()[..8])
+            } else {
+                format!("v{}", new.version())
+            }
+        }
+    }
+}
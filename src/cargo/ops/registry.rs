@@ -18,6 +18,7 @@ use crate::core::source::Source;
 use crate::core::{Package, SourceId, Workspace};
 use crate::ops;
 use crate::sources::{RegistrySource, SourceConfigMap, CRATES_IO_REGISTRY};
+use crate::util::auth;
 use crate::util::config::{self, Config, SslVersionConfig, SslVersionConfigRange};
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::important_paths::find_root_manifest_for_wd;
@@ -360,7 +361,7 @@ fn registry(
         index: index_config,
     } = registry_configuration(config, registry.clone())?;
     let token = token.or(token_config);
-    let sid = get_source_id(config, index_config.or(index), registry)?;
+    let sid = get_source_id(config, index_config.or(index), registry.clone())?;
     if !sid.is_remote_registry() {
         bail!(
             "{} does not support API commands.\n\
@@ -368,7 +369,7 @@ fn registry(
             sid
         );
     }
-    let api_host = {
+    let (api_host, signed_tokens) = {
         let _lock = config.acquire_package_cache_lock()?;
         let mut src = RegistrySource::remote(sid, &HashSet::new(), config);
         // Only update the index if the config is not available or `force` is set.
@@ -385,14 +386,29 @@ fn registry(
             cfg.or_else(|_| updated_cfg())?
         };
 
-        cfg.and_then(|cfg| cfg.api)
-            .ok_or_else(|| format_err!("{} does not support API commands", sid))?
+        let cfg = cfg.ok_or_else(|| format_err!("{} does not support API commands", sid))?;
+        let api = cfg
+            .api
+            .ok_or_else(|| format_err!("{} does not support API commands", sid))?;
+        (api, cfg.signed_tokens)
     };
+    let key = auth::signing_key(config, sid, registry.as_deref())?;
+    if key.is_some() && !signed_tokens {
+        bail!(
+            "a `secret-key` is configured for `{}`, but the registry does not \
+             support signed tokens",
+            sid.display_registry_name()
+        );
+    }
     let handle = http_handle(config)?;
-    if validate_token && token.is_none() {
+    if validate_token && token.is_none() && key.is_none() {
         bail!("no upload token found, please run `cargo login`");
     };
-    Ok((Registry::new_handle(api_host, token, handle), sid))
+    let mut api = Registry::new_handle(api_host, token, handle);
+    if let Some(key) = key {
+        api.set_signer(move |op| key.sign(op));
+    }
+    Ok((api, sid))
 }
 
 /// Creates a new HTTP handle with appropriate global configuration for cargo.
@@ -633,6 +649,29 @@ pub fn registry_login(
     Ok(())
 }
 
+/// Generates a key pair for signed registry tokens, saving the secret half
+/// in the credentials file and printing the public half for the user to
+/// register with the registry.
+pub fn registry_generate_key(config: &Config, reg: Option<String>) -> CargoResult<()> {
+    if !config.cli_unstable().signed_tokens {
+        bail!("`cargo login --generate-key` requires `-Z signed-tokens`");
+    }
+    if let Some(reg) = &reg {
+        validate_package_name(reg, "registry name", "")?;
+    }
+    let (secret, public) = auth::generate_key_pair();
+    config::save_secret_key(config, secret, reg.clone())?;
+    config.shell().status(
+        "Login",
+        format!(
+            "secret key for `{}` saved",
+            reg.as_ref().map_or("crates.io", String::as_str)
+        ),
+    )?;
+    println!("{}", public);
+    Ok(())
+}
+
 pub struct OwnersOptions {
     pub krate: Option<String>,
     pub token: Option<String>,
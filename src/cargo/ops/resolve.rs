@@ -98,7 +98,7 @@ pub fn resolve_ws_with_opts<'cfg>(
 
         add_overrides(&mut registry, ws)?;
 
-        for &(ref replace_spec, ref dep) in ws.root_replace() {
+        for (replace_spec, dep) in ws.root_replace()? {
             if !resolve
                 .iter()
                 .any(|r| replace_spec.matches(r) && !dep.matches_id(r))
@@ -123,6 +123,7 @@ pub fn resolve_ws_with_opts<'cfg>(
         specs,
         add_patches,
     )?;
+    warn_deprecated(ws, &mut registry, &resolved_with_overrides)?;
 
     let pkg_set = get_resolved_packages(&resolved_with_overrides, registry)?;
 
@@ -161,10 +162,59 @@ fn resolve_with_registry<'cfg>(
 
     if !ws.is_ephemeral() {
         ops::write_pkg_lockfile(ws, &resolve)?;
+        if ws.config().cli_unstable().lockfile_diff {
+            ops::LockfileDiff::new(prev.as_ref(), &resolve).print(ws.config())?;
+        }
     }
+    warn_deprecated(ws, registry, &resolve)?;
     Ok(resolve)
 }
 
+/// Emits a warning for each package in `resolve` which its registry has
+/// marked as deprecated. Commands which resolve more than once only warn about
+/// each package the first time.
+///
+/// This is purely informational, deprecated packages are resolved like any
+/// other.
+pub fn warn_deprecated(
+    ws: &Workspace<'_>,
+    registry: &mut PackageRegistry<'_>,
+    resolve: &Resolve,
+) -> CargoResult<()> {
+    let mut pkg_ids = {
+        let mut warned = ws.config().warned_deprecated();
+        resolve
+            .iter()
+            .filter(|id| id.source_id().is_registry())
+            .filter(|id| warned.insert(*id))
+            .collect::<Vec<_>>()
+    };
+    if pkg_ids.is_empty() {
+        return Ok(());
+    }
+    pkg_ids.sort();
+    // Looking up deprecations reads the registry index, so be sure to lock it
+    // here.
+    let _lock = ws.config().acquire_package_cache_lock()?;
+    for pkg_id in pkg_ids {
+        let deprecation = match registry.deprecation(pkg_id)? {
+            Some(deprecation) => deprecation,
+            None => continue,
+        };
+        let mut msg = format!(
+            "package `{}` is deprecated in registry `{}`: {}",
+            pkg_id,
+            pkg_id.source_id().display_registry_name(),
+            deprecation.message
+        );
+        if let Some(replacement) = &deprecation.replacement {
+            msg.push_str(&format!("\nconsider using `{}` instead", replacement));
+        }
+        ws.config().shell().warn(msg)?;
+    }
+    Ok(())
+}
+
 /// Resolves all dependencies for a package using an optional previous instance.
 /// of resolve to guide the resolution process.
 ///
@@ -235,30 +285,31 @@ pub fn resolve_with_previous<'cfg>(
     }
 
     if register_patches {
-        for (url, patches) in ws.root_patch() {
+        for (url, patches) in ws.root_patch()? {
             let previous = match previous {
                 Some(r) => r,
                 None => {
-                    registry.patch(url, patches)?;
+                    registry.patch(&url, &patches)?;
                     continue;
                 }
             };
             let patches = patches
                 .iter()
-                .map(|dep| {
+                .map(|(dep, definition)| {
                     let unused = previous.unused_patches().iter().cloned();
                     let candidates = previous.iter().chain(unused);
-                    match candidates.filter(keep).find(|&id| dep.matches_id(id)) {
+                    let dep = match candidates.filter(keep).find(|&id| dep.matches_id(id)) {
                         Some(id) => {
                             let mut dep = dep.clone();
                             dep.lock_to(id);
                             dep
                         }
                         None => dep.clone(),
-                    }
+                    };
+                    (dep, definition.clone())
                 })
                 .collect::<Vec<_>>();
-            registry.patch(url, &patches)?;
+            registry.patch(&url, &patches)?;
         }
 
         registry.lock_patches();
@@ -278,12 +329,14 @@ pub fn resolve_with_previous<'cfg>(
                 ResolveOpts {
                     dev_deps: opts.dev_deps,
                     features,
+                    direct_minimal_versions: opts.direct_minimal_versions
+                        || ws.config().cli_unstable().direct_minimal_versions,
                 },
             )
         })
         .collect();
 
-    let root_replace = ws.root_replace();
+    let root_replace = ws.root_replace()?;
 
     let replace = match previous {
         Some(r) => root_replace
@@ -299,7 +352,7 @@ pub fn resolve_with_previous<'cfg>(
                 (spec.clone(), dep.clone())
             })
             .collect::<Vec<_>>(),
-        None => root_replace.to_vec(),
+        None => root_replace,
     };
 
     ws.preload(registry);
@@ -310,6 +363,7 @@ pub fn resolve_with_previous<'cfg>(
         &try_to_use,
         Some(ws.config()),
         ws.features().require(Feature::public_dependency()).is_ok(),
+        &ws.allowed_duplicate_links(),
     )?;
     resolved.register_used_patches(&registry.patches());
     if register_patches {
@@ -320,7 +374,13 @@ pub fn resolve_with_previous<'cfg>(
         let warnings: Vec<String> = resolved
             .unused_patches()
             .iter()
-            .map(|pkgid| format!("Patch `{}` was not used in the crate graph.", pkgid))
+            .map(|&pkgid| match ws.config_patch_definition(pkgid) {
+                Some(definition) => format!(
+                    "Patch `{}` defined in `{}` was not used in the crate graph.",
+                    pkgid, definition
+                ),
+                None => format!("Patch `{}` was not used in the crate graph.", pkgid),
+            })
             .collect();
         if !warnings.is_empty() {
             ws.config().shell().warn(format!(
@@ -67,7 +67,7 @@
 //! hopefully those are more obvious inline in the code itself.
 
 use crate::core::dependency::Dependency;
-use crate::core::{InternedString, PackageId, SourceId, Summary};
+use crate::core::{Deprecation, InternedString, PackageId, SourceId, Summary};
 use crate::sources::registry::{RegistryData, RegistryPackage};
 use crate::util::paths;
 use crate::util::{internal, CargoResult, Config, Filesystem, ToSemver};
@@ -210,10 +210,12 @@ enum MaybeIndexSummary {
 
 /// A parsed representation of a summary from the index.
 ///
-/// In addition to a full `Summary` we have information on whether it is `yanked`.
+/// In addition to a full `Summary` we have information on whether it is
+/// `yanked` or `deprecated`.
 pub struct IndexSummary {
     pub summary: Summary,
     pub yanked: bool,
+    pub deprecated: Option<Deprecation>,
 }
 
 /// A representation of the cache on disk that Cargo maintains of summaries.
@@ -380,6 +382,19 @@ impl<'cfg> RegistryIndex<'cfg> {
         Ok(())
     }
 
+    /// Queries all the versions of a package which were published in the
+    /// index, including those which were never downloaded with `--offline`.
+    pub fn query_published(
+        &mut self,
+        dep: &Dependency,
+        load: &mut dyn RegistryData,
+        yanked_whitelist: &HashSet<PackageId>,
+        f: &mut dyn FnMut(Summary),
+    ) -> CargoResult<()> {
+        self.query_inner_with_online(dep, load, yanked_whitelist, f, true)?;
+        Ok(())
+    }
+
     fn query_inner_with_online(
         &mut self,
         dep: &Dependency,
@@ -444,6 +459,18 @@ impl<'cfg> RegistryIndex<'cfg> {
             .any(|summary| summary.yanked);
         Ok(found)
     }
+
+    pub fn deprecation(
+        &mut self,
+        pkg: PackageId,
+        load: &mut dyn RegistryData,
+    ) -> CargoResult<Option<Deprecation>> {
+        let req = VersionReq::exact(pkg.version());
+        let found = self
+            .summaries(pkg.name(), &req, load)?
+            .find_map(|summary| summary.deprecated.clone());
+        Ok(found)
+    }
 }
 
 impl Summaries {
@@ -716,6 +743,7 @@ impl IndexSummary {
             yanked,
             links,
             pm,
+            deprecated,
         } = serde_json::from_slice(line)?;
         log::trace!("json parsed registry {}/{}", name, vers);
         let pkgid = PackageId::new(name, &vers, source_id)?;
@@ -736,6 +764,7 @@ impl IndexSummary {
         Ok(IndexSummary {
             summary,
             yanked: yanked.unwrap_or(false),
+            deprecated,
         })
     }
 }
@@ -173,7 +173,7 @@ use tar::Archive;
 
 use crate::core::dependency::{DepKind, Dependency};
 use crate::core::source::MaybePackage;
-use crate::core::{InternedString, Package, PackageId, Source, SourceId, Summary};
+use crate::core::{Deprecation, InternedString, Package, PackageId, Source, SourceId, Summary};
 use crate::sources::PathSource;
 use crate::util::errors::CargoResultExt;
 use crate::util::hex;
@@ -215,6 +215,12 @@ pub struct RegistryConfig {
     /// operations like yanks, owner modifications, publish new crates, etc.
     /// If this is None, the registry does not support API commands.
     pub api: Option<String>,
+
+    /// Whether the registry accepts short-lived signed tokens, minted from a
+    /// `secret-key` in Cargo's configuration, in place of a bearer token.
+    /// See `util::auth` for the token format.
+    #[serde(default, rename = "signed-tokens")]
+    pub signed_tokens: bool,
 }
 
 /// A single line in the index representing a single version of a package.
@@ -241,6 +247,9 @@ pub struct RegistryPackage<'a> {
     /// If `None`, then the status is unknown (crate was published before this
     /// field was added), and generally should be treated as `false.`
     pm: Option<bool>,
+    /// An advisory from the registry that this version should no longer be
+    /// used. Unlike `yanked`, this does not affect resolution.
+    deprecated: Option<Deprecation>,
 }
 
 #[test]
@@ -391,7 +400,11 @@ pub trait RegistryData {
 
 pub enum MaybeLock {
     Ready(File),
-    Download { url: String, descriptor: String },
+    Download {
+        url: String,
+        descriptor: String,
+        authorization: Option<String>,
+    },
 }
 
 mod index;
@@ -588,6 +601,11 @@ impl<'cfg> Source for RegistrySource<'cfg> {
             .query_inner(dep, &mut *self.ops, &self.yanked_whitelist, f)
     }
 
+    fn query_published(&mut self, dep: &Dependency, f: &mut dyn FnMut(Summary)) -> CargoResult<()> {
+        self.index
+            .query_published(dep, &mut *self.ops, &self.yanked_whitelist, f)
+    }
+
     fn supports_checksums(&self) -> bool {
         true
     }
@@ -620,9 +638,15 @@ impl<'cfg> Source for RegistrySource<'cfg> {
         let hash = self.index.hash(package, &mut *self.ops)?;
         match self.ops.download(package, hash)? {
             MaybeLock::Ready(file) => self.get_pkg(package, &file).map(MaybePackage::Ready),
-            MaybeLock::Download { url, descriptor } => {
-                Ok(MaybePackage::Download { url, descriptor })
-            }
+            MaybeLock::Download {
+                url,
+                descriptor,
+                authorization,
+            } => Ok(MaybePackage::Download {
+                url,
+                descriptor,
+                authorization,
+            }),
         }
     }
 
@@ -650,4 +674,10 @@ impl<'cfg> Source for RegistrySource<'cfg> {
         }
         self.index.is_yanked(pkg, &mut *self.ops)
     }
+
+    fn deprecation(&mut self, pkg: PackageId) -> CargoResult<Option<Deprecation>> {
+        // Unlike `is_yanked` this does not update the index, it is only
+        // consulted after resolution, which has already done so if needed.
+        self.index.deprecation(pkg, &mut *self.ops)
+    }
 }
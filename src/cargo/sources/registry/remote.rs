@@ -2,9 +2,11 @@ use crate::core::{InternedString, PackageId, SourceId};
 use crate::sources::git;
 use crate::sources::registry::MaybeLock;
 use crate::sources::registry::{RegistryConfig, RegistryData, CRATE_TEMPLATE, VERSION_TEMPLATE};
+use crate::util::auth;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::paths;
 use crate::util::{Config, Filesystem, Sha256};
+use crates_io::Operation;
 use lazycell::LazyCell;
 use log::{debug, trace};
 use std::cell::{Cell, Ref, RefCell};
@@ -257,9 +259,22 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
             .replace(CRATE_TEMPLATE, &*pkg.name())
             .replace(VERSION_TEMPLATE, &pkg.version().to_string());
 
+        let authorization = if config.signed_tokens {
+            match auth::signing_key(self.config, self.source_id, None)? {
+                Some(key) => Some(key.sign(Operation::Download {
+                    name: &pkg.name(),
+                    vers: &pkg.version().to_string(),
+                })?),
+                None => None,
+            }
+        } else {
+            None
+        };
+
         Ok(MaybeLock::Download {
             url,
             descriptor: pkg.to_string(),
+            authorization,
         })
     }
 
@@ -1,5 +1,5 @@
 use crate::core::source::MaybePackage;
-use crate::core::{Dependency, Package, PackageId, Source, SourceId, Summary};
+use crate::core::{Dependency, Deprecation, Package, PackageId, Source, SourceId, Summary};
 use crate::util::errors::{CargoResult, CargoResultExt};
 
 pub struct ReplacedSource<'cfg> {
@@ -63,6 +63,18 @@ impl<'cfg> Source for ReplacedSource<'cfg> {
         Ok(())
     }
 
+    fn query_published(&mut self, dep: &Dependency, f: &mut dyn FnMut(Summary)) -> CargoResult<()> {
+        let (replace_with, to_replace) = (self.replace_with, self.to_replace);
+        let dep = dep.clone().map_source(to_replace, replace_with);
+
+        self.inner
+            .query_published(&dep, &mut |summary| {
+                f(summary.map_source(replace_with, to_replace))
+            })
+            .chain_err(|| format!("failed to query replaced source {}", self.to_replace))?;
+        Ok(())
+    }
+
     fn update(&mut self) -> CargoResult<()> {
         self.inner
             .update()
@@ -125,4 +137,8 @@ impl<'cfg> Source for ReplacedSource<'cfg> {
     fn is_yanked(&mut self, pkg: PackageId) -> CargoResult<bool> {
         self.inner.is_yanked(pkg)
     }
+
+    fn deprecation(&mut self, pkg: PackageId) -> CargoResult<Option<Deprecation>> {
+        self.inner.deprecation(pkg)
+    }
 }
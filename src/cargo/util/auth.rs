@@ -0,0 +1,202 @@
+//! Short-lived signed tokens for registry requests.
+//!
+//! Instead of a long-lived bearer `token`, a registry may be configured with
+//! a `secret-key` (a PASERK `k4.secret.` string). If the registry advertises
+//! `"signed-tokens": true` in its `config.json`, Cargo mints a fresh
+//! [PASETO] `v4.public` token for every request it makes. The signed claims
+//! name the operation, the crate and version it applies to, a random nonce,
+//! and an expiry time, so a leaked token is only good for a single request
+//! within a short window.
+//!
+//! [PASETO]: https://github.com/paseto-standard/paseto-spec
+
+use std::time::{Duration, SystemTime};
+
+use crates_io::Operation;
+use ed25519_compact::{KeyPair, Noise, SecretKey};
+use serde::Serialize;
+
+use crate::core::SourceId;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::Config;
+
+const HEADER: &str = "v4.public.";
+const SECRET_PREFIX: &str = "k4.secret.";
+const PUBLIC_PREFIX: &str = "k4.public.";
+
+/// How long a minted token remains valid.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(60);
+
+/// A key used to sign tokens for a single registry.
+pub struct SigningKey {
+    secret: SecretKey,
+    /// Optional `secret-key-subject`, passed through to the registry so it
+    /// can tell multiple keys of the same user apart.
+    subject: Option<String>,
+    /// The index URL of the registry, included in the footer so a token
+    /// minted for one registry is rejected by another.
+    url: String,
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    op: &'static str,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vers: Option<&'a str>,
+    nonce: String,
+    iat: String,
+    exp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Footer<'a> {
+    url: &'a str,
+}
+
+impl SigningKey {
+    /// Parses a PASERK `k4.secret.` key.
+    pub fn new(key: &str, subject: Option<String>, url: &str) -> CargoResult<SigningKey> {
+        let secret = parse_secret_key(key)?;
+        Ok(SigningKey {
+            secret,
+            subject,
+            url: url.to_string(),
+        })
+    }
+
+    /// Returns the PASERK `k4.public.` form of the key the registry needs to
+    /// verify tokens signed with this key.
+    pub fn public_key(&self) -> String {
+        encode_public_key(&self.secret)
+    }
+
+    /// Mints a token authorizing `op`.
+    pub fn sign(&self, op: Operation<'_>) -> CargoResult<String> {
+        let now = SystemTime::now();
+        let claims = Claims {
+            op: op.as_str(),
+            name: op.name(),
+            vers: op.vers(),
+            nonce: hex::encode(&Noise::generate()[..]),
+            iat: humantime::format_rfc3339_seconds(now).to_string(),
+            exp: humantime::format_rfc3339_seconds(now + TOKEN_LIFETIME).to_string(),
+            sub: self.subject.as_deref(),
+        };
+        let message = serde_json::to_vec(&claims)?;
+        let footer = serde_json::to_vec(&Footer { url: &self.url })?;
+        let signature = self.secret.sign(
+            pre_auth_encode(&[HEADER.as_bytes(), &message, &footer, b""]),
+            None,
+        );
+
+        let mut payload = message;
+        payload.extend_from_slice(&*signature);
+        Ok(format!(
+            "{}{}.{}",
+            HEADER,
+            base64::encode_config(&payload, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(&footer, base64::URL_SAFE_NO_PAD),
+        ))
+    }
+}
+
+/// Generates a new random key pair, returned as PASERK
+/// `(k4.secret., k4.public.)` strings.
+pub fn generate_key_pair() -> (String, String) {
+    let pair = KeyPair::generate();
+    let secret = format!(
+        "{}{}",
+        SECRET_PREFIX,
+        base64::encode_config(&pair.sk[..], base64::URL_SAFE_NO_PAD)
+    );
+    (secret, encode_public_key(&pair.sk))
+}
+
+/// Looks up the signing key configured for the registry `sid`, if any.
+///
+/// `registry` is the name of the registry in `[registries]`, or `None` for
+/// crates.io (or whatever replaces it via `registry.index`). When it is not
+/// known the name is looked up by comparing index URLs.
+pub fn signing_key(
+    config: &Config,
+    sid: SourceId,
+    registry: Option<&str>,
+) -> CargoResult<Option<SigningKey>> {
+    if !config.cli_unstable().signed_tokens {
+        return Ok(None);
+    }
+    let prefix = match registry {
+        Some(name) => format!("registries.{}", name),
+        None if sid.is_default_registry() => "registry".to_string(),
+        None => match registry_name(config, sid)? {
+            Some(name) => format!("registries.{}", name),
+            None => return Ok(None),
+        },
+    };
+    let key = match config.get_string(&format!("{}.secret-key", prefix))? {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+    let subject = config
+        .get_string(&format!("{}.secret-key-subject", prefix))?
+        .map(|s| s.val);
+    let key = SigningKey::new(&key.val, subject, sid.url().as_str()).chain_err(|| {
+        format!(
+            "failed to parse `{}.secret-key` from {}",
+            prefix, key.definition
+        )
+    })?;
+    Ok(Some(key))
+}
+
+/// Finds the name under `[registries]` whose index is `sid`.
+fn registry_name(config: &Config, sid: SourceId) -> CargoResult<Option<String>> {
+    let names = match config.values()?.get("registries") {
+        Some(registries) => registries
+            .table("registries")?
+            .0
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+    for name in names {
+        if SourceId::alt_registry(config, &name)?.canonical_url() == sid.canonical_url() {
+            return Ok(Some(name));
+        }
+    }
+    Ok(None)
+}
+
+fn parse_secret_key(key: &str) -> CargoResult<SecretKey> {
+    if !key.starts_with(SECRET_PREFIX) {
+        anyhow::bail!("expected a key starting with `{}`", SECRET_PREFIX);
+    }
+    let bytes = base64::decode_config(&key[SECRET_PREFIX.len()..], base64::URL_SAFE_NO_PAD)
+        .map_err(|_| anyhow::format_err!("key is not valid base64"))?;
+    SecretKey::from_slice(&bytes).map_err(|_| anyhow::format_err!("key has an invalid length"))
+}
+
+fn encode_public_key(secret: &SecretKey) -> String {
+    format!(
+        "{}{}",
+        PUBLIC_PREFIX,
+        base64::encode_config(&secret.public_key()[..], base64::URL_SAFE_NO_PAD)
+    )
+}
+
+/// PASETO's pre-authentication encoding of `pieces`.
+fn pre_auth_encode(pieces: &[&[u8]]) -> Vec<u8> {
+    fn le64(n: usize) -> [u8; 8] {
+        (n as u64 & !(1 << 63)).to_le_bytes()
+    }
+    let mut out = le64(pieces.len()).to_vec();
+    for piece in pieces {
+        out.extend_from_slice(&le64(piece.len()));
+        out.extend_from_slice(piece);
+    }
+    out
+}
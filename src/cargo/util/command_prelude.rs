@@ -1,7 +1,7 @@
 use crate::core::compiler::{BuildConfig, MessageFormat};
 use crate::core::InternedString;
 use crate::core::Workspace;
-use crate::ops::{CompileFilter, CompileOptions, NewOptions, Packages, VersionControl};
+use crate::ops::{CompileFilter, CompileOptions, NewOptions, Packages, VersionControl, WatchCache};
 use crate::sources::CRATES_IO_REGISTRY;
 use crate::util::important_paths::find_root_manifest_for_wd;
 use crate::util::{paths, toml::TomlProfile, validate_package_name};
@@ -154,6 +154,10 @@ pub trait AppExt: Sized {
         self._arg(opt("unit-graph", "Output build graph in JSON (unstable)").hidden(true))
     }
 
+    fn arg_watch(self, watch: &'static str) -> Self {
+        self._arg(opt("watch", watch))
+    }
+
     fn arg_new_opts(self) -> Self {
         self._arg(
             opt(
@@ -438,9 +442,15 @@ pub trait ArgMatchesExt {
             }
         }
 
-        let mut build_config = BuildConfig::new(config, self.jobs()?, &self.target(), mode)?;
+        let requested_profile = self.get_profile_name(config, "dev", profile_checking)?;
+        let mut build_config = BuildConfig::new(
+            config,
+            self.jobs()?,
+            &self.target(),
+            requested_profile,
+            mode,
+        )?;
         build_config.message_format = message_format.unwrap_or(MessageFormat::Human);
-        build_config.requested_profile = self.get_profile_name(config, "dev", profile_checking)?;
         build_config.build_plan = self._is_present("build-plan");
         build_config.unit_graph = self._is_present("unit-graph");
         if build_config.build_plan {
@@ -453,6 +463,10 @@ pub trait ArgMatchesExt {
                 .cli_unstable()
                 .fail_if_stable_opt("--unit-graph", 8002)?;
         }
+        let watch = self._is_present("watch");
+        if watch && !config.cli_unstable().unstable_options {
+            bail!("the `--watch` flag is unstable, pass `-Z unstable-options` to enable it");
+        }
 
         let opts = CompileOptions {
             config,
@@ -478,6 +492,11 @@ pub trait ArgMatchesExt {
             local_rustdoc_args: None,
             rustdoc_document_private_items: false,
             export_dir: None,
+            watch: if watch {
+                Some(WatchCache::default())
+            } else {
+                None
+            },
         };
 
         if let Some(ws) = workspace {
@@ -0,0 +1,199 @@
+//! Support for config tables keyed on `cfg(…)` expressions.
+//!
+//! With `-Z config-cfg`, a top-level table such as
+//!
+//! ```toml
+//! ['cfg(all(windows, profile = "release"))'.build]
+//! jobs = 2
+//! ```
+//!
+//! is an overlay which only applies when the expression matches the build.
+//! The expressions are evaluated against the cfg values rustc reports for the
+//! requested target, plus a `profile` key set to the name of the requested
+//! profile. As neither is known when the config is loaded, the tables are
+//! applied by `BuildConfig::new`, and commands which don't build anything
+//! never look at them.
+//!
+//! Values in a matching table take precedence over the values of the same
+//! keys in config files, but not over environment variables or `--config`
+//! arguments. When several matching tables set the same key, it is an error
+//! rather than one of them silently winning.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::bail;
+use cargo_platform::{Cfg, CfgExpr};
+use lazycell::LazyCell;
+
+use super::{CargoBuildConfig, Config, ConfigKey, TargetCfgConfig, CV};
+use crate::core::compiler::{CompileKind, TargetInfo};
+use crate::core::InternedString;
+use crate::util::errors::{CargoResult, CargoResultExt};
+
+/// Keys which are read before the tables are applied, or which are needed to
+/// evaluate them, so a matching table can't set them.
+const EARLY_KEYS: &[&str] = &[
+    "build.target",
+    "build.target-dir",
+    "build.rustc",
+    "build.rustc-wrapper",
+    "build.rustc-workspace-wrapper",
+];
+
+/// The config values with the matching `cfg(…)` tables merged in.
+#[derive(Debug)]
+pub(super) struct Conditional {
+    kind: CompileKind,
+    profile: InternedString,
+    pub(super) values: HashMap<String, CV>,
+    pub(super) build_config: LazyCell<CargoBuildConfig>,
+    pub(super) target_cfgs: LazyCell<Vec<(String, TargetCfgConfig)>>,
+}
+
+/// Merges the `cfg(…)` tables which match a build for `kind` with the
+/// `profile` into the config values.
+///
+/// The tables can only be applied once, as values read from the config may
+/// already have been used.
+pub(super) fn apply(
+    config: &Config,
+    kind: CompileKind,
+    profile: InternedString,
+) -> CargoResult<()> {
+    // Without the flag the tables are unused keys like any other.
+    if !config.cli_unstable().config_cfg {
+        return Ok(());
+    }
+    if let Some(conditional) = config.conditional.borrow() {
+        if conditional.kind != kind || conditional.profile != profile {
+            bail!(
+                "the `cfg(…)` config tables were already applied for a build \
+                 with another target or profile"
+            );
+        }
+        return Ok(());
+    }
+    let values = config.values()?;
+    let mut tables = values
+        .iter()
+        .filter(|(key, _)| key.starts_with("cfg("))
+        .collect::<Vec<_>>();
+    if tables.is_empty() {
+        return Ok(());
+    }
+    // Sorted so that errors are deterministic.
+    tables.sort_by(|a, b| a.0.cmp(b.0));
+
+    let rustc = config.load_global_rustc(None)?;
+    let info = TargetInfo::new(config, kind, &rustc, kind)?;
+    let mut cfg = info.cfg().to_vec();
+    cfg.push(Cfg::KeyPair("profile".to_string(), profile.to_string()));
+
+    // The key of every value set by a matching table, with the table which
+    // set it.
+    let mut leaves: Vec<(Vec<String>, CV)> = Vec::new();
+    let mut set_by: HashMap<Vec<String>, &str> = HashMap::new();
+    for (key, table) in tables {
+        if !key.ends_with(')') {
+            bail!("config table `{}` is not a valid cfg expression", key);
+        }
+        let expr = CfgExpr::from_str(&key[4..key.len() - 1])
+            .chain_err(|| format!("failed to parse config table `{}`", key))?;
+        if !expr.matches(&cfg) {
+            continue;
+        }
+        let mut table_leaves = Vec::new();
+        collect_leaves(&mut Vec::new(), table.clone(), &mut table_leaves);
+        for (path, value) in table_leaves {
+            let name = path.join(".");
+            if let Some(early) = EARLY_KEYS.iter().find(|early| name == **early) {
+                bail!(
+                    "config key `{}` cannot be set in the `{}` table, \
+                     as it is read before the table is applied",
+                    early,
+                    key
+                );
+            }
+            if let Some(other) = set_by.get(&path) {
+                bail!(
+                    "config key `{}` is set by both the `{}` and `{}` tables, \
+                     which both match the build",
+                    name,
+                    other,
+                    key
+                );
+            }
+            set_by.insert(path.clone(), key);
+            leaves.push((path, value));
+        }
+    }
+
+    let mut merged = values
+        .iter()
+        .filter(|(key, _)| !key.starts_with("cfg("))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    for (path, value) in leaves {
+        insert_leaf(&mut merged, &path, value)
+            .chain_err(|| format!("failed to apply config key `{}`", path.join(".")))?;
+    }
+    let conditional = Conditional {
+        kind,
+        profile,
+        values: merged,
+        build_config: LazyCell::new(),
+        target_cfgs: LazyCell::new(),
+    };
+    if config.conditional.fill(conditional).is_err() {
+        bail!("the `cfg(…)` config tables were already applied");
+    }
+    Ok(())
+}
+
+fn collect_leaves(path: &mut Vec<String>, value: CV, leaves: &mut Vec<(Vec<String>, CV)>) {
+    match value {
+        CV::Table(table, _def) => {
+            for (key, value) in table {
+                path.push(key);
+                collect_leaves(path, value, leaves);
+                path.pop();
+            }
+        }
+        value => leaves.push((path.clone(), value)),
+    }
+}
+
+/// Sets the value at `path` in `values`, unless the existing value has a
+/// higher priority source. Lists are merged like those of config files.
+fn insert_leaf(values: &mut HashMap<String, CV>, path: &[String], value: CV) -> CargoResult<()> {
+    let (name, parents) = path.split_last().unwrap();
+    let mut table = values;
+    let mut key = ConfigKey::new();
+    for part in parents {
+        key.push(part);
+        let entry = table
+            .entry(part.clone())
+            .or_insert_with(|| CV::Table(HashMap::new(), value.definition().clone()));
+        table = match entry {
+            CV::Table(table, _def) => table,
+            other => bail!(
+                "expected table for configuration key `{}`, but found {} in {}",
+                key,
+                other.desc(),
+                other.definition()
+            ),
+        };
+    }
+    match table.get_mut(name) {
+        Some(existing) => {
+            if !existing.definition().is_higher_priority(value.definition()) {
+                existing.merge(value, true)?;
+            }
+        }
+        None => {
+            table.insert(name.clone(), value);
+        }
+    }
+    Ok(())
+}
@@ -36,9 +36,9 @@ impl ConfigKey {
     ///
     /// The `key` specified is expected to be a period-separated toml
     /// configuration key.
-    pub fn from_str(key: &str) -> ConfigKey {
+    pub fn parse(key: &str) -> ConfigKey {
         let mut cfg = ConfigKey::new();
-        for part in key.split('.') {
+        for part in key.split('.').filter(|part| !part.is_empty()) {
             cfg.push(part);
         }
         cfg
@@ -83,8 +83,13 @@ impl ConfigKey {
         &self.env
     }
 
+    /// Returns `true` if this is the root key, with no parts pushed.
+    pub fn is_root(&self) -> bool {
+        self.parts.is_empty()
+    }
+
     /// Returns an iterator of the key parts as strings.
-    pub(super) fn parts(&self) -> impl Iterator<Item = &str> {
+    pub fn parts(&self) -> impl Iterator<Item = &str> {
         self.parts.iter().map(|p| p.0.as_ref())
     }
 }
@@ -49,7 +49,7 @@
 //! translate from `ConfigValue` and environment variables to the caller's
 //! desired type.
 
-use std::cell::{RefCell, RefMut};
+use std::cell::{Cell, RefCell, RefMut};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::{HashMap, HashSet};
 use std::env;
@@ -70,14 +70,18 @@ use serde::Deserialize;
 use url::Url;
 
 use self::ConfigValue as CV;
+use crate::core::compiler::CompileKind;
 use crate::core::shell::Verbosity;
-use crate::core::{nightly_features_allowed, CliUnstable, Shell, SourceId, Workspace};
+use crate::core::InternedString;
+use crate::core::{nightly_features_allowed, CliUnstable, PackageId, Shell, SourceId, Workspace};
 use crate::ops;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::toml as cargo_toml;
 use crate::util::{paths, validate_package_name};
 use crate::util::{FileLock, Filesystem, IntoUrl, IntoUrlWithBase, Rustc};
 
+mod conditional;
+
 mod de;
 use de::Deserializer;
 
@@ -85,7 +89,7 @@ mod value;
 pub use value::{Definition, OptValue, Value};
 
 mod key;
-use key::ConfigKey;
+pub use key::ConfigKey;
 
 mod path;
 pub use path::{ConfigRelativePath, PathAndArgs};
@@ -157,13 +161,15 @@ pub struct Config {
     /// If false, don't cache `rustc --version --verbose` invocations
     cache_rustc_info: bool,
     /// Creation time of this config, used to output the total build time
-    creation_time: Instant,
+    creation_time: Cell<Instant>,
     /// Target Directory via resolved Cli parameter
     target_dir: Option<Filesystem>,
     /// Environment variables, separated to assist testing.
     env: HashMap<String, String>,
     /// Tracks which sources have been updated to avoid multiple updates.
     updated_sources: LazyCell<RefCell<HashSet<SourceId>>>,
+    /// Tracks which deprecated packages have been warned about.
+    warned_deprecated: LazyCell<RefCell<HashSet<PackageId>>>,
     /// Lock, if held, of the global package cache along with the number of
     /// acquisitions so far.
     package_cache_lock: RefCell<Option<(Option<FileLock>, usize)>>,
@@ -172,6 +178,9 @@ pub struct Config {
     net_config: LazyCell<CargoNetConfig>,
     build_config: LazyCell<CargoBuildConfig>,
     target_cfgs: LazyCell<Vec<(String, TargetCfgConfig)>>,
+    /// The values with the matching `cfg(…)` tables merged in, once a build
+    /// has applied them.
+    conditional: LazyCell<conditional::Conditional>,
 }
 
 impl Config {
@@ -232,15 +241,17 @@ impl Config {
             easy: LazyCell::new(),
             crates_io_source_id: LazyCell::new(),
             cache_rustc_info,
-            creation_time: Instant::now(),
+            creation_time: Cell::new(Instant::now()),
             target_dir: None,
             env,
             updated_sources: LazyCell::new(),
+            warned_deprecated: LazyCell::new(),
             package_cache_lock: RefCell::new(None),
             http_config: LazyCell::new(),
             net_config: LazyCell::new(),
             build_config: LazyCell::new(),
             target_cfgs: LazyCell::new(),
+            conditional: LazyCell::new(),
         }
     }
 
@@ -385,12 +396,23 @@ impl Config {
             .borrow_mut()
     }
 
+    /// Which packages have been checked for deprecation, used to ensure each
+    /// one is only warned about once.
+    pub fn warned_deprecated(&self) -> RefMut<'_, HashSet<PackageId>> {
+        self.warned_deprecated
+            .borrow_with(|| RefCell::new(HashSet::new()))
+            .borrow_mut()
+    }
+
     /// Gets all config values from disk.
     ///
     /// This will lazy-load the values as necessary. Callers are responsible
     /// for checking environment variables. Callers outside of the `config`
     /// module should avoid using this.
     pub fn values(&self) -> CargoResult<&HashMap<String, ConfigValue>> {
+        if let Some(conditional) = self.conditional.borrow() {
+            return Ok(&conditional.values);
+        }
         self.values.try_borrow_with(|| self.load_values())
     }
 
@@ -423,6 +445,7 @@ impl Config {
     pub fn reload_rooted_at<P: AsRef<Path>>(&mut self, path: P) -> CargoResult<()> {
         let values = self.load_values_from(path.as_ref())?;
         self.values.replace(values);
+        self.conditional = LazyCell::new();
         self.merge_cli_args()?;
         Ok(())
     }
@@ -489,6 +512,55 @@ impl Config {
         Ok(Some(val.clone()))
     }
 
+    /// Get a configuration value by key, including the value of the
+    /// corresponding environment variable.
+    ///
+    /// An environment variable overrides a value from a file, except for
+    /// lists which are merged. Tables are returned as they are in the files;
+    /// use `env_vars` to find the environment variables that may affect them.
+    pub fn get_cv_with_env(&self, key: &ConfigKey) -> CargoResult<Option<ConfigValue>> {
+        if key.is_root() {
+            return Ok(None);
+        }
+        let cv = self.get_cv(key)?;
+        let env = match self.env.get(key.as_env_key()) {
+            Some(env) => env,
+            None => return Ok(cv),
+        };
+        let env_def = Definition::Environment(key.as_env_key().to_string());
+        let list_env =
+            self.cli_unstable().advanced_env && env.starts_with('[') && env.ends_with(']');
+        match cv {
+            Some(CV::List(mut list, def)) => {
+                self.get_env_list(key, &mut list)?;
+                Ok(Some(CV::List(list, def)))
+            }
+            Some(CV::Table(..)) => Ok(cv),
+            Some(ref cv) if !env_def.is_higher_priority(cv.definition()) => Ok(Some(cv.clone())),
+            _ if list_env => {
+                let mut list = Vec::new();
+                self.get_env_list(key, &mut list)?;
+                Ok(Some(CV::List(list, env_def)))
+            }
+            _ => Ok(Some(if env == "true" || env == "false" {
+                CV::Boolean(env.parse().unwrap(), env_def)
+            } else if let Ok(i) = env.parse::<i64>() {
+                CV::Integer(i, env_def)
+            } else {
+                CV::String(env.to_string(), env_def)
+            })),
+        }
+    }
+
+    /// Returns the environment variables which may set configuration
+    /// values, those starting with `CARGO_`.
+    pub fn env_vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.env
+            .iter()
+            .filter(|(key, _)| key.starts_with("CARGO_"))
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
     /// Helper primarily for testing.
     pub fn set_env(&mut self, env: HashMap<String, String>) {
         self.env = env;
@@ -571,7 +643,7 @@ impl Config {
     /// NOTE: this does **not** support environment variables. Use `get` instead
     /// if you want that.
     pub fn get_list(&self, key: &str) -> CargoResult<OptValue<Vec<(String, Definition)>>> {
-        let key = ConfigKey::from_str(key);
+        let key = ConfigKey::parse(key);
         self._get_list(&key)
     }
 
@@ -686,6 +758,11 @@ impl Config {
         cli_config: &[String],
     ) -> CargoResult<()> {
         self.unstable_flags.parse(unstable_flags)?;
+        if self.unstable_flags.signed_tokens {
+            // A `secret-key` may be needed to download crates, not just by
+            // the commands which use the registry API.
+            self.load_credentials()?;
+        }
         if !cli_config.is_empty() {
             self.unstable_flags.fail_if_stable_opt("--config", 6699)?;
             self.cli_config = Some(cli_config.iter().map(|s| s.to_string()).collect());
@@ -1139,16 +1216,35 @@ impl Config {
     }
 
     pub fn build_config(&self) -> CargoResult<&CargoBuildConfig> {
-        self.build_config
-            .try_borrow_with(|| Ok(self.get::<CargoBuildConfig>("build")?))
+        let cell = match self.conditional.borrow() {
+            Some(conditional) => &conditional.build_config,
+            None => &self.build_config,
+        };
+        cell.try_borrow_with(|| Ok(self.get::<CargoBuildConfig>("build")?))
     }
 
     /// Returns a list of [target.'cfg()'] tables.
     ///
     /// The list is sorted by the table name.
     pub fn target_cfgs(&self) -> CargoResult<&Vec<(String, TargetCfgConfig)>> {
-        self.target_cfgs
-            .try_borrow_with(|| target::load_target_cfgs(self))
+        let cell = match self.conditional.borrow() {
+            Some(conditional) => &conditional.target_cfgs,
+            None => &self.target_cfgs,
+        };
+        cell.try_borrow_with(|| target::load_target_cfgs(self))
+    }
+
+    /// Merges the `cfg(…)` tables matching a build for the given target
+    /// and profile into the config values, with `-Z config-cfg`.
+    ///
+    /// Values which were read before this are not affected, so it should be
+    /// called as soon as the target and profile of a build are known.
+    pub fn apply_conditional_tables(
+        &self,
+        kind: CompileKind,
+        profile: InternedString,
+    ) -> CargoResult<()> {
+        conditional::apply(self, kind, profile)
     }
 
     /// Returns the `[target]` table definition for the given target triple.
@@ -1164,7 +1260,13 @@ impl Config {
     }
 
     pub fn creation_time(&self) -> Instant {
-        self.creation_time
+        self.creation_time.get()
+    }
+
+    /// Makes the total build time start from now, for builds which are run
+    /// again with `--watch`.
+    pub fn reset_creation_time(&self) {
+        self.creation_time.set(Instant::now());
     }
 
     /// Retrieves a config variable.
@@ -1184,7 +1286,7 @@ impl Config {
     pub fn get<'de, T: serde::de::Deserialize<'de>>(&self, key: &str) -> CargoResult<T> {
         let d = Deserializer {
             config: self,
-            key: ConfigKey::from_str(key),
+            key: ConfigKey::parse(key),
             env_prefix_ok: true,
         };
         T::deserialize(d).map_err(|e| e.into())
@@ -1553,6 +1655,20 @@ pub fn homedir(cwd: &Path) -> Option<PathBuf> {
 }
 
 pub fn save_credentials(cfg: &Config, token: String, registry: Option<String>) -> CargoResult<()> {
+    save_credential(cfg, "token", token, registry)
+}
+
+/// Saves a `secret-key` used to sign registry tokens, see `util::auth`.
+pub fn save_secret_key(cfg: &Config, key: String, registry: Option<String>) -> CargoResult<()> {
+    save_credential(cfg, "secret-key", key, registry)
+}
+
+fn save_credential(
+    cfg: &Config,
+    name: &str,
+    value: String,
+    registry: Option<String>,
+) -> CargoResult<()> {
     // If 'credentials.toml' exists, we should write to that, otherwise
     // use the legacy 'credentials'. There's no need to print the warning
     // here, because it would already be printed at load time.
@@ -1572,13 +1688,13 @@ pub fn save_credentials(cfg: &Config, token: String, registry: Option<String>) -
     };
 
     let (key, mut value) = {
-        let key = "token".to_string();
-        let value = ConfigValue::String(token, Definition::Path(file.path().to_path_buf()));
+        let key = name.to_string();
+        let value = ConfigValue::String(value, Definition::Path(file.path().to_path_buf()));
         let mut map = HashMap::new();
         map.insert(key, value);
         let table = CV::Table(map, Definition::Path(file.path().to_path_buf()));
 
-        if let Some(registry) = registry.clone() {
+        if let Some(registry) = registry {
             let mut map = HashMap::new();
             map.insert(registry, table);
             (
@@ -1609,11 +1725,9 @@ pub fn save_credentials(cfg: &Config, token: String, registry: Option<String>) -
             .insert("registry".into(), map.into());
     }
 
-    if registry.is_some() {
-        if let Some(table) = toml.as_table_mut().unwrap().remove("registries") {
-            let v = CV::from_toml(Definition::Path(file.path().to_path_buf()), table)?;
-            value.merge(v, false)?;
-        }
+    if let Some(table) = toml.as_table_mut().unwrap().remove(&key) {
+        let v = CV::from_toml(Definition::Path(file.path().to_path_buf()), table)?;
+        value.merge(v, false)?;
     }
     toml.as_table_mut().unwrap().insert(key, value.into_toml());
 
@@ -75,7 +75,7 @@ pub(super) fn load_target_triple(config: &Config, triple: &str) -> CargoResult<T
     let rustflags: OptValue<StringList> = config.get(&format!("target.{}.rustflags", triple))?;
     let linker: OptValue<ConfigRelativePath> = config.get(&format!("target.{}.linker", triple))?;
     // Links do not support environment variables.
-    let target_key = ConfigKey::from_str(&format!("target.{}", triple));
+    let target_key = ConfigKey::parse(&format!("target.{}", triple));
     let links_overrides = match config.get_table(&target_key)? {
         Some(links) => parse_links_overrides(&target_key, links.val)?,
         None => BTreeMap::new(),
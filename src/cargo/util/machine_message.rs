@@ -4,7 +4,9 @@ use serde::ser;
 use serde::Serialize;
 use serde_json::{self, json, value::RawValue};
 
-use crate::core::{compiler::CompileMode, PackageId, Target};
+use crate::core::compiler::{CompileMode, DirtyReason};
+use crate::core::resolver::ConflictReport;
+use crate::core::{PackageId, Target};
 
 pub trait Message: ser::Serialize {
     fn reason(&self) -> &str;
@@ -90,3 +92,29 @@ impl<'a> Message for TimingInfo<'a> {
         "timing-info"
     }
 }
+
+#[derive(Serialize)]
+pub struct DirtyUnit<'a> {
+    pub package_id: PackageId,
+    pub target: &'a Target,
+    pub mode: CompileMode,
+    pub dirty_reason: &'a DirtyReason,
+}
+
+impl<'a> Message for DirtyUnit<'a> {
+    fn reason(&self) -> &str {
+        "dirty-unit"
+    }
+}
+
+#[derive(Serialize)]
+pub struct ResolverConflict<'a> {
+    #[serde(flatten)]
+    pub report: &'a ConflictReport,
+}
+
+impl<'a> Message for ResolverConflict<'a> {
+    fn reason(&self) -> &str {
+        "resolver-conflict"
+    }
+}
@@ -30,6 +30,7 @@ pub use self::workspace::{
     print_available_tests,
 };
 
+pub mod auth;
 mod canonical_url;
 pub mod command_prelude;
 pub mod config;
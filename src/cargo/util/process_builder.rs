@@ -220,11 +220,27 @@ impl ProcessBuilder {
         on_stdout_line: &mut dyn FnMut(&str) -> CargoResult<()>,
         on_stderr_line: &mut dyn FnMut(&str) -> CargoResult<()>,
         capture_output: bool,
+    ) -> CargoResult<Output> {
+        self.exec_command_with_streaming(
+            self.build_command(),
+            on_stdout_line,
+            on_stderr_line,
+            capture_output,
+        )
+    }
+
+    /// Like `exec_with_streaming`, but executes `cmd`, which is the result of
+    /// `build_command` with some further customization by the caller.
+    pub fn exec_command_with_streaming(
+        &self,
+        mut cmd: Command,
+        on_stdout_line: &mut dyn FnMut(&str) -> CargoResult<()>,
+        on_stderr_line: &mut dyn FnMut(&str) -> CargoResult<()>,
+        capture_output: bool,
     ) -> CargoResult<Output> {
         let mut stdout = Vec::new();
         let mut stderr = Vec::new();
 
-        let mut cmd = self.build_command();
         cmd.stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .stdin(Stdio::null());
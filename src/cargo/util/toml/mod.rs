@@ -20,6 +20,7 @@ use crate::core::{Dependency, InternedString, Manifest, PackageId, Summary, Targ
 use crate::core::{Edition, EitherManifest, Feature, Features, VirtualManifest};
 use crate::core::{GitReference, PackageIdSpec, SourceId, WorkspaceConfig, WorkspaceRootConfig};
 use crate::sources::{CRATES_IO_INDEX, CRATES_IO_REGISTRY};
+use crate::util::config::{Definition, Value as ConfigValue};
 use crate::util::errors::{CargoResult, CargoResultExt, ManifestError};
 use crate::util::{self, paths, validate_package_name, Config, IntoUrl};
 
@@ -38,7 +39,18 @@ pub fn read_manifest(
     );
     let contents = paths::read(path).map_err(|err| ManifestError::new(err, path.into()))?;
 
-    do_read_manifest(&contents, path, source_id, config)
+    read_manifest_from_str(&contents, path, source_id, config)
+}
+
+/// Like `read_manifest`, but with the contents of the manifest at `path`
+/// given instead of read from it.
+pub fn read_manifest_from_str(
+    contents: &str,
+    path: &Path,
+    source_id: SourceId,
+    config: &Config,
+) -> Result<(EitherManifest, Vec<PathBuf>), ManifestError> {
+    do_read_manifest(contents, path, source_id, config)
         .chain_err(|| format!("failed to parse manifest at `{}`", path.display()))
         .map_err(|err| ManifestError::new(err, path.into()))
 }
@@ -814,6 +826,8 @@ pub struct TomlWorkspace {
     #[serde(rename = "default-members")]
     default_members: Option<Vec<String>>,
     exclude: Option<Vec<String>>,
+    #[serde(rename = "allow-duplicate-links")]
+    allow_duplicate_links: Option<Vec<String>>,
 }
 
 impl TomlProject {
@@ -1179,12 +1193,18 @@ impl TomlManifest {
             links: project.links.clone(),
         };
 
+        if let Some(config) = &me.workspace {
+            if config.allow_duplicate_links.is_some() {
+                features.require(Feature::allow_duplicate_links())?;
+            }
+        }
         let workspace_config = match (me.workspace.as_ref(), project.workspace.as_ref()) {
             (Some(config), None) => WorkspaceConfig::Root(WorkspaceRootConfig::new(
                 package_root,
                 &config.members,
                 &config.default_members,
                 &config.exclude,
+                &config.allow_duplicate_links,
             )),
             (None, root) => WorkspaceConfig::Member {
                 root: root.cloned(),
@@ -1237,6 +1257,14 @@ impl TomlManifest {
             }
         }
 
+        // A handwritten build script runs after the `metabuild` packages.
+        let metabuild_script = match project.metabuild {
+            Some(_) => me
+                .maybe_custom_build(&project.build, package_root)
+                .map(|path| package_root.join(path)),
+            None => None,
+        };
+
         let custom_metadata = project.metadata.clone();
         let mut manifest = Manifest::new(
             summary,
@@ -1258,6 +1286,7 @@ impl TomlManifest {
             project.default_run.clone(),
             Rc::clone(me),
             project.metabuild.clone().map(|sov| sov.0),
+            metabuild_script,
         );
         if project.license_file.is_some() && project.license.is_some() {
             manifest.warnings_mut().add_warning(
@@ -1349,12 +1378,18 @@ impl TomlManifest {
         if let Some(profiles) = &profiles {
             profiles.validate(&features, &mut warnings)?;
         }
+        if let Some(config) = &me.workspace {
+            if config.allow_duplicate_links.is_some() {
+                features.require(Feature::allow_duplicate_links())?;
+            }
+        }
         let workspace_config = match me.workspace {
             Some(ref config) => WorkspaceConfig::Root(WorkspaceRootConfig::new(
                 root,
                 &config.members,
                 &config.default_members,
                 &config.exclude,
+                &config.allow_duplicate_links,
             )),
             None => {
                 bail!("virtual manifests must be configured with [workspace]");
@@ -1372,37 +1407,7 @@ impl TomlManifest {
         }
         let mut replace = Vec::new();
         for (spec, replacement) in self.replace.iter().flatten() {
-            let mut spec = PackageIdSpec::parse(spec).chain_err(|| {
-                format!(
-                    "replacements must specify a valid semver \
-                     version to replace, but `{}` does not",
-                    spec
-                )
-            })?;
-            if spec.url().is_none() {
-                spec.set_url(CRATES_IO_INDEX.parse().unwrap());
-            }
-
-            if replacement.is_version_specified() {
-                bail!(
-                    "replacements cannot specify a version \
-                     requirement, but found one for `{}`",
-                    spec
-                );
-            }
-
-            let mut dep = replacement.to_dependency(spec.name().as_str(), cx, None)?;
-            {
-                let version = spec.version().ok_or_else(|| {
-                    anyhow!(
-                        "replacements must specify a version \
-                         to replace, but `{}` does not",
-                        spec
-                    )
-                })?;
-                dep.set_version_req(VersionReq::exact(version));
-            }
-            replace.push((spec, dep));
+            replace.push(replace_dependency(spec, replacement, cx)?);
         }
         Ok(replace)
     }
@@ -1410,18 +1415,8 @@ impl TomlManifest {
     fn patch(&self, cx: &mut Context<'_, '_>) -> CargoResult<HashMap<Url, Vec<Dependency>>> {
         let mut patch = HashMap::new();
         for (url, deps) in self.patch.iter().flatten() {
-            let url = match &url[..] {
-                CRATES_IO_REGISTRY => CRATES_IO_INDEX.parse().unwrap(),
-                _ => cx
-                    .config
-                    .get_registry_index(url)
-                    .or_else(|_| url.into_url())
-                    .chain_err(|| {
-                        format!("[patch] entry `{}` should be a URL or registry name", url)
-                    })?,
-            };
             patch.insert(
-                url,
+                patch_url(cx.config, url)?,
                 deps.iter()
                     .map(|(name, dep)| dep.to_dependency(name, cx, None))
                     .collect::<CargoResult<Vec<_>>>()?,
@@ -1457,6 +1452,145 @@ impl TomlManifest {
     }
 }
 
+/// Parses the key of a `[patch]` table, which is either a registry name or
+/// the URL of a source.
+fn patch_url(config: &Config, url: &str) -> CargoResult<Url> {
+    match url {
+        CRATES_IO_REGISTRY => Ok(CRATES_IO_INDEX.parse().unwrap()),
+        _ => config
+            .get_registry_index(url)
+            .or_else(|_| url.into_url())
+            .chain_err(|| format!("[patch] entry `{}` should be a URL or registry name", url)),
+    }
+}
+
+/// Parses a single entry of a `[replace]` table.
+fn replace_dependency(
+    spec: &str,
+    replacement: &TomlDependency,
+    cx: &mut Context<'_, '_>,
+) -> CargoResult<(PackageIdSpec, Dependency)> {
+    let mut spec = PackageIdSpec::parse(spec).chain_err(|| {
+        format!(
+            "replacements must specify a valid semver \
+             version to replace, but `{}` does not",
+            spec
+        )
+    })?;
+    if spec.url().is_none() {
+        spec.set_url(CRATES_IO_INDEX.parse().unwrap());
+    }
+
+    if replacement.is_version_specified() {
+        bail!(
+            "replacements cannot specify a version \
+             requirement, but found one for `{}`",
+            spec
+        );
+    }
+
+    let mut dep = replacement.to_dependency(spec.name().as_str(), cx, None)?;
+    {
+        let version = spec.version().ok_or_else(|| {
+            anyhow!(
+                "replacements must specify a version \
+                 to replace, but `{}` does not",
+                spec
+            )
+        })?;
+        dep.set_version_req(VersionReq::exact(version));
+    }
+    Ok((spec, dep))
+}
+
+/// The `[patch]` and `[replace]` tables of the Cargo configuration.
+///
+/// With `-Z patch-in-config` these are applied to every workspace on top of
+/// the ones in the root manifest. Paths in them are relative to the
+/// directory containing the `.cargo` directory they are defined in.
+#[derive(Default, Debug)]
+pub struct ConfigPatches {
+    /// The patches for each source, with the location of their definition.
+    pub patch: HashMap<Url, Vec<(Dependency, Definition)>>,
+    pub replace: Vec<(PackageIdSpec, Dependency)>,
+}
+
+impl ConfigPatches {
+    pub fn load(config: &Config) -> CargoResult<ConfigPatches> {
+        type Deps = BTreeMap<String, ConfigValue<TomlDependency>>;
+
+        let mut ret = ConfigPatches::default();
+        let patch = config.get::<Option<BTreeMap<String, Deps>>>("patch")?;
+        let replace = config.get::<Option<Deps>>("replace")?;
+        if patch.is_none() && replace.is_none() {
+            return Ok(ret);
+        }
+        if !config.cli_unstable().patch_in_config {
+            config.shell().warn(
+                "`[patch]` and `[replace]` in cargo config were ignored, \
+                 the -Zpatch-in-config command-line flag is required",
+            )?;
+            return Ok(ret);
+        }
+        if patch.is_some() && replace.is_some() {
+            bail!("cannot specify both [replace] and [patch] in cargo config");
+        }
+
+        let mut warnings = Vec::new();
+        let features = Features::new(&[], &mut warnings)?;
+        for (url, deps) in patch.iter().flatten() {
+            let url = patch_url(config, url)?;
+            let mut patches = Vec::new();
+            for (name, dep) in deps {
+                let parsed = with_config_context(config, dep, &features, &mut warnings, |cx| {
+                    dep.val.to_dependency(name, cx, None)
+                })
+                .chain_err(|| {
+                    format!("failed to parse `patch` entry `{}` in cargo config", name)
+                })?;
+                patches.push((parsed, dep.definition.clone()));
+            }
+            ret.patch.insert(url, patches);
+        }
+        for (spec, dep) in replace.iter().flatten() {
+            let parsed = with_config_context(config, dep, &features, &mut warnings, |cx| {
+                replace_dependency(spec, &dep.val, cx)
+            })
+            .chain_err(|| format!("failed to parse `replace` entry `{}` in cargo config", spec))?;
+            ret.replace.push(parsed);
+        }
+
+        for warning in warnings {
+            config.shell().warn(warning)?;
+        }
+        Ok(ret)
+    }
+}
+
+/// Runs `f` with a `Context` for parsing a dependency defined in the Cargo
+/// configuration, resolving paths relative to where `value` was defined.
+fn with_config_context<T, R>(
+    config: &Config,
+    value: &ConfigValue<T>,
+    features: &Features,
+    warnings: &mut Vec<String>,
+    f: impl FnOnce(&mut Context<'_, '_>) -> CargoResult<R>,
+) -> CargoResult<R> {
+    let root = value.definition.root(config);
+    let mut cx = Context {
+        pkgid: None,
+        deps: &mut Vec::new(),
+        source_id: SourceId::for_path(root)?,
+        nested_paths: &mut Vec::new(),
+        config,
+        warnings,
+        platform: None,
+        features,
+        root,
+    };
+    f(&mut cx)
+}
+
 /// Checks a list of build targets, and ensures the target names are unique within a vector.
 /// If not, the name of the offending build target is returned.
 fn unique_build_targets(targets: &[Target], package_root: &Path) -> Result<(), String> {
@@ -99,11 +99,12 @@ pub fn targets(
         errors,
     )?);
 
-    // processing the custom build script
-    if let Some(custom_build) = manifest.maybe_custom_build(custom_build, package_root) {
-        if metabuild.is_some() {
-            anyhow::bail!("cannot specify both `metabuild` and `build`");
-        }
+    // processing the custom build script, which is run by the generated
+    // build script with `metabuild`
+    let custom_build = manifest
+        .maybe_custom_build(custom_build, package_root)
+        .filter(|_| metabuild.is_none());
+    if let Some(custom_build) = custom_build {
         let name = format!(
             "build-script-{}",
             custom_build
@@ -0,0 +1,157 @@
+//! Tests for the `allow-duplicate-links` workspace setting.
+
+use cargo_test_support::{project, Project, ProjectBuilder};
+
+fn links_package(builder: ProjectBuilder, name: &str) -> ProjectBuilder {
+    builder
+        .file(
+            format!("{}/Cargo.toml", name),
+            &format!(
+                r#"
+                [package]
+                name = "{}"
+                version = "0.5.0"
+                links = "z"
+                build = "build.rs"
+                "#,
+                name
+            ),
+        )
+        .file(format!("{}/src/lib.rs", name), "")
+        .file(format!("{}/build.rs", name), "fn main() {}")
+}
+
+fn workspace(root_manifest: &str, second_bin_dep: &str) -> Project {
+    let p = project()
+        .file("Cargo.toml", root_manifest)
+        .file(
+            "bin1/Cargo.toml",
+            r#"
+            [package]
+            name = "bin1"
+            version = "0.1.0"
+
+            [dependencies]
+            a = { path = "../a" }
+            "#,
+        )
+        .file("bin1/src/main.rs", "fn main() {}")
+        .file(
+            "bin2/Cargo.toml",
+            &format!(
+                r#"
+                [package]
+                name = "bin2"
+                version = "0.1.0"
+
+                [dependencies]
+                {}
+                "#,
+                second_bin_dep
+            ),
+        )
+        .file("bin2/src/main.rs", "fn main() {}");
+    let p = links_package(p, "a");
+    links_package(p, "b").build()
+}
+
+const ALLOWED: &str = r#"
+    cargo-features = ["allow-duplicate-links"]
+
+    [workspace]
+    members = ["bin1", "bin2"]
+    allow-duplicate-links = ["z"]
+"#;
+
+#[cargo_test]
+fn requires_feature() {
+    let p = workspace(
+        r#"
+        [workspace]
+        members = ["bin1", "bin2"]
+        allow-duplicate-links = ["z"]
+        "#,
+        r#"b = { path = "../b" }"#,
+    );
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  feature `allow-duplicate-links` is required
+
+consider adding `cargo-features = [\"allow-duplicate-links\"]` to the manifest
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn separate_artifacts() {
+    let p = workspace(ALLOWED, r#"b = { path = "../b" }"#);
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[COMPILING] a v0.5.0 ([..])
+[COMPILING] b v0.5.0 ([..])
+[COMPILING] bin1 v0.1.0 ([..])
+[COMPILING] bin2 v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn same_artifact() {
+    let p = workspace(
+        ALLOWED,
+        r#"
+        a = { path = "../a" }
+        b = { path = "../b" }
+        "#,
+    );
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] multiple packages link to native library `z` in the bin \"bin2\" target of \
+package `bin2 v0.1.0 ([..])`, but a native library can be linked only once in each artifact
+
+package `a v0.5.0 ([..])`
+    ... which is depended on by `bin1 v0.1.0 ([..])`
+links to native library `z`
+
+package `b v0.5.0 ([..])`
+    ... which is depended on by `bin2 v0.1.0 ([..])`
+also links to native library `z`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn not_allowed() {
+    let p = workspace(
+        r#"
+        [workspace]
+        members = ["bin1", "bin2"]
+        "#,
+        r#"b = { path = "../b" }"#,
+    );
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] Attempting to resolve a dependency with more then one crate with links=z.",
+        )
+        .run();
+}
@@ -390,6 +390,9 @@ the package `a-sys` links to the native library `a`, but it conflicts with a pre
 package `foo v0.5.0 ([..])`
 
 failed to select a version for `a-sys` which could resolve this conflict
+
+help: `foo v0.5.0 ([..])` and `a-sys` both link to the native library `a`, \
+consider removing the dependency on one of them
 ").run();
 }
 
@@ -509,6 +512,9 @@ the package `a-sys` links to the native library `a`, but it conflicts with a pre
 package `foo v0.5.0 ([..])`
 
 failed to select a version for `a-sys` which could resolve this conflict
+
+help: `foo v0.5.0 ([..])` and `a-sys` both link to the native library `a`, \
+consider removing the dependency on one of them
 ").run();
 }
 
@@ -3578,6 +3584,9 @@ the package `a` links to the native library `a`, but it conflicts with a previou
 package `foo v0.5.0 ([..])`
 
 failed to select a version for `a` which could resolve this conflict
+
+help: `foo v0.5.0 ([..])` and `a` both link to the native library `a`, \
+consider removing the dependency on one of them
 ").run();
 }
 
@@ -0,0 +1,190 @@
+//! Tests for restoring the runs of build scripts with `-Z build-script-cache`.
+
+use cargo_test_support::paths::CargoPathExt;
+use cargo_test_support::{project, Project};
+
+/// A package whose build script counts its runs in `runs`, outside of the
+/// package, and generates a constant with the contents of `input.txt`.
+fn generating_project(rerun_if: &str) -> Project {
+    project()
+        .file(
+            "build.rs",
+            &format!(
+                r#"
+                use std::io::Write;
+                use std::path::Path;
+
+                fn main() {{
+                    {}
+                    let mut runs = std::fs::OpenOptions::new()
+                        .append(true)
+                        .create(true)
+                        .open("../runs")
+                        .unwrap();
+                    writeln!(runs, "run").unwrap();
+
+                    let input = std::fs::read_to_string("input.txt").unwrap();
+                    let out_dir = std::env::var("OUT_DIR").unwrap();
+                    let gen = Path::new(&out_dir).join("gen.rs");
+                    std::fs::write(&gen, format!("pub const INPUT: &str = {{:?}};", input))
+                        .unwrap();
+                    println!("cargo:rustc-env=GEN={{}}", gen.display());
+                }}
+                "#,
+                rerun_if
+            ),
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+            include!(env!("GEN"));
+
+            pub fn out_dir() -> &'static str {
+                env!("OUT_DIR")
+            }
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"
+            fn main() {
+                assert!(env!("GEN").starts_with(foo::out_dir()));
+                print!("{}", foo::INPUT);
+            }
+            "#,
+        )
+        .file("input.txt", "one")
+        .build()
+}
+
+fn runs(p: &Project) -> usize {
+    std::fs::read_to_string(p.root().join("../runs"))
+        .unwrap()
+        .lines()
+        .count()
+}
+
+#[cargo_test]
+fn restored_when_inputs_are_unchanged() {
+    let p = generating_project(r#"println!("cargo:rerun-if-changed=input.txt");"#);
+
+    p.cargo("run -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("one")
+        .run();
+    assert_eq!(runs(&p), 1);
+
+    // Touching the input makes the build script dirty, but its run is
+    // restored.
+    p.root().join("input.txt").move_into_the_future();
+    p.cargo("run -v -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RESTORED] build script output of `foo v0.0.1 ([CWD])`")
+        .with_stderr_does_not_contain("[RUNNING] `[..]build-script-build`")
+        .with_stdout("one")
+        .run();
+    assert_eq!(runs(&p), 1);
+
+    p.change_file("input.txt", "two");
+    p.cargo("run -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("two")
+        .run();
+    assert_eq!(runs(&p), 2);
+
+    // Both runs are kept.
+    p.change_file("input.txt", "one");
+    p.cargo("run -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("one")
+        .run();
+    assert_eq!(runs(&p), 2);
+
+    // Without the flag the build script runs.
+    p.change_file("input.txt", "two");
+    p.cargo("run").with_stdout("two").run();
+    assert_eq!(runs(&p), 3);
+}
+
+#[cargo_test]
+fn restored_in_another_out_dir() {
+    let p = generating_project(r#"println!("cargo:rerun-if-changed=input.txt");"#);
+    p.change_file(
+        "Cargo.toml",
+        r#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+
+        [profile.dev.build-override]
+        debug = 2
+        "#,
+    );
+
+    p.cargo("run -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("one")
+        .run();
+
+    // Another level of debug info gives the build script of the package
+    // another `OUT_DIR`, but the same executable and environment. The path of
+    // the new `OUT_DIR` replaces the old one in the output of the build
+    // script.
+    p.cargo("run -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_PROFILE_DEV_DEBUG", "1")
+        .with_stdout("one")
+        .run();
+    assert_eq!(runs(&p), 1);
+    let out_dirs = p
+        .build_dir()
+        .join("debug/build")
+        .read_dir()
+        .unwrap()
+        .filter(|dir| dir.as_ref().unwrap().path().join("out/gen.rs").exists())
+        .count();
+    assert_eq!(out_dirs, 2);
+
+    // Cleaning the package doesn't remove the cache.
+    p.cargo("clean -p foo").run();
+    p.cargo("run -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("one")
+        .run();
+    assert_eq!(runs(&p), 1);
+}
+
+#[cargo_test]
+fn env_inputs() {
+    let p = generating_project(r#"println!("cargo:rerun-if-env-changed=FOO");"#);
+
+    p.cargo("run -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .env("FOO", "1")
+        .run();
+    p.cargo("run -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .env("FOO", "2")
+        .run();
+    assert_eq!(runs(&p), 2);
+    p.cargo("run -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .env("FOO", "1")
+        .run();
+    assert_eq!(runs(&p), 2);
+}
+
+#[cargo_test]
+fn undeclared_inputs_not_cached() {
+    let p = generating_project("");
+
+    p.cargo("run -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.root().join("input.txt").move_into_the_future();
+    p.cargo("run -Zbuild-script-cache")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert_eq!(runs(&p), 2);
+    assert!(!p.build_dir().join("build-script-cache").exists());
+}
@@ -0,0 +1,240 @@
+//! Tests for the `cargo::` build script directives with
+//! `-Z build-script-directives`.
+
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn directives() {
+    let p = project()
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo::rustc-cfg=foo");
+                println!("cargo::rustc-env=FOO=bar");
+                println!("cargo::warning=hello");
+                println!("cargo::rerun-if-changed=build.rs");
+            }
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"
+            #[cfg(foo)]
+            fn main() {
+                assert_eq!(env!("FOO"), "bar");
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("run -Zbuild-script-directives")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[WARNING] hello
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] `target/debug/foo[EXE]`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn unknown_directive() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo::rustc-link-libs=z");
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zbuild-script-directives")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[ERROR] unknown directive `cargo::rustc-link-libs` in build script of `foo v0.0.1 ([CWD])`
+Metadata for the dependents of the package is passed with `cargo::metadata=KEY=VALUE`.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn error_directive() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo::warning=looking for libbar");
+                println!("cargo::error=libbar was not found");
+                println!("cargo::error=set BAR_DIR to its location");
+                eprintln!("details of the search");
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zbuild-script-directives")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+The following warnings were emitted during compilation:
+
+[WARNING] looking for libbar
+
+[ERROR] libbar was not found
+[ERROR] set BAR_DIR to its location
+[ERROR] build script of `foo v0.0.1 ([CWD])` reported 2 errors
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn metadata_directive() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [dependencies]
+            bar = { path = "bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                assert_eq!(std::env::var("DEP_BAR_ROOT").unwrap(), "/opt/bar=1");
+            }
+            "#,
+        )
+        .file(
+            "bar/Cargo.toml",
+            r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            links = "bar"
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file(
+            "bar/build.rs",
+            r#"
+            fn main() {
+                println!("cargo::metadata=root=/opt/bar=1");
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zbuild-script-directives")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.change_file(
+        "bar/build.rs",
+        r#"fn main() { println!("cargo::metadata=root"); }"#,
+    );
+    p.cargo("build -Zbuild-script-directives")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] invalid `cargo::metadata` in build script of `bar v0.0.1 ([CWD]/bar)`: \
+             `root`, expected `KEY=VALUE`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn check_cfg_directive() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [features]
+            f = []
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+            #[cfg(has_bar)]
+            pub fn bar() {}
+
+            #[cfg(feature = "f")]
+            pub fn f() {}
+
+            #[cfg(has_baz)]
+            pub fn baz() {}
+            "#,
+        )
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo::rustc-check-cfg=cfg(has_bar)");
+                println!("cargo::rustc-cfg=has_bar");
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zbuild-script-directives")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[WARNING] unexpected `cfg` condition name: `has_baz`")
+        .with_stderr_does_not_contain("[..]condition name: `has_bar`[..]")
+        .with_stderr_does_not_contain("[..]condition name: `feature`[..]")
+        .with_stderr_does_not_contain("[..]condition value[..]")
+        .run();
+}
+
+#[cargo_test]
+fn old_syntax_unchanged() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo:error=not an error");
+                println!("cargo:bogus=metadata");
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zbuild-script-directives")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    // Without the flag `cargo::` lines are still metadata.
+    p.change_file(
+        "build.rs",
+        r#"fn main() { println!("cargo::error=not an error"); }"#,
+    );
+    p.cargo("build").run();
+}
@@ -0,0 +1,152 @@
+//! Tests for the `cargo config` command.
+
+use cargo_test_support::project;
+
+fn config_project() -> cargo_test_support::Project {
+    project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [build]
+                jobs = 2
+                rustflags = ["-C", "opt-level=1"]
+
+                [alias]
+                b2 = "build"
+            "#,
+        )
+        .file(
+            ".cargo/extra.toml",
+            r#"
+                [build]
+                incremental = false
+            "#,
+        )
+        .build()
+}
+
+#[cargo_test]
+fn requires_unstable_options() {
+    let p = config_project();
+
+    p.cargo("config get")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] `cargo config` is unstable, pass `-Z unstable-options` to enable it")
+        .run();
+}
+
+#[cargo_test]
+fn get_toml() {
+    let p = config_project();
+
+    p.cargo("config get build -Zunstable-options -Zconfig-include")
+        .arg("--config")
+        .arg("include='.cargo/extra.toml'")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+build.incremental = false
+build.jobs = 2
+build.rustflags = [\"-C\", \"opt-level=1\"]
+",
+        )
+        .with_stderr("")
+        .run();
+
+    p.cargo("config get build.jobs -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("build.jobs = 2")
+        .run();
+}
+
+#[cargo_test]
+fn show_origin() {
+    let p = config_project();
+
+    p.cargo("config get build --show-origin -Zunstable-options -Zconfig-include")
+        .arg("--config")
+        .arg("build.jobs=4")
+        .arg("--config")
+        .arg("include='.cargo/extra.toml'")
+        .env("CARGO_BUILD_RUSTFLAGS", "-Cdebuginfo=1")
+        .env("CARGO_BUILD_TARGET_DIR", "out")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+build.incremental = false # [ROOT]/foo/.cargo/extra.toml
+build.jobs = 4 # --config cli option
+build.rustflags = [
+    \"-C\", # [ROOT]/foo/.cargo/config
+    \"opt-level=1\", # [ROOT]/foo/.cargo/config
+    \"-Cdebuginfo=1\", # environment variable `CARGO_BUILD_RUSTFLAGS`
+]
+# The following environment variables may affect the loaded values.
+# CARGO_BUILD_TARGET_DIR=\"out\"
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn env_overrides_file() {
+    let p = config_project();
+
+    p.cargo("config get build.jobs --show-origin -Zunstable-options")
+        .env("CARGO_BUILD_JOBS", "8")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("build.jobs = 8 # environment variable `CARGO_BUILD_JOBS`")
+        .run();
+
+    // Values which are only set in the environment can be looked up.
+    p.cargo("config get build.target-dir -Zunstable-options")
+        .env("CARGO_BUILD_TARGET_DIR", "out")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("build.target-dir = \"out\"")
+        .run();
+}
+
+#[cargo_test]
+fn get_json() {
+    let p = config_project();
+
+    p.cargo("config get build --format=json -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+                {
+                    "build": {
+                        "jobs": 2,
+                        "rustflags": ["-C", "opt-level=1"]
+                    }
+                }
+            "#,
+        )
+        .run();
+
+    p.cargo("config get alias.b2 --format=json-value -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("\"build\"")
+        .run();
+
+    p.cargo("config get build --format=json --show-origin -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `json` format does not support --show-origin, \
+             try the `toml` format instead",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn missing_key() {
+    let p = config_project();
+
+    p.cargo("config get build.target -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] config value `build.target` is not set")
+        .run();
+}
@@ -0,0 +1,146 @@
+//! Tests for `-Z checksum-freshness`.
+
+use cargo_test_support::paths::CargoPathExt;
+use cargo_test_support::project;
+
+#[cargo_test]
+fn newer_mtime_same_contents() {
+    let p = project()
+        .file("src/lib.rs", "mod a;")
+        .file("src/a.rs", "")
+        .build();
+
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    // Simulate checking out the sources again.
+    p.root().join("src").move_into_the_future();
+
+    p.cargo("build -v -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    // Without the flag only mtimes are compared.
+    p.cargo("build")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn not_recorded_without_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build").run();
+
+    p.root().join("src").move_into_the_future();
+
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn changed_contents() {
+    let p = project()
+        .file("src/lib.rs", "pub mod a;")
+        .file("src/a.rs", "pub fn f() {}")
+        .build();
+
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    // The size stays the same, only the checksum differs.
+    p.change_file("src/a.rs", "pub fn g() {}");
+    p.root().join("src").move_into_the_future();
+
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.change_file("src/a.rs", "pub fn g() {} pub fn h() {}");
+    p.root().join("src").move_into_the_future();
+
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn rerun_if_changed() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=build.rs");
+                    println!("cargo:rerun-if-changed=data.txt");
+                }
+            "#,
+        )
+        .file("data.txt", "hello")
+        .build();
+
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.root().join("data.txt").move_into_the_future();
+    p.root().join("build.rs").move_into_the_future();
+
+    p.cargo("build -v -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.change_file("data.txt", "world");
+    p.root().join("data.txt").move_into_the_future();
+
+    p.cargo("build -v -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[RUNNING] `[..]build-script-build`
+[RUNNING] `rustc --crate-name foo [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+}
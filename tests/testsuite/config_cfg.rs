@@ -0,0 +1,268 @@
+//! Tests for config tables keyed on `cfg(…)` expressions.
+
+use cargo_test_support::{basic_bin_manifest, cross_compile, project};
+
+#[cargo_test]
+fn ignored_without_flag() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                ['cfg(all())'.build]
+                rustflags = ["--cfg", "from_overlay"]
+            "#,
+        )
+        .build();
+
+    p.cargo("build -v")
+        .with_stderr_does_not_contain("[..]from_overlay[..]")
+        .run();
+}
+
+#[cargo_test]
+fn not_evaluated_without_build() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                ['cfg(foo bar)'.build]
+                jobs = 2
+            "#,
+        )
+        .build();
+
+    // Only a build knows what to evaluate the tables against, and rustc isn't
+    // run for commands which don't need it.
+    p.cargo("generate-lockfile -Zconfig-cfg")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC", "does-not-exist")
+        .run();
+}
+
+#[cargo_test]
+fn overlay_matching_host() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                #[cfg(not(from_overlay))]
+                compile_error!("the overlay was not applied");
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            r#"
+                [build]
+                rustflags = ["--cfg", "from_base"]
+
+                ['cfg(all())'.build]
+                rustflags = ["--cfg", "from_overlay"]
+
+                ['cfg(any())'.build]
+                rustflags = ["--cfg", "from_never"]
+            "#,
+        )
+        .build();
+
+    p.cargo("build -v -Zconfig-cfg")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc [..]--cfg from_base --cfg from_overlay`")
+        .with_stderr_does_not_contain("[..]from_never[..]")
+        .run();
+}
+
+#[cargo_test]
+fn overlay_matching_target() {
+    let alternate = cross_compile::alternate();
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    ['cfg(target_arch = "{arch}")'.build]
+                    rustflags = ["--cfg", "alternate_overlay"]
+
+                    ['cfg(target_arch = "{arch}")'.target.'{alternate}']
+                    linker = "alternate-linker"
+
+                    ['cfg(not(target_arch = "{arch}"))'.build]
+                    rustflags = ["--cfg", "host_overlay"]
+                "#,
+                arch = cross_compile::alternate_arch(),
+                alternate = alternate,
+            ),
+        )
+        .build();
+
+    p.cargo("build -v -Zconfig-cfg")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc [..]--cfg host_overlay`")
+        .with_stderr_does_not_contain("[..]alternate_overlay[..]")
+        .with_stderr_does_not_contain("[..]alternate-linker[..]")
+        .run();
+
+    // The build plan doesn't need the standard library of the target.
+    p.cargo("build --build-plan -Zunstable-options -Zconfig-cfg --target")
+        .arg(alternate)
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains(r#"[..]"--cfg","alternate_overlay"[..]"#)
+        .with_stdout_contains(r#"[..]"linker=alternate-linker"[..]"#)
+        .with_stdout_does_not_contain("[..]host_overlay[..]")
+        .run();
+}
+
+#[cargo_test]
+fn overlay_matching_profile() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                ['cfg(profile = "release")'.build]
+                rustflags = ["--cfg", "release_overlay"]
+
+                ['cfg(profile = "dev")'.build]
+                rustflags = ["--cfg", "dev_overlay"]
+            "#,
+        )
+        .build();
+
+    p.cargo("build -v -Zconfig-cfg")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc [..]--cfg dev_overlay`")
+        .with_stderr_does_not_contain("[..]release_overlay[..]")
+        .run();
+
+    p.cargo("build -v -Zconfig-cfg --release")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc [..]--cfg release_overlay`")
+        .with_stderr_does_not_contain("[..]dev_overlay[..]")
+        .run();
+}
+
+#[cargo_test]
+fn precedence() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [build]
+                incremental = true
+
+                ['cfg(all())'.build]
+                incremental = false
+            "#,
+        )
+        .build();
+
+    // The overlays override config files...
+    p.cargo("build -v -Zconfig-cfg")
+        .env_remove("CARGO_INCREMENTAL")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]-C incremental[..]")
+        .run();
+
+    // ...but not environment variables or `--config`.
+    p.cargo("build -v -Zconfig-cfg -Zunstable-options")
+        .arg("--config")
+        .arg("build.incremental=true")
+        .env_remove("CARGO_INCREMENTAL")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc [..]-C incremental=[..]")
+        .run();
+    p.cargo("clean").run();
+    p.cargo("build -v -Zconfig-cfg")
+        .env("CARGO_BUILD_INCREMENTAL", "true")
+        .env_remove("CARGO_INCREMENTAL")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc [..]-C incremental=[..]")
+        .run();
+}
+
+#[cargo_test]
+fn conflicting_tables() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                ['cfg(all())'.build]
+                jobs = 2
+
+                ['cfg(not(any()))'.build]
+                jobs = 3
+
+                ['cfg(any())'.build]
+                jobs = 4
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zconfig-cfg")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] config key `build.jobs` is set by both the `cfg(all())` and \
+`cfg(not(any()))` tables, which both match the build
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn early_key() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                ['cfg(all())'.build]
+                target-dir = "other"
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zconfig-cfg")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] config key `build.target-dir` cannot be set in the `cfg(all())` table, \
+as it is read before the table is applied
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_expression() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                ['cfg(foo bar)'.build]
+                jobs = 2
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zconfig-cfg")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse config table `cfg(foo bar)`
+
+Caused by:
+  [..]
+",
+        )
+        .run();
+}
@@ -0,0 +1,182 @@
+//! Tests for versions marked as deprecated in the registry index.
+
+use cargo_test_support::project;
+use cargo_test_support::registry::Package;
+
+#[cargo_test]
+fn warns_and_still_resolves() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.1.1")
+        .deprecated("bar is no longer maintained", None)
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[WARNING] package `bar v0.1.1` is deprecated in registry `crates.io`: \
+bar is no longer maintained
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.1 (registry `[ROOT][..]`)
+[COMPILING] bar v0.1.1
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    // The warning is repeated as long as the version stays in the lock file.
+    p.cargo("build")
+        .with_stderr(
+            "\
+[WARNING] package `bar v0.1.1` is deprecated in registry `crates.io`: \
+bar is no longer maintained
+[FINISHED] [..]
+",
+        )
+        .run();
+    p.cargo("update")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[WARNING] package `bar v0.1.1` is deprecated in registry `crates.io`: \
+bar is no longer maintained
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn warns_once_per_command() {
+    Package::new("bar", "0.1.0")
+        .deprecated("bar is no longer maintained", None)
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // `cargo doc` resolves the workspace more than once.
+    p.cargo("doc")
+        .with_stderr_unordered(
+            "\
+[UPDATING] `[..]` index
+[WARNING] package `bar v0.1.0` is deprecated in registry `crates.io`: \
+bar is no longer maintained
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.0 (registry `[ROOT][..]`)
+[DOCUMENTING] bar v0.1.0
+[CHECKING] bar v0.1.0
+[DOCUMENTING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn replacement() {
+    Package::new("baz", "1.0.0").publish();
+    Package::new("bar", "0.1.0")
+        .dep("baz", "1.0")
+        .deprecated("superseded", Some("bar2"))
+        .publish();
+    Package::new("baz", "1.0.1")
+        .deprecated("has a bug", None)
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[WARNING] package `bar v0.1.0` is deprecated in registry `crates.io`: superseded
+consider using `bar2` instead
+[WARNING] package `baz v1.0.1` is deprecated in registry `crates.io`: has a bug
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn update_away_from_deprecated() {
+    Package::new("bar", "0.1.0")
+        .deprecated("please upgrade", None)
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile")
+        .with_stderr_contains("[WARNING] package `bar v0.1.0` is deprecated [..]")
+        .run();
+
+    Package::new("bar", "0.1.1").publish();
+    p.cargo("update")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] bar v0.1.0 -> v0.1.1
+",
+        )
+        .run();
+    p.cargo("build")
+        .with_stderr_does_not_contain("[WARNING] [..]")
+        .run();
+}
@@ -0,0 +1,153 @@
+//! Tests for `-Z direct-minimal-versions`.
+
+use cargo_test_support::project;
+use cargo_test_support::registry::Package;
+
+fn publish_graph() {
+    Package::new("indirect", "1.0.0").publish();
+    Package::new("indirect", "1.1.0").publish();
+    Package::new("dep", "1.0.0")
+        .dep("indirect", "1.0")
+        .publish();
+    Package::new("dep", "1.1.0")
+        .dep("indirect", "1.0")
+        .publish();
+}
+
+fn foo_manifest() -> &'static str {
+    r#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+
+        [dependencies]
+        dep = "1.0"
+    "#
+}
+
+#[cargo_test]
+fn direct_minimal_transitive_maximal() {
+    publish_graph();
+
+    let p = project()
+        .file("Cargo.toml", foo_manifest())
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile -Zdirect-minimal-versions")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"dep\"\nversion = \"1.0.0\""));
+    assert!(lock.contains("name = \"indirect\"\nversion = \"1.1.0\""));
+}
+
+#[cargo_test]
+fn workspace_members() {
+    publish_graph();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file("foo/Cargo.toml", foo_manifest())
+        .file("foo/src/lib.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.0.1"
+
+                [dependencies]
+                foo = { path = "../foo" }
+                indirect = "1.0"
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    // `indirect` is a direct dependency of the `bar` member.
+    p.cargo("generate-lockfile -Zdirect-minimal-versions")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"dep\"\nversion = \"1.0.0\""));
+    assert!(lock.contains("name = \"indirect\"\nversion = \"1.0.0\""));
+}
+
+#[cargo_test]
+fn lower_bound_does_not_work() {
+    Package::new("indirect", "1.0.0").publish();
+    Package::new("indirect", "1.1.0").publish();
+    Package::new("dep", "1.0.0")
+        .dep("indirect", "1.1")
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                dep = "1.0"
+                indirect = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // `dep` needs a higher version of `indirect` than the lower bound in the
+    // manifest of `foo`, which is reported instead of silently raised.
+    p.cargo("generate-lockfile -Zdirect-minimal-versions")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] failed to select a version for [..]")
+        .run();
+}
+
+#[cargo_test]
+fn update_flag() {
+    publish_graph();
+
+    let p = project()
+        .file("Cargo.toml", foo_manifest())
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"dep\"\nversion = \"1.1.0\""));
+
+    p.cargo("update --direct-minimal-versions")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `--direct-minimal-versions` flag is unstable, \
+             pass `-Z unstable-options` to enable it",
+        )
+        .run();
+
+    p.cargo("update --direct-minimal-versions -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNGRADING] dep v1.1.0 -> v1.0.0
+",
+        )
+        .run();
+
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"dep\"\nversion = \"1.0.0\""));
+    assert!(lock.contains("name = \"indirect\"\nversion = \"1.1.0\""));
+}
@@ -0,0 +1,181 @@
+//! Tests for `-Z dirty-reasons`.
+
+use cargo_test_support::paths::CargoPathExt;
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn file_changed() {
+    let p = project().file("src/lib.rs", "").build();
+
+    // The first build has nothing to explain.
+    p.cargo("build -v -Zdirty-reasons")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.root().move_into_the_past();
+    p.root().join("target").move_into_the_past();
+    p.change_file("src/lib.rs", "pub fn f() {}");
+
+    p.cargo("build -v -Zdirty-reasons")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[DIRTY] foo: the file `src/lib.rs` has changed
+[COMPILING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    // Without `-v` only the usual status is shown.
+    p.root().move_into_the_past();
+    p.root().join("target").move_into_the_past();
+    p.change_file("src/lib.rs", "");
+
+    p.cargo("build -Zdirty-reasons")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn dependency_rebuilt() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.0.1"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("build").run();
+
+    p.root().move_into_the_past();
+    p.root().join("target").move_into_the_past();
+    p.change_file("bar/src/lib.rs", "pub fn f() {}");
+
+    p.cargo("build -v -Zdirty-reasons")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[DIRTY] bar: the file `src/lib.rs` has changed
+[COMPILING] bar v0.0.1 ([CWD]/bar)
+[RUNNING] `rustc --crate-name bar [..]`
+[DIRTY] foo: the dependency `bar` was rebuilt
+[COMPILING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc --crate-name foo [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn env_var_changed() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-env-changed=FOO");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build").run();
+
+    p.cargo("build -v -Zdirty-reasons")
+        .env("FOO", "1")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[DIRTY] foo: the env variable `FOO` was set
+[COMPILING] foo v0.0.1 ([CWD])
+[RUNNING] `[..]build-script-build`
+[RUNNING] `rustc --crate-name foo [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.cargo("build -v -Zdirty-reasons")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[DIRTY] foo: the env variable `FOO` was unset")
+        .run();
+}
+
+#[cargo_test]
+fn rustflags_changed() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build").run();
+
+    p.cargo("build -v -Zdirty-reasons")
+        .env("RUSTFLAGS", "-Cdebug-assertions")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[DIRTY] foo: the rustflags changed from [] to [\"-Cdebug-assertions\"]
+[COMPILING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn json_message() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build").run();
+
+    p.root().move_into_the_past();
+    p.root().join("target").move_into_the_past();
+    p.change_file("src/lib.rs", "pub fn f() {}");
+
+    p.cargo("build --message-format=json -Zdirty-reasons")
+        .masquerade_as_nightly_cargo()
+        .with_json_contains_unordered(
+            r#"
+                {
+                    "reason": "dirty-unit",
+                    "package_id": "foo 0.0.1 [..]",
+                    "target": "{...}",
+                    "mode": "build",
+                    "dirty_reason": {
+                        "kind": "file-changed",
+                        "path": "[..]src/lib.rs",
+                        "mtime": "{...}",
+                        "reference": "[..]",
+                        "reference_mtime": "{...}"
+                    }
+                }
+            "#,
+        )
+        .run();
+}
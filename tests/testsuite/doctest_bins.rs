@@ -0,0 +1,215 @@
+//! Tests for running the doc tests of binaries with `-Z doctest-bins`.
+
+use cargo_test_support::project;
+
+#[cargo_test]
+fn doctest_bin() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo-cli"
+            version = "0.0.1"
+
+            [[bin]]
+            name = "foo-cli"
+            path = "src/main.rs"
+            doctest = true
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"
+            /// ```
+            /// assert_eq!(foo_cli::add(1, 2), 3);
+            /// ```
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
+
+            fn main() {
+                println!("{}", add(1, 2));
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("test -Zdoctest-bins")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo-cli v0.0.1 ([CWD])
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] target/debug/deps/foo_cli-[..][EXE]
+[DOCTEST] foo-cli
+",
+        )
+        .with_stdout_contains("test [..]src/main.rs - add (line 2) ... ok")
+        .run();
+
+    p.cargo("test --doc -Zdoctest-bins")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[DOCTEST] foo-cli
+",
+        )
+        .with_stdout_contains("test [..]src/main.rs - add (line 2) ... ok")
+        .run();
+
+    // Without the flag binaries aren't doc tested.
+    p.cargo("test")
+        .with_stderr(
+            "\
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] target/debug/deps/foo_cli-[..][EXE]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn doctest_bin_with_lib() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [[bin]]
+            name = "cli"
+            doctest = true
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+            /// ```
+            /// assert_eq!(foo::one(), 1);
+            /// ```
+            pub fn one() -> u32 {
+                1
+            }
+            "#,
+        )
+        .file(
+            "src/bin/cli.rs",
+            r#"
+            /// ```
+            /// assert_eq!(cli::two(), 2);
+            /// ```
+            pub fn two() -> u32 {
+                foo::one() + 1
+            }
+
+            fn main() {}
+            "#,
+        )
+        .build();
+
+    p.cargo("test --doc -Zdoctest-bins")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[DOCTEST] foo")
+        .with_stderr_contains("[DOCTEST] cli")
+        .with_stdout_contains("test [..]src/lib.rs - one (line 2) ... ok")
+        .with_stdout_contains("test [..]src/bin/cli.rs - two (line 2) ... ok")
+        .run();
+}
+
+#[cargo_test]
+fn doctest_bin_failure() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [[bin]]
+            name = "foo"
+            path = "src/main.rs"
+            doctest = true
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"
+            /// ```
+            /// assert!(false);
+            /// ```
+            pub fn f() {}
+
+            fn main() {}
+            "#,
+        )
+        .build();
+
+    p.cargo("test -Zdoctest-bins")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stdout_contains("test [..]src/main.rs - f (line 2) ... FAILED")
+        .with_stderr_contains("[ERROR] test failed, to rerun pass '--doc'")
+        .run();
+}
+
+#[cargo_test]
+fn doctest_bin_skipped() {
+    // `foo` has the same name as the library, and `bar` isn't doc tested by
+    // default.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            edition = "2018"
+
+            [[bin]]
+            name = "foo"
+            path = "src/main.rs"
+            doctest = true
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "src/main.rs",
+            r#"
+            /// ```
+            /// assert!(false);
+            /// ```
+            pub fn f() {}
+
+            fn main() {}
+            "#,
+        )
+        .file(
+            "src/bin/bar.rs",
+            r#"
+            /// ```
+            /// assert!(false);
+            /// ```
+            pub fn f() {}
+
+            fn main() {}
+            "#,
+        )
+        .build();
+
+    p.cargo("test --doc -Zdoctest-bins")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[DOCTEST] foo
+",
+        )
+        .with_stdout_contains("running 0 tests")
+        .run();
+}
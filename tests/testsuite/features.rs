@@ -139,7 +139,10 @@ versions that meet the requirements `*` are: 0.0.1
 the package `foo` depends on `bar`, with features: `bar` but `bar` does not have these features.
 
 
-failed to select a version for `bar` which could resolve this conflict",
+failed to select a version for `bar` which could resolve this conflict
+
+help: consider removing the features `bar` from the `bar` dependency of \
+`foo v0.0.1 ([..])`",
         )
         .run();
 
@@ -345,6 +348,9 @@ the package `foo` depends on `bar`, with features: `baz` but `bar` does not have
 
 
 failed to select a version for `bar` which could resolve this conflict
+
+help: consider removing the features `baz` from the `bar` dependency of \
+`foo v0.0.1 ([..])`
 ").with_status(101)
         .run();
 }
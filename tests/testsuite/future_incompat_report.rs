@@ -0,0 +1,153 @@
+//! Tests for `-Z future-incompat-report`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+
+// A lint which rustc reports as future-incompatible, even in dependencies
+// built with `--cap-lints allow`.
+const FUTURE_INCOMPAT_CODE: &str = r#"
+    fn make<T: Default>() -> Result<T, ()> { Err(()) }
+    pub fn f() -> Result<(), ()> { make()?; Ok(()) }
+"#;
+
+#[cargo_test]
+fn report_in_dependency() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", FUTURE_INCOMPAT_CODE)
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check -Zfuture-incompat-report")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.0 (registry `[ROOT][..]`)
+[CHECKING] bar v0.1.0
+[CHECKING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+[WARNING] the following packages contain code that will be rejected by a future \
+version of Rust: bar v0.1.0
+[NOTE] to see what the problems were, run \
+`cargo report future-incompat -Z future-incompat-report`
+",
+        )
+        .run();
+
+    // The report is replayed from the message cache for fresh units.
+    p.cargo("check -Zfuture-incompat-report")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[WARNING] the following packages contain code that will be rejected by a future \
+version of Rust: bar v0.1.0
+[NOTE] [..]
+",
+        )
+        .run();
+
+    Package::new("bar", "0.1.1").publish();
+    Package::new("bar", "0.2.0").publish();
+    p.cargo("report future-incompat -Zfuture-incompat-report")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[UPDATING] `[..]` index")
+        .with_stdout_contains(
+            "The package `bar v0.1.0` currently triggers the following future \
+             incompatibility lints:",
+        )
+        .with_stdout_contains("> [..]never type fallback[..]")
+        .with_stdout_contains(
+            "`bar v0.1.0` has the following newer versions available: 0.1.1, 0.2.0",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn no_report_without_flag() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", FUTURE_INCOMPAT_CODE)
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .with_stderr_does_not_contain("[..]future version of Rust[..]")
+        .run();
+
+    p.cargo("report future-incompat -Zfuture-incompat-report")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] no future-incompat report found; run a build with \
+`-Z future-incompat-report` to generate one
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn clean_build_report() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check -Zfuture-incompat-report")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.cargo("report future-incompat -Zfuture-incompat-report")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("No future incompatibility warnings were found in the last build.")
+        .run();
+}
+
+#[cargo_test]
+fn report_requires_flag() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("report future-incompat")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] `cargo report` requires `-Z future-incompat-report`")
+        .run();
+}
@@ -2827,3 +2827,81 @@ to proceed despite [..]
     git::commit(&repo);
     git_project.cargo("package --no-verify").run();
 }
+
+#[cargo_test]
+fn update_precise_git_revisions() {
+    let (git_project, repo) = git::new_repo("bar", |project| {
+        project
+            .file("Cargo.toml", &basic_lib_manifest("bar"))
+            .file("src/lib.rs", "pub fn a() {}")
+    });
+    let first = repo.head().unwrap().target().unwrap();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "foo"
+                    version = "0.5.0"
+
+                    [dependencies]
+                    bar = {{ git = '{}' }}
+                "#,
+                git_project.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    git_project.change_file("src/lib.rs", "pub fn b() {}");
+    git::add(&repo);
+    let second = git::commit(&repo);
+    p.cargo("generate-lockfile").run();
+    assert!(p.read_lockfile().contains(&second.to_string()));
+
+    // A commit which is only reachable from a tag.
+    git_project.change_file("src/lib.rs", "pub fn c() {}");
+    git::add(&repo);
+    let third = git::commit(&repo);
+    git::tag(&repo, "only-tagged");
+    let second_commit = repo.find_commit(second).unwrap();
+    repo.reset(second_commit.as_object(), git2::ResetType::Hard, None)
+        .unwrap();
+
+    // An abbreviated commit hash is pinned as the full hash.
+    p.cargo("update -p bar --precise")
+        .arg(&first.to_string()[..7])
+        .with_stderr(&format!(
+            "[UPDATING] bar v0.5.0 ([..]) -> #{}",
+            &first.to_string()[..8]
+        ))
+        .run();
+    let lock = p.read_lockfile();
+    assert!(lock.contains(&format!("#{}", first)));
+
+    p.cargo("update -p bar --precise only-tagged")
+        .with_stderr(&format!(
+            "\
+[UPDATING] git repository `[..]`
+[UPDATING] bar v0.5.0 ([..]) -> #{}
+",
+            &third.to_string()[..8]
+        ))
+        .run();
+    let lock = p.read_lockfile();
+    assert!(lock.contains(&format!("#{}", third)));
+    p.cargo("build").run();
+
+    // Updating without `--precise` goes back to the branch.
+    p.cargo("update -p bar")
+        .with_stderr(&format!(
+            "\
+[UPDATING] git repository `[..]`
+[UPDATING] bar v0.5.0 ([..]) -> #{}
+",
+            &second.to_string()[..8]
+        ))
+        .run();
+}
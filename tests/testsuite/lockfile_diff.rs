@@ -0,0 +1,176 @@
+//! Tests for displaying the changes to `Cargo.lock`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+
+fn manifest(deps: &str) -> String {
+    format!(
+        r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies]
+            {}
+        "#,
+        deps
+    )
+}
+
+#[cargo_test]
+fn build_displays_changes() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("baz", "1.0.0").publish();
+    Package::new("qux", "1.0.0").publish();
+
+    let p = project()
+        .file("Cargo.toml", &manifest("bar = \"0.1\"\nbaz = \"1.0\""))
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile -Zlockfile-diff")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] bar v0.1.0
+[ADDING] baz v1.0.0
+[ADDING] foo v0.1.0 ([ROOT]/foo)
+",
+        )
+        .run();
+
+    // Nothing is displayed without the flag.
+    Package::new("baz", "1.1.0").publish();
+    p.change_file("Cargo.toml", &manifest("bar = \"0.1\"\nbaz = \"1.1\""));
+    p.cargo("fetch")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]baz v1.0.0[..]")
+        .run();
+
+    Package::new("baz", "2.0.0").publish();
+    p.change_file(
+        "Cargo.toml",
+        &manifest("bar = { path = \"bar\" }\nbaz = \"2.0\"\nqux = \"1.0\""),
+    );
+    p.cargo("metadata --format-version=1 -Zlockfile-diff")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] bar v0.1.0 -> bar v0.1.0 ([ROOT]/foo/bar)
+[UPDATING] baz v1.1.0 -> v2.0.0
+[ADDING] qux v1.0.0
+[DOWNLOADING] crates ...
+[DOWNLOADED] [..]
+[DOWNLOADED] [..]
+",
+        )
+        .run();
+
+    p.change_file("Cargo.toml", &manifest("bar = { path = \"bar\" }"));
+    p.cargo("metadata --format-version=1 -Zlockfile-diff")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[REMOVING] baz v2.0.0
+[REMOVING] qux v1.0.0
+",
+        )
+        .run();
+
+    // Unchanged lock files are not displayed.
+    p.cargo("metadata --format-version=1 -Zlockfile-diff")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("")
+        .run();
+}
+
+#[cargo_test]
+fn update_displays_downgrades() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.1.1").publish();
+
+    let p = project()
+        .file("Cargo.toml", &manifest("bar = \"0.1\""))
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+
+    p.cargo("update -p bar --precise 0.1.0 --dry-run")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNGRADING] bar v0.1.1 -> v0.1.0
+[WARNING] not updating lockfile due to dry run
+",
+        )
+        .run();
+    assert!(p.read_lockfile().contains("0.1.1"));
+
+    p.cargo("update -p bar --precise 0.1.0")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNGRADING] bar v0.1.1 -> v0.1.0
+",
+        )
+        .run();
+    assert!(!p.read_lockfile().contains("0.1.1"));
+}
+
+#[cargo_test]
+fn update_dry_run_without_lockfile() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file("Cargo.toml", &manifest("bar = \"0.1\""))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("update --dry-run")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] bar v0.1.0
+[ADDING] foo v0.1.0 ([ROOT]/foo)
+[WARNING] not updating lockfile due to dry run
+",
+        )
+        .run();
+    assert!(!p.root().join("Cargo.lock").exists());
+}
+
+#[cargo_test]
+fn added_checksum() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file("Cargo.toml", &manifest("bar = \"0.1\""))
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+
+    let lockfile = p.read_lockfile();
+    let without_checksum: String = lockfile
+        .lines()
+        .filter(|line| !line.starts_with("checksum = "))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    assert_ne!(lockfile, without_checksum);
+    p.change_file("Cargo.lock", &without_checksum);
+
+    p.cargo("metadata --format-version=1 -Zlockfile-diff")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] checksum of bar v0.1.0
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.0 ([..])
+",
+        )
+        .run();
+    assert!(p.read_lockfile().contains("checksum = "));
+}
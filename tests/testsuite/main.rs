@@ -12,6 +12,7 @@
 extern crate cargo_test_macro;
 
 mod advanced_env;
+mod allow_duplicate_links;
 mod alt_registry;
 mod bad_config;
 mod bad_manifest_path;
@@ -19,18 +20,23 @@ mod bench;
 mod build;
 mod build_plan;
 mod build_script;
+mod build_script_cache;
+mod build_script_directives;
 mod build_script_env;
 mod cache_messages;
 mod cargo_alias_config;
 mod cargo_command;
+mod cargo_config;
 mod cargo_features;
 mod cargo_targets;
 mod cfg;
 mod check;
+mod checksum_freshness;
 mod clean;
 mod collisions;
 mod concurrent;
 mod config;
+mod config_cfg;
 mod config_cli;
 mod config_include;
 mod corrupt_git;
@@ -39,8 +45,12 @@ mod cross_publish;
 mod custom_target;
 mod death;
 mod dep_info;
+mod deprecated;
+mod direct_minimal_versions;
 mod directory;
+mod dirty_reasons;
 mod doc;
+mod doctest_bins;
 mod edition;
 mod error;
 mod features;
@@ -48,6 +58,7 @@ mod features2;
 mod fetch;
 mod fix;
 mod freshness;
+mod future_incompat_report;
 mod generate_lockfile;
 mod git;
 mod git_auth;
@@ -60,8 +71,10 @@ mod list_targets;
 mod local_registry;
 mod locate_project;
 mod lockfile_compat;
+mod lockfile_diff;
 mod login;
 mod member_errors;
+mod merged_doctests;
 mod message_format;
 mod metabuild;
 mod metadata;
@@ -70,9 +83,12 @@ mod net_config;
 mod new;
 mod offline;
 mod out_dir;
+mod outdated;
 mod owner;
 mod package;
+mod parallel_tests;
 mod patch;
+mod patch_in_config;
 mod path;
 mod paths;
 mod pkgid;
@@ -91,16 +107,21 @@ mod registry;
 mod rename_deps;
 mod replace;
 mod required_features;
+mod resolver_conflicts;
 mod run;
+mod run_multiple;
 mod rustc;
 mod rustc_info_cache;
 mod rustdoc;
 mod rustdocflags;
 mod rustflags;
+mod sandbox;
 mod search;
 mod shell_quoting;
+mod signed_tokens;
 mod standard_lib;
 mod test;
+mod test_shard;
 mod timings;
 mod tool_paths;
 mod unit_graph;
@@ -109,6 +130,7 @@ mod vendor;
 mod verify_project;
 mod version;
 mod warn_on_failure;
+mod watch;
 mod workspaces;
 mod yank;
 
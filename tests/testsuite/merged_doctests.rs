@@ -0,0 +1,125 @@
+//! Tests for compiling merged doc tests as jobs of the build with
+//! `-Z merged-doctests`.
+
+use cargo_test_support::{basic_manifest, project, Project};
+
+fn doctested_lib(name: &str, body: &str) -> String {
+    format!(
+        r#"
+        /// ```
+        /// assert_eq!({name}::one(), 1);
+        /// ```
+        pub fn one() -> u32 {{
+            1
+        }}
+
+        /// ```
+        /// {body}
+        /// ```
+        pub fn two() -> u32 {{
+            2
+        }}
+
+        #[test]
+        fn unit_test() {{}}
+        "#,
+        name = name,
+        body = body,
+    )
+}
+
+fn workspace(b_body: &str) -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["a", "b"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.0.1"))
+        .file(
+            "a/src/lib.rs",
+            &doctested_lib("a", "assert_eq!(a::two(), 2);"),
+        )
+        .file("b/Cargo.toml", &basic_manifest("b", "0.0.1"))
+        .file("b/src/lib.rs", &doctested_lib("b", b_body))
+        .build()
+}
+
+#[cargo_test]
+fn merged_doctests() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            &doctested_lib("foo", "assert_eq!(foo::two(), 2);"),
+        )
+        .build();
+
+    p.cargo("test -Zmerged-doctests")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[DOCTEST] foo
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] target/debug/deps/foo-[..][EXE]
+",
+        )
+        .with_stdout_contains("test [..]src/lib.rs - one (line 2) ... ok")
+        .with_stdout_contains("test [..]src/lib.rs - two (line 9) ... ok")
+        .with_stdout_contains("[..]merged doctests compilation took [..]")
+        .with_stdout_contains("test unit_test ... ok")
+        .run();
+}
+
+#[cargo_test]
+fn merged_doctests_of_workspace() {
+    let p = workspace("assert_eq!(b::two(), 2);");
+
+    p.cargo("test --doc -Zmerged-doctests")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_stderr_unordered(
+            "\
+[COMPILING] a v0.0.1 ([CWD]/a)
+[COMPILING] b v0.0.1 ([CWD]/b)
+[DOCTEST] a
+[DOCTEST] b
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .with_stdout_contains_n("[..]merged doctests compilation took [..]", 2)
+        .run();
+}
+
+#[cargo_test]
+fn merged_doctests_fail_fast() {
+    let p = workspace("assert!(false);");
+
+    p.cargo("test -p b -Zmerged-doctests")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_status(101)
+        .with_stdout_contains("test [..]src/lib.rs - two (line 9) ... FAILED")
+        .with_stderr_contains("[ERROR] test failed, to rerun pass '--doc'")
+        .with_stderr_does_not_contain("[RUNNING] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn merged_doctests_no_fail_fast() {
+    let p = workspace("assert!(false);");
+
+    p.cargo("test -Zmerged-doctests --no-fail-fast")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_status(101)
+        .with_stdout_contains("test [..]src/lib.rs - two (line 9) ... FAILED")
+        .with_stdout_contains_n("test unit_test ... ok", 2)
+        .with_stderr_contains("[RUNNING] target/debug/deps/a-[..][EXE]")
+        .with_stderr_contains("[RUNNING] target/debug/deps/b-[..][EXE]")
+        .with_stderr_contains("[ERROR] test failed, to rerun pass '--doc'")
+        .run();
+}
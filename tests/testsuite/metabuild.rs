@@ -86,7 +86,9 @@ fn metabuild_basic() {
 }
 
 #[cargo_test]
-fn metabuild_error_both() {
+fn metabuild_with_build_script() {
+    // The handwritten build script runs after the metabuild packages, and
+    // the output of all of them is combined.
     let p = project()
         .file(
             "Cargo.toml",
@@ -101,26 +103,197 @@ fn metabuild_error_both() {
             mb = {path="mb"}
         "#,
         )
-        .file("src/lib.rs", "")
-        .file("build.rs", r#"fn main() {}"#)
+        .file(
+            "src/lib.rs",
+            r#"
+            pub const MB: &str = env!("MB");
+            pub const BUILD: &str = env!("BUILD");
+            "#,
+        )
+        .file(
+            "build.rs",
+            r#"
+            mod helper;
+
+            fn main() {
+                println!("Hello build.rs");
+                println!("cargo:rustc-env=BUILD={}", helper::value());
+            }
+            "#,
+        )
+        .file("helper.rs", r#"pub fn value() -> &'static str { "build" }"#)
         .file("mb/Cargo.toml", &basic_lib_manifest("mb"))
         .file(
             "mb/src/lib.rs",
-            r#"pub fn metabuild() { println!("Hello mb"); }"#,
+            r#"pub fn metabuild() {
+                println!("Hello mb");
+                println!("cargo:rustc-env=MB=mb");
+            }"#,
         )
         .build();
 
+    let output = p
+        .cargo("build -vv")
+        .masquerade_as_nightly_cargo()
+        .exec_with_output()
+        .expect("cargo build failed");
+    let stdout = str::from_utf8(&output.stdout).unwrap();
+    let mb = stdout.find("[foo 0.0.1] Hello mb").unwrap();
+    let build = stdout.find("[foo 0.0.1] Hello build.rs").unwrap();
+    assert!(mb < build);
+
+    // Changing the handwritten build script runs it again.
+    p.change_file(
+        "build.rs",
+        r#"fn main() { println!("cargo:rustc-env=BUILD=changed"); }"#,
+    );
+    p.cargo("build -vv")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("[foo 0.0.1] Hello mb")
+        .run();
+
+    // `build = false` turns it off.
+    p.change_file(
+        "Cargo.toml",
+        r#"
+        cargo-features = ["metabuild"]
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        metabuild = "mb"
+        build = false
+
+        [build-dependencies]
+        mb = {path="mb"}
+        "#,
+    );
     p.cargo("build -vv")
         .masquerade_as_nightly_cargo()
         .with_status(101)
-        .with_stderr_contains(
-            "\
-error: failed to parse manifest at [..]
+        .with_stderr_contains("[..]environment variable `BUILD` not defined[..]")
+        .run();
+}
 
-Caused by:
-  cannot specify both `metabuild` and `build`
-",
+#[cargo_test]
+fn metabuild_build_script_inner_attributes() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["metabuild"]
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            metabuild = "mb"
+
+            [build-dependencies]
+            mb = {path="mb"}
+        "#,
+        )
+        .file("src/lib.rs", r#"pub const BUILD: &str = env!("BUILD");"#)
+        .file(
+            "build.rs",
+            r#"
+            //! Sets `BUILD`.
+            #![allow(dead_code)]
+
+            fn unused() {}
+
+            fn main() {
+                println!("cargo:rustc-env=BUILD=build");
+            }
+            "#,
         )
+        .file("mb/Cargo.toml", &basic_lib_manifest("mb"))
+        .file("mb/src/lib.rs", "pub fn metabuild() {}")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]never used[..]")
+        .run();
+}
+
+#[cargo_test]
+fn metabuild_config() {
+    // Each metabuild package gets its `package.metadata` table as JSON.
+    let mb = |name: &str| {
+        format!(
+            r#"pub fn metabuild() {{
+                match std::env::var("CARGO_METABUILD_CONFIG") {{
+                    Ok(path) => {{
+                        let config = std::fs::read_to_string(path).unwrap();
+                        println!("{} config: {{}}", config);
+                    }}
+                    Err(_) => println!("{} no config"),
+                }}
+            }}"#,
+            name, name
+        )
+    };
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["metabuild"]
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            metabuild = ["mb", "mb-other", "mb-third"]
+
+            [build-dependencies]
+            mb = {path="mb"}
+            mb-other = {path="mb-other"}
+            mb-third = {path="mb-third"}
+
+            [package.metadata.mb]
+            files = ["a.c", "b.c"]
+            opt = { level = 2 }
+
+            [package.metadata.mb-third]
+            name = '"quoted"'
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .file("mb/Cargo.toml", &basic_lib_manifest("mb"))
+        .file("mb/src/lib.rs", &mb("mb"))
+        .file("mb-other/Cargo.toml", &basic_manifest("mb-other", "0.0.1"))
+        .file("mb-other/src/lib.rs", &mb("mb-other"))
+        .file("mb-third/Cargo.toml", &basic_manifest("mb-third", "0.0.1"))
+        .file("mb-third/src/lib.rs", &mb("mb-third"))
+        .build();
+
+    p.cargo("build -vv")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains(r#"[foo 0.0.1] mb config: {"files":["a.c","b.c"],"opt":{"level":2}}"#)
+        .with_stdout_contains("[foo 0.0.1] mb-other no config")
+        .with_stdout_contains(r#"[foo 0.0.1] mb-third config: {"name":"\"quoted\""}"#)
+        .run();
+
+    // Changing the configuration runs the build script again.
+    p.change_file(
+        "Cargo.toml",
+        r#"
+        cargo-features = ["metabuild"]
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        metabuild = ["mb", "mb-other", "mb-third"]
+
+        [build-dependencies]
+        mb = {path="mb"}
+        mb-other = {path="mb-other"}
+        mb-third = {path="mb-third"}
+
+        [package.metadata.mb-other]
+        enabled = true
+        "#,
+    );
+    p.cargo("build -vv")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("[foo 0.0.1] mb no config")
+        .with_stdout_contains(r#"[foo 0.0.1] mb-other config: {"enabled":true}"#)
+        .with_stdout_contains("[foo 0.0.1] mb-third no config")
         .run();
 }
 
@@ -0,0 +1,194 @@
+//! Tests for the `cargo outdated` command.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project, Project};
+
+fn outdated_project() -> Project {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "0.1.0").publish();
+    Package::new("transitive", "0.5.0").publish();
+    Package::new("dep", "1.0.0")
+        .dep("transitive", "0.5")
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+                dep = "1.0"
+                local = { path = "local" }
+
+                [dev-dependencies]
+                baz = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("local/Cargo.toml", &basic_manifest("local", "0.1.0"))
+        .file("local/src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+
+    Package::new("bar", "1.1.0").publish();
+    Package::new("bar", "2.0.0").publish();
+    Package::new("bar", "3.0.0-beta.1").publish();
+    Package::new("baz", "0.2.0").publish();
+    Package::new("transitive", "0.5.1").publish();
+    p
+}
+
+#[cargo_test]
+fn requires_unstable_options() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("outdated")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `cargo outdated` is unstable, pass `-Z unstable-options` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn human_report() {
+    let p = outdated_project();
+
+    p.cargo("outdated -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+Name        Locked  Compatible  Latest  Kind    Dependent
+bar         1.0.0   1.1.0       2.0.0   normal  foo v0.1.0
+baz         0.1.0   -           0.2.0   dev     foo v0.1.0
+transitive  0.5.0   0.5.1       -       normal  dep v1.0.0
+",
+        )
+        .with_stderr("[UPDATING] `[..]` index")
+        .run();
+
+    p.cargo("outdated --root-deps-only -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+Name  Locked  Compatible  Latest  Kind    Dependent
+bar   1.0.0   1.1.0       2.0.0   normal  foo v0.1.0
+baz   0.1.0   -           0.2.0   dev     foo v0.1.0
+",
+        )
+        .run();
+
+    // The lock file is not changed.
+    p.cargo("update -p bar -p baz -p transitive").run();
+    p.cargo("outdated -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+Name  Locked  Compatible  Latest  Kind    Dependent
+bar   1.1.0   -           2.0.0   normal  foo v0.1.0
+baz   0.1.0   -           0.2.0   dev     foo v0.1.0
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn json_report_and_exit_code() {
+    let p = outdated_project();
+
+    p.cargo("outdated --format=json --root-deps-only --exit-code -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(1)
+        .with_json(
+            r#"
+                {
+                    "outdated": [
+                        {
+                            "name": "bar",
+                            "parent": "foo 0.1.0 (path+file://[..])",
+                            "requirement": "^1.0",
+                            "kinds": ["normal"],
+                            "locked": "1.0.0",
+                            "compatible": "1.1.0",
+                            "latest": "2.0.0"
+                        },
+                        {
+                            "name": "baz",
+                            "parent": "foo 0.1.0 (path+file://[..])",
+                            "requirement": "^0.1",
+                            "kinds": ["dev"],
+                            "locked": "0.1.0",
+                            "compatible": "0.1.0",
+                            "latest": "0.2.0"
+                        }
+                    ]
+                }
+            "#,
+        )
+        .run();
+}
+
+#[cargo_test]
+fn up_to_date() {
+    Package::new("bar", "1.0.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("outdated --exit-code -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[NOTE] all dependencies are up to date
+",
+        )
+        .run();
+
+    p.cargo("outdated --format=json --exit-code -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_json(r#"{"outdated": []}"#)
+        .run();
+}
+
+#[cargo_test]
+fn offline_uses_cached_index() {
+    let p = outdated_project();
+    // Only the locked versions are downloaded, but the index is updated.
+    p.cargo("fetch").run();
+    p.cargo("outdated -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    Package::new("bar", "2.1.0").publish();
+
+    p.cargo("outdated --root-deps-only --offline -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+Name  Locked  Compatible  Latest  Kind    Dependent
+bar   1.0.0   1.1.0       2.0.0   normal  foo v0.1.0
+baz   0.1.0   -           0.2.0   dev     foo v0.1.0
+",
+        )
+        .with_stderr("")
+        .run();
+}
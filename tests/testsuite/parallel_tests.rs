@@ -0,0 +1,104 @@
+//! Tests for running test executables as part of the build with
+//! `-Z parallel-tests`.
+
+use cargo_test_support::project;
+
+#[cargo_test]
+fn runs_tests_with_build_environment() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            build = "build.rs"
+            "#,
+        )
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo:rustc-env=FOO=foo-from-build-script");
+            }
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+            #[test]
+            fn lib_test() {
+                assert!(std::env::var("OUT_DIR").is_ok());
+            }
+            "#,
+        )
+        .file(
+            "tests/a.rs",
+            r#"
+            #[test]
+            fn a_test() {
+                assert_eq!(std::env::var("FOO").unwrap(), "foo-from-build-script");
+            }
+            "#,
+        )
+        .file("tests/b.rs", "#[test] fn b_test() {}")
+        .build();
+
+    p.cargo("test -Zparallel-tests")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[RUNNING] target/debug/deps/foo-[..][EXE]
+[RUNNING] target/debug/deps/a-[..][EXE]
+[RUNNING] target/debug/deps/b-[..][EXE]
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[DOCTEST] foo
+",
+        )
+        .with_stdout_contains("test lib_test ... ok")
+        .with_stdout_contains("test a_test ... ok")
+        .with_stdout_contains("test b_test ... ok")
+        .run();
+}
+
+#[cargo_test]
+fn no_fail_fast_aggregates_failures() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("tests/a.rs", "#[test] fn a_test() { panic!(); }")
+        .file("tests/b.rs", "#[test] fn b_test() {}")
+        .file("tests/c.rs", "#[test] fn c_test() { panic!(); }")
+        .build();
+
+    p.cargo("test -Zparallel-tests --no-fail-fast")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[RUNNING] target/debug/deps/b-[..][EXE]")
+        .with_stderr_contains("[DOCTEST] foo")
+        .with_stderr_contains("[ERROR] test failed.")
+        .with_stdout_contains("test a_test ... FAILED")
+        .with_stdout_contains("test b_test ... ok")
+        .with_stdout_contains("test c_test ... FAILED")
+        .run();
+}
+
+#[cargo_test]
+fn fail_fast_skips_remaining_tests() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("tests/a.rs", "#[test] fn a_test() { panic!(); }")
+        .file("tests/b.rs", "#[test] fn b_test() { panic!(); }")
+        .file("tests/c.rs", "#[test] fn c_test() { panic!(); }")
+        .build();
+
+    // With a single job the test executables run one after another, so the
+    // first failure stops the rest from starting.
+    p.cargo("test -Zparallel-tests -j1")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] test failed, to rerun pass '--test [..]'")
+        .with_stderr_does_not_contain("[DOCTEST] foo")
+        .with_stdout_contains_n("test result: FAILED. [..]", 1)
+        .run();
+}
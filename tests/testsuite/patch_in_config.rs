@@ -0,0 +1,379 @@
+//! Tests for `[patch]` and `[replace]` in the cargo configuration.
+
+use std::fs;
+use std::io::Write;
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, paths, project};
+
+#[cargo_test]
+fn requires_flag() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = "0.1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [patch.crates-io]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_stderr(
+            "\
+[WARNING] `[patch]` and `[replace]` in cargo config were ignored, \
+the -Zpatch-in-config command-line flag is required
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.0 ([..])
+[COMPILING] bar v0.1.0
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn patch_relative_to_config() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = "0.1.0"
+            "#,
+        )
+        .file(
+            "foo/src/lib.rs",
+            "extern crate bar; pub fn f() { bar::patched(); }",
+        )
+        .file(
+            ".cargo/config",
+            r#"
+                [patch.crates-io]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "pub fn patched() {}")
+        .build();
+
+    // The path is relative to the directory above `.cargo`, not to the
+    // current directory.
+    p.cargo("build -Zpatch-in-config")
+        .cwd("foo")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[COMPILING] bar v0.1.0 ([ROOT]/foo/bar)
+[COMPILING] foo v0.0.1 ([ROOT]/foo/foo)
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn config_takes_precedence() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = "0.1.0"
+
+                [patch.crates-io]
+                bar = { path = "manifest-bar" }
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "extern crate bar; pub fn f() { bar::config(); }",
+        )
+        .file("manifest-bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("manifest-bar/src/lib.rs", "pub fn manifest() {}")
+        .file("config-bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("config-bar/src/lib.rs", "pub fn config() {}")
+        .build();
+
+    p.cargo("build -Zpatch-in-config -Zunstable-options")
+        .arg("--config")
+        .arg("patch.crates-io.bar.path='config-bar'")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[COMPILING] bar v0.1.0 ([CWD]/config-bar)
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn unused_patch_names_config() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = "0.1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [patch.crates-io]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.2.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Zpatch-in-config")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[WARNING] Patch `bar v0.2.0 ([CWD]/bar)` defined in `[CWD]/.cargo/config` \
+was not used in the crate graph.
+[..]
+[..]
+[..]
+[..]
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.0 ([..])
+[COMPILING] bar v0.1.0
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    let lock = p.read_lockfile();
+    assert!(lock.contains(
+        "\
+# defined in `.cargo/config`
+[[patch.unused]]
+name = \"bar\"
+version = \"0.2.0\"
+"
+    ));
+
+    // The lock file is not rewritten when nothing changed.
+    p.cargo("build -Zpatch-in-config")
+        .masquerade_as_nightly_cargo()
+        .arg("--locked")
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn unused_patch_outside_workspace() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = "0.1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.2.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+    // The config of the test registry is in the user's config too.
+    let mut config = fs::OpenOptions::new()
+        .append(true)
+        .open(paths::home().join(".cargo/config"))
+        .unwrap();
+    write!(
+        config,
+        r#"
+            [patch.crates-io]
+            bar = {{ path = '{}' }}
+        "#,
+        p.root().join("bar").display()
+    )
+    .unwrap();
+
+    p.cargo("build -Zpatch-in-config")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] Patch `bar v0.2.0 ([CWD]/bar)` defined in \
+             `[ROOT]/home/.cargo/config` was not used in the crate graph.",
+        )
+        .run();
+
+    // The path of the user's config isn't written to the lock file.
+    let lock = p.read_lockfile();
+    assert!(lock.contains(
+        "\
+# defined in a config file outside of the workspace
+[[patch.unused]]
+name = \"bar\"
+version = \"0.2.0\"
+"
+    ));
+}
+
+#[cargo_test]
+fn patch_error_names_config() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = "0.1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [patch.crates-io]
+                bar = "0.1.0"
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zpatch-in-config")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] failed to resolve patches for `https://github.com/rust-lang/crates.io-index`
+
+Caused by:
+  patch for `bar` in `https://github.com/rust-lang/crates.io-index` defined in \
+`[CWD]/.cargo/config` points to the same source, but patches must point to different sources
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn replace() {
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = "0.1.0"
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "extern crate bar; pub fn f() { bar::replaced(); }",
+        )
+        .file(
+            ".cargo/config",
+            r#"
+                [replace]
+                "bar:0.1.0" = { path = "bar" }
+            "#,
+        )
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "pub fn replaced() {}")
+        .build();
+
+    p.cargo("build -Zpatch-in-config")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[COMPILING] bar v0.1.0 ([CWD]/bar)
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn both_patch_and_replace() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [patch.crates-io]
+                bar = { path = "bar" }
+
+                [replace]
+                "bar:0.1.0" = { path = "bar" }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zpatch-in-config")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] cannot specify both [replace] and [patch] in cargo config")
+        .run();
+}
@@ -204,3 +204,76 @@ Caused by:
         )
         .run()
 }
+
+#[cargo_test]
+fn leaked_private_dependencies_summary() {
+    Package::new("priv_dep", "0.1.0")
+        .file("src/lib.rs", "pub struct FromPriv;")
+        .publish();
+    Package::new("pub_dep", "0.1.0")
+        .file("src/lib.rs", "pub struct FromPub;")
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["public-dependency"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [dependencies]
+            priv_dep = "0.1.0"
+            pub_dep = {version = "0.1.0", public = true}
+        "#,
+        )
+        .file(
+            "src/lib.rs",
+            "
+            pub fn use_priv(_: priv_dep::FromPriv) {}
+            pub fn use_pub(_: pub_dep::FromPub) {}
+        ",
+        )
+        .file(
+            "src/main.rs",
+            "fn main() { foo::use_priv(priv_dep::FromPriv); }",
+        )
+        .build();
+
+    // The lib of the package is not a private dependency of the binary.
+    p.cargo("build -v --message-format=short")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo src/main.rs [..]--extern foo=[..] \
+             --extern 'priv:priv_dep=[..]' --extern pub_dep=[..]`",
+        )
+        .with_stderr_contains(
+            "\
+[FINISHED] [..]
+[WARNING] `foo v0.0.1 ([CWD])` exposes types from private dependencies in its public API: \
+`priv_dep`
+[NOTE] dependencies which are meant to be part of the public API can be marked with \
+`public = true`
+",
+        )
+        .run();
+
+    // The summary is replayed from the message cache for fresh units.
+    p.cargo("build --message-format=short")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_stderr(
+            "\
+src/lib.rs:2:13: warning: type `FromPriv` from private dependency 'priv_dep' in public interface
+warning: 1 warning emitted
+[FINISHED] [..]
+[WARNING] `foo v0.0.1 ([CWD])` exposes types from private dependencies in its public API: \
+`priv_dep`
+[NOTE] [..]
+",
+        )
+        .run();
+}
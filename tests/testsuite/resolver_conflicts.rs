@@ -0,0 +1,167 @@
+//! Tests for the explanations of conflicting dependency requirements.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{project, Project};
+
+fn exact_requirement_project() -> Project {
+    Package::new("bad", "1.0.0").publish();
+    Package::new("bad", "1.0.1").publish();
+    Package::new("bad", "1.0.2").publish();
+    Package::new("baz", "0.1.0").dep("bad", "=1.0.0").publish();
+    Package::new("qux", "0.1.0").dep("bad", ">=1.0.1").publish();
+
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+
+            [dependencies]
+            baz = "0.1.0"
+            qux = "0.1.0"
+        "#,
+        )
+        .file("src/main.rs", "fn main(){}")
+        .build()
+}
+
+#[cargo_test]
+fn suggests_relaxing_exact_requirement() {
+    let p = exact_requirement_project();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+failed to select a version for `bad` which could resolve this conflict
+
+help: consider relaxing the `bad` requirement `= 1.0.0` of `baz v0.1.0` \
+so that it allows one of the versions 1.0.2, 1.0.1",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn json_report() {
+    let p = exact_requirement_project();
+
+    p.cargo("build --message-format=json")
+        .with_status(101)
+        .with_json(r#"
+                {
+                    "reason": "resolver-conflict",
+                    "package": "bad",
+                    "requirement": ">= 1.0.1",
+                    "path": [
+                        "foo 0.0.1 (path+file:///[..]/foo)",
+                        "qux 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)"
+                    ],
+                    "candidates": ["1.0.2", "1.0.1"],
+                    "conflicts": [
+                        {
+                            "reason": "semver",
+                            "package": "bad 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                            "path": [
+                                "foo 0.0.1 (path+file:///[..]/foo)",
+                                "baz 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                                "bad 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)"
+                            ],
+                            "requirement": "= 1.0.0"
+                        }
+                    ],
+                    "suggestion": "consider relaxing the `bad` requirement `= 1.0.0` of `baz v0.1.0` so that it allows one of the versions 1.0.2, 1.0.1"
+                }
+            "#)
+        .run();
+}
+
+#[cargo_test]
+fn links_conflict() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+
+            [dependencies]
+            a = { path = "a" }
+            b = { path = "b" }
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "a/Cargo.toml",
+            r#"
+            [project]
+            name = "a"
+            version = "0.5.0"
+            links = "z"
+            build = "build.rs"
+        "#,
+        )
+        .file("a/src/lib.rs", "")
+        .file("a/build.rs", "fn main() {}")
+        .file(
+            "b/Cargo.toml",
+            r#"
+            [project]
+            name = "b"
+            version = "0.5.0"
+            links = "z"
+            build = "build.rs"
+        "#,
+        )
+        .file("b/src/lib.rs", "")
+        .file("b/build.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+failed to select a version for `b` which could resolve this conflict
+
+help: `a v0.5.0 ([..])` and `b` both link to the native library `z`, \
+consider removing the dependency on one of them",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn semver_conflict() {
+    Package::new("bad", "1.0.0").publish();
+    Package::new("bad", "1.1.0").publish();
+    Package::new("bar", "0.1.0").dep("bad", "1.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+
+            [dependencies]
+            bad = "~1.0.0"
+            bar = "0.1.0"
+        "#,
+        )
+        .file("src/main.rs", "fn main(){}")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+failed to select a version for `bad` which could resolve this conflict
+
+help: consider relaxing the `bad` requirement `^1.1` of `bar v0.1.0` \
+so that it allows `bad v1.0.0`, or the `bad` requirement `~1.0.0` of \
+`foo v0.0.1 ([..])` so that it allows one of the versions 1.1.0",
+        )
+        .run();
+}
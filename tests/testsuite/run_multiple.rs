@@ -0,0 +1,182 @@
+//! Tests for running several executables with `cargo run -Z run-multiple`.
+
+use cargo_test_support::project;
+
+#[cargo_test]
+fn run_multiple_bins() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "src/bin/server.rs",
+            r#"
+            fn main() {
+                println!("server: {:?}", std::env::args().skip(1).collect::<Vec<_>>());
+                eprintln!("server listening");
+            }
+            "#,
+        )
+        .file(
+            "src/bin/client.rs",
+            r#"
+            fn main() {
+                println!("client: {:?}", std::env::args().skip(1).collect::<Vec<_>>());
+            }
+            "#,
+        )
+        .file(
+            "examples/ex.rs",
+            r#"fn main() { print!("ex without newline"); }"#,
+        )
+        .build();
+
+    p.cargo("run -Zrun-multiple --bin server --bin client --example ex -- a b")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains(r#"server | server: ["a", "b"]"#)
+        .with_stdout_contains(r#"client | client: ["a", "b"]"#)
+        .with_stdout_contains("ex     | ex without newline")
+        .with_stderr_contains("server | server listening")
+        .with_stderr_contains("[RUNNING] `target/debug/server a b`")
+        .with_stderr_contains("[RUNNING] `target/debug/client a b`")
+        .with_stderr_contains("[RUNNING] `target/debug/examples/ex a b`")
+        .run();
+
+    // A single executable still runs as usual.
+    p.cargo("run -Zrun-multiple --bin client")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("client: []")
+        .run();
+}
+
+#[cargo_test]
+fn run_multiple_failure_terminates_others() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "src/bin/server.rs",
+            r#"
+            fn main() {
+                std::thread::sleep(std::time::Duration::from_secs(600));
+            }
+            "#,
+        )
+        .file(
+            "src/bin/client.rs",
+            r#"
+            fn main() {
+                eprintln!("client failed");
+                std::process::exit(2);
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("run -Zrun-multiple --bin server --bin client")
+        .masquerade_as_nightly_cargo()
+        .with_status(2)
+        .with_stderr_contains("client | client failed")
+        .with_stderr_contains(
+            "[ERROR] process didn't exit successfully: `target/debug/client` (exit [..]: 2)",
+        )
+        .run();
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn run_multiple_failure_kills_others() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "src/bin/server.rs",
+            r#"
+            extern "C" {
+                fn signal(signal: i32, handler: usize) -> usize;
+            }
+
+            fn main() {
+                // Ignores `SIGTERM`.
+                unsafe {
+                    signal(15, 1);
+                }
+                std::thread::sleep(std::time::Duration::from_secs(600));
+            }
+            "#,
+        )
+        .file(
+            "src/bin/client.rs",
+            r#"
+            fn main() {
+                std::thread::sleep(std::time::Duration::from_millis(500));
+                std::process::exit(2);
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("run -Zrun-multiple --bin server --bin client")
+        .masquerade_as_nightly_cargo()
+        .with_status(2)
+        .with_stderr_contains(
+            "[ERROR] process didn't exit successfully: `target/debug/client` (exit [..]: 2)",
+        )
+        .run();
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn run_multiple_forwards_signals() {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let bin = format!(
+        r#"
+        use std::io::Read;
+
+        fn main() {{
+            let mut socket = std::net::TcpStream::connect("{}").unwrap();
+            let _ = socket.read(&mut [0; 10]);
+        }}
+        "#,
+        listener.local_addr().unwrap()
+    );
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("src/bin/server.rs", &bin)
+        .file("src/bin/client.rs", &bin)
+        .build();
+    p.cargo("build").run();
+
+    let mut cargo = p
+        .cargo("run -Zrun-multiple --bin server --bin client")
+        .masquerade_as_nightly_cargo()
+        .build_command()
+        .spawn()
+        .unwrap();
+    let mut sockets = vec![listener.accept().unwrap().0, listener.accept().unwrap().0];
+
+    // The executables are in their own process groups, and only get the
+    // signal through Cargo.
+    unsafe {
+        libc::kill(cargo.id() as libc::pid_t, libc::SIGINT);
+    }
+    for socket in sockets.iter_mut() {
+        assert_eq!(socket.read(&mut [0; 10]).unwrap(), 0);
+    }
+    let status = cargo.wait().unwrap();
+    assert_eq!(status.code(), Some(101));
+}
+
+#[cargo_test]
+fn run_multiple_requires_flag() {
+    let p = project()
+        .file("src/bin/a.rs", "fn main() {}")
+        .file("src/bin/b.rs", "fn main() {}")
+        .build();
+
+    p.cargo("run --bin a --bin b")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `cargo run` can run at most one executable, but multiple were specified",
+        )
+        .run();
+}
@@ -0,0 +1,217 @@
+//! Tests for running build scripts in a sandbox with
+//! `-Z sandbox-build-scripts`.
+
+#[cfg(target_os = "linux")]
+use cargo_test_support::paths;
+use cargo_test_support::project;
+
+#[cfg(target_os = "linux")]
+#[cargo_test]
+fn sandbox_filesystem() {
+    let secret = paths::home().join("secret");
+    std::fs::write(&secret, "hunter2").unwrap();
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            use std::env;
+            use std::fs;
+            use std::path::Path;
+
+            fn main() {
+                let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+                let out_dir = env::var("OUT_DIR").unwrap();
+                let secret = env::var("SECRET").unwrap();
+
+                // The source of the package can be read, but not written.
+                fs::read(Path::new(&manifest_dir).join("src/lib.rs")).unwrap();
+                assert!(fs::write(Path::new(&manifest_dir).join("new"), "").is_err());
+                // `OUT_DIR` and `/tmp` can be written.
+                fs::write(Path::new(&out_dir).join("out"), "").unwrap();
+                fs::write("/tmp/tmp", "").unwrap();
+                // Nothing else can be read or written.
+                assert!(fs::read(&secret).is_err());
+                assert!(fs::write(Path::new(&secret).with_file_name("new"), "").is_err());
+                assert!(fs::write("/usr/new", "").is_err());
+                // Programs of the system and the toolchain can run.
+                let rustc = env::var("RUSTC").unwrap();
+                let status = std::process::Command::new(rustc)
+                    .arg("--version")
+                    .status()
+                    .unwrap();
+                assert!(status.success());
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zsandbox-build-scripts")
+        .masquerade_as_nightly_cargo()
+        .env("SECRET", &secret)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+    assert!(!p.root().join("new").exists());
+    assert!(!paths::home().join("new").exists());
+    assert!(p
+        .build_dir()
+        .join("debug/build")
+        .read_dir()
+        .unwrap()
+        .any(|dir| dir.unwrap().path().join("out/out").exists()));
+}
+
+#[cfg(target_os = "linux")]
+#[cargo_test]
+fn sandbox_network() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo:rerun-if-env-changed=ALLOWED");
+                let allowed = std::env::var("ALLOWED").is_ok();
+                let addr = std::env::var("ADDR").unwrap();
+                assert_eq!(std::net::TcpStream::connect(addr).is_ok(), allowed);
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zsandbox-build-scripts")
+        .masquerade_as_nightly_cargo()
+        .env("ADDR", &addr)
+        .run();
+
+    p.change_file(
+        ".cargo/config",
+        r#"
+        [sandbox.allow.foo]
+        network = true
+        "#,
+    );
+    p.cargo("build -Zsandbox-build-scripts")
+        .masquerade_as_nightly_cargo()
+        .env("ADDR", &addr)
+        .env("ALLOWED", "1")
+        .with_stderr_contains("[COMPILING] foo [..]")
+        .run();
+
+    // Without the flag build scripts aren't sandboxed.
+    p.cargo("build")
+        .env("ADDR", &addr)
+        .env("ALLOWED", "2")
+        .with_stderr_contains("[COMPILING] foo [..]")
+        .run();
+}
+
+#[cfg(target_os = "linux")]
+#[cargo_test]
+fn sandbox_allow_paths() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                let home = std::path::PathBuf::from(std::env::var("HOME").unwrap());
+                assert_eq!(std::fs::read_to_string(home.join("data/input")).unwrap(), "1");
+                std::fs::write(home.join("cache/output"), "2").unwrap();
+            }
+            "#,
+        )
+        .build();
+    let home = paths::home();
+    std::fs::create_dir_all(home.join("data")).unwrap();
+    std::fs::create_dir_all(home.join("cache")).unwrap();
+    std::fs::write(home.join("data/input"), "1").unwrap();
+
+    p.cargo("build -Zsandbox-build-scripts")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]failed to run custom build command for `foo v0.0.1 ([CWD])`")
+        .run();
+
+    // Relative paths are relative to the directory containing `.cargo`.
+    p.change_file(
+        ".cargo/config",
+        &format!(
+            r#"
+            [sandbox.allow.foo]
+            read = ["{}"]
+            write = ["../home/cache"]
+            "#,
+            home.join("data").display()
+        ),
+    );
+    p.cargo("build -Zsandbox-build-scripts")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert_eq!(
+        std::fs::read_to_string(home.join("cache/output")).unwrap(),
+        "2"
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[cargo_test]
+fn sandbox_links_dependency() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [dependencies]
+            bar = { path = "bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                let include = std::env::var("DEP_BAR_INCLUDE").unwrap();
+                let header = std::path::Path::new(&include).join("bar.h");
+                assert_eq!(std::fs::read_to_string(header).unwrap(), "int bar();");
+                assert!(std::fs::write(std::path::Path::new(&include).join("new"), "").is_err());
+            }
+            "#,
+        )
+        .file(
+            "bar/Cargo.toml",
+            r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            links = "bar"
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file(
+            "bar/build.rs",
+            r#"
+            fn main() {
+                let include = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("include");
+                std::fs::create_dir_all(&include).unwrap();
+                std::fs::write(include.join("bar.h"), "int bar();").unwrap();
+                println!("cargo:include={}", include.display());
+            }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zsandbox-build-scripts")
+        .masquerade_as_nightly_cargo()
+        .run();
+}
@@ -0,0 +1,206 @@
+//! Tests for `-Z signed-tokens`.
+
+use cargo_test_support::publish::validate_alt_upload;
+use cargo_test_support::registry::{self, Package};
+use cargo_test_support::signed_registry::{self, SignedRegistry};
+use cargo_test_support::{cargo_process, paths, project, t};
+use std::fs;
+
+#[cargo_test]
+fn download_publish_and_yank() {
+    let server = SignedRegistry::start();
+    Package::new("bar", "0.0.1").alternative(true).publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+
+            [dependencies.bar]
+            version = "0.0.1"
+            registry = "alternative"
+        "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -Zsigned-tokens")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.0.1 (registry `[ROOT][..]`)
+[COMPILING] bar v0.0.1 (registry `[ROOT][..]`)
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]s
+",
+        )
+        .run();
+
+    p.cargo("publish --no-verify --registry alternative -Zsigned-tokens")
+        .masquerade_as_nightly_cargo()
+        .run();
+    validate_alt_upload(
+        r#"
+        {
+          "authors": [],
+          "badges": {},
+          "categories": [],
+          "deps": [
+            {
+              "default_features": true,
+              "features": [],
+              "kind": "normal",
+              "name": "bar",
+              "optional": false,
+              "target": null,
+              "version_req": "^0.0.1"
+            }
+          ],
+          "description": "foo",
+          "documentation": null,
+          "features": {},
+          "homepage": null,
+          "keywords": [],
+          "license": "MIT",
+          "license_file": null,
+          "links": null,
+          "name": "foo",
+          "proc_macro": false,
+          "readme": null,
+          "readme_file": null,
+          "repository": null,
+          "vers": "0.0.1"
+        }
+        "#,
+        "foo-0.0.1.crate",
+        &["Cargo.lock", "Cargo.toml", "Cargo.toml.orig", "src/main.rs"],
+    );
+
+    p.cargo("yank --vers 0.0.1 --registry alternative -Zsigned-tokens")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("owner --list --registry alternative -Zsigned-tokens")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    assert_eq!(
+        server.requests(),
+        [
+            "download bar 0.0.1",
+            "publish foo 0.0.1",
+            "yank foo 0.0.1",
+            "owners foo",
+        ]
+    );
+}
+
+#[cargo_test]
+fn bearer_token_is_rejected() {
+    let server = SignedRegistry::start();
+
+    cargo_process("yank foo --vers 0.0.1 --registry alternative")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[..]Yank foo:0.0.1
+[ERROR] failed to yank
+
+Caused by:
+  api errors (status 403 Forbidden): expected a signed token
+",
+        )
+        .run();
+    assert_eq!(server.requests(), ["rejected: expected a signed token"]);
+}
+
+#[cargo_test]
+fn unknown_key_is_rejected() {
+    let server = SignedRegistry::start();
+
+    cargo_process("yank foo --vers 0.0.1 --registry alternative -Zsigned-tokens")
+        .masquerade_as_nightly_cargo()
+        .env(
+            "CARGO_REGISTRIES_ALTERNATIVE_SECRET_KEY",
+            signed_registry::unknown_secret_key(),
+        )
+        .with_status(101)
+        .with_stderr_contains("  api errors (status 403 Forbidden): invalid signature")
+        .run();
+    assert_eq!(server.requests(), ["rejected: invalid signature"]);
+}
+
+#[cargo_test]
+fn registry_without_support() {
+    registry::init();
+
+    cargo_process("yank foo --vers 0.0.1 --registry alternative -Zsigned-tokens")
+        .masquerade_as_nightly_cargo()
+        .env(
+            "CARGO_REGISTRIES_ALTERNATIVE_SECRET_KEY",
+            signed_registry::secret_key(),
+        )
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] a `secret-key` is configured for `alternative`, but the registry does not \
+support signed tokens
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_key() {
+    SignedRegistry::start();
+
+    cargo_process("yank foo --vers 0.0.1 --registry alternative -Zsigned-tokens")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_REGISTRIES_ALTERNATIVE_SECRET_KEY", "not-a-key")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] failed to parse `registries.alternative.secret-key` from \
+environment variable `CARGO_REGISTRIES_ALTERNATIVE_SECRET_KEY`
+
+Caused by:
+  expected a key starting with `k4.secret.`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn generate_key() {
+    registry::init();
+
+    cargo_process("login --generate-key --registry alternative")
+        .with_status(101)
+        .with_stderr("[ERROR] `cargo login --generate-key` requires `-Z signed-tokens`")
+        .run();
+
+    cargo_process("login --generate-key --registry alternative -Zsigned-tokens")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("k4.public.[..]")
+        .with_stderr("[..]Login secret key for `alternative` saved")
+        .run();
+
+    let credentials = t!(fs::read_to_string(paths::home().join(".cargo/credentials")));
+    let credentials: toml::Value = t!(credentials.parse());
+    let alternative = &credentials["registries"]["alternative"];
+    // The existing token is kept alongside the new key.
+    assert_eq!(alternative["token"].as_str(), Some("api-token"));
+    assert!(alternative["secret-key"]
+        .as_str()
+        .unwrap()
+        .starts_with("k4.secret."));
+}
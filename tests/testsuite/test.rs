@@ -3717,10 +3717,6 @@ fn cargo_test_doctest_xcompile() {
     if !cross_compile::can_run_on_host() {
         return;
     }
-    if !is_nightly() {
-        // -Zdoctest-xcompile is unstable
-        return;
-    }
     let p = project()
         .file("Cargo.toml", &basic_lib_manifest("foo"))
         .file(
@@ -3739,17 +3735,10 @@ fn cargo_test_doctest_xcompile() {
 
     p.cargo("build").run();
     p.cargo(&format!("test --target {}", cross_compile::alternate()))
-        .with_stdout_contains("running 0 tests")
+        .with_stdout_contains(
+            "test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out",
+        )
         .run();
-    p.cargo(&format!(
-        "test --target {} -Zdoctest-xcompile",
-        cross_compile::alternate()
-    ))
-    .masquerade_as_nightly_cargo()
-    .with_stdout_contains(
-        "test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out",
-    )
-    .run();
 }
 
 #[cargo_test]
@@ -3757,10 +3746,6 @@ fn cargo_test_doctest_xcompile_runner() {
     if !cross_compile::can_run_on_host() {
         return;
     }
-    if !is_nightly() {
-        // -Zdoctest-xcompile is unstable
-        return;
-    }
 
     let runner = project()
         .file("Cargo.toml", &basic_bin_manifest("runner"))
@@ -3819,18 +3804,11 @@ runner = "{}"
 
     p.cargo("build").run();
     p.cargo(&format!("test --target {}", cross_compile::alternate()))
-        .with_stdout_contains("running 0 tests")
+        .with_stdout_contains(
+            "test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out",
+        )
+        .with_stderr_contains("this is a runner")
         .run();
-    p.cargo(&format!(
-        "test --target {} -Zdoctest-xcompile",
-        cross_compile::alternate()
-    ))
-    .masquerade_as_nightly_cargo()
-    .with_stdout_contains(
-        "test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out",
-    )
-    .with_stderr_contains("this is a runner")
-    .run();
 }
 
 #[cargo_test]
@@ -3838,10 +3816,6 @@ fn cargo_test_doctest_xcompile_no_runner() {
     if !cross_compile::can_run_on_host() {
         return;
     }
-    if !is_nightly() {
-        // -Zdoctest-xcompile is unstable
-        return;
-    }
 
     let p = project()
         .file("Cargo.toml", &basic_lib_manifest("foo"))
@@ -3863,17 +3837,10 @@ fn cargo_test_doctest_xcompile_no_runner() {
 
     p.cargo("build").run();
     p.cargo(&format!("test --target {}", cross_compile::alternate()))
-        .with_stdout_contains("running 0 tests")
+        .with_stdout_contains(
+            "test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out",
+        )
         .run();
-    p.cargo(&format!(
-        "test --target {} -Zdoctest-xcompile",
-        cross_compile::alternate()
-    ))
-    .masquerade_as_nightly_cargo()
-    .with_stdout_contains(
-        "test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out",
-    )
-    .run();
 }
 
 #[cargo_test]
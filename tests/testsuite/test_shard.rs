@@ -0,0 +1,172 @@
+//! Tests for `cargo test --shard`.
+
+use cargo_test_support::{project, Project};
+
+fn sharded_project() -> Project {
+    project()
+        .file(
+            "src/lib.rs",
+            r#"
+            /// ```
+            /// foo::f();
+            /// ```
+            pub fn f() {}
+
+            #[test]
+            fn lib_test() {}
+            "#,
+        )
+        .file("tests/a.rs", "#[test] fn a_test() {}")
+        .file("tests/b.rs", "#[test] fn b_test() {}")
+        .file("tests/c.rs", "#[test] fn c_test() {}")
+        .build()
+}
+
+#[cargo_test]
+fn shard_requires_unstable_options() {
+    let p = sharded_project();
+
+    p.cargo("test --shard 1/2")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `--shard` flag is unstable, pass `-Z unstable-options` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_shard() {
+    let p = sharded_project();
+
+    for shard in &["0/2", "3/2", "2", "a/b"] {
+        p.cargo(&format!("test -Zunstable-options --shard {}", shard))
+            .masquerade_as_nightly_cargo()
+            .with_status(101)
+            .with_stderr(format!(
+                "[ERROR] invalid shard `{}`, expected `N/M` where `N` is between 1 and `M`",
+                shard
+            ))
+            .run();
+    }
+}
+
+#[cargo_test]
+fn shards_without_durations() {
+    let p = sharded_project();
+
+    // Without any recorded durations the tests are assigned in turn to each
+    // shard, ordered by name.
+    p.cargo("test -Zunstable-options --shard 1/2")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] target/debug/deps/foo-[..][EXE]
+[RUNNING] target/debug/deps/b-[..][EXE]
+[DOCTEST] foo
+",
+        )
+        .with_stdout_contains("test lib_test ... ok")
+        .with_stdout_contains("test b_test ... ok")
+        .with_stdout_contains("test [..]src/lib.rs - f (line 2) ... ok")
+        .run();
+
+    p.cargo("test -Zunstable-options --shard 2/2")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] target/debug/deps/a-[..][EXE]
+[RUNNING] target/debug/deps/c-[..][EXE]
+",
+        )
+        .with_stdout_contains("test a_test ... ok")
+        .with_stdout_contains("test c_test ... ok")
+        .run();
+}
+
+#[cargo_test]
+fn shards_balanced_by_durations() {
+    let p = sharded_project();
+
+    p.cargo("test -Zunstable-options --shard 1/1")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(!p.root().join("target/.test-durations.json").exists());
+    let durations = p.read_file("target/.test-durations.shard-1.json");
+    for key in &[
+        "foo lib foo",
+        "foo integration-test a",
+        "foo integration-test b",
+        "foo integration-test c",
+        "foo doc foo",
+    ] {
+        assert!(durations.contains(key), "{} not in {}", key, durations);
+    }
+
+    // The slowest test executable gets a shard of its own.
+    p.change_file(
+        "target/.test-durations.json",
+        r#"{"durations": {
+            "foo lib foo": 0.1,
+            "foo integration-test a": 0.1,
+            "foo integration-test b": 10.0,
+            "foo integration-test c": 0.1,
+            "foo doc foo": 0.1
+        }}"#,
+    );
+    p.cargo("test -Zunstable-options --shard 1/2")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] target/debug/deps/b-[..][EXE]
+",
+        )
+        .run();
+
+    // Only the durations of the tests of the shard are recorded, and the
+    // ones the shards are balanced with are kept.
+    let durations = p.read_file("target/.test-durations.shard-1.json");
+    assert!(durations.contains("foo integration-test b"));
+    assert!(!durations.contains("foo integration-test a"));
+    let durations = p.read_file("target/.test-durations.json");
+    assert!(durations.contains("\"foo integration-test b\": 10.0"));
+}
+
+#[cargo_test]
+fn shards_in_same_target_dir() {
+    let p = sharded_project();
+    p.change_file(
+        "target/.test-durations.json",
+        r#"{"durations": {
+            "foo lib foo": 0.1,
+            "foo integration-test a": 0.2,
+            "foo integration-test b": 0.3,
+            "foo integration-test c": 0.4,
+            "foo doc foo": 0.5
+        }}"#,
+    );
+
+    // The shards run one after another are partitioned with the same
+    // durations, so every test runs in exactly one of them.
+    let mut stdout = String::new();
+    for shard in &["1/2", "2/2"] {
+        let output = p
+            .cargo(&format!("test -Zunstable-options --shard {}", shard))
+            .masquerade_as_nightly_cargo()
+            .exec_with_output()
+            .unwrap();
+        stdout.push_str(&String::from_utf8(output.stdout).unwrap());
+    }
+    for test in &[
+        "test lib_test ... ok",
+        "test a_test ... ok",
+        "test b_test ... ok",
+        "test c_test ... ok",
+        "src/lib.rs - f (line 2) ... ok",
+    ] {
+        assert_eq!(stdout.matches(test).count(), 1, "{} in {}", test, stdout);
+    }
+}
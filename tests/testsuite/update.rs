@@ -391,7 +391,7 @@ fn update_precise() {
         .with_stderr(
             "\
 [UPDATING] `[..]` index
-[UPDATING] serde v0.2.1 -> v0.2.0
+[DOWNGRADING] serde v0.2.1 -> v0.2.0
 ",
         )
         .run();
@@ -425,7 +425,7 @@ fn update_precise_first_run() {
         .with_stderr(
             "\
 [UPDATING] `[..]` index
-[UPDATING] serde v0.2.1 -> v0.2.0
+[DOWNGRADING] serde v0.2.1 -> v0.2.0
 ",
         )
         .run();
@@ -647,3 +647,368 @@ fn dry_run_update() {
     let new_lockfile = p.read_file("Cargo.lock");
     assert_eq!(old_lockfile, new_lockfile)
 }
+
+#[cargo_test]
+fn breaking_requires_unstable_options() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("update --breaking")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `--breaking` flag is unstable, pass `-Z unstable-options` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn breaking_upgrades_requirements() {
+    Package::new("compatible", "1.0.0").publish();
+    Package::new("incompatible", "1.0.0").publish();
+    Package::new("pinned", "1.0.0").publish();
+    Package::new("renamed", "0.1.0").publish();
+    Package::new("dev", "1.0.0").publish();
+    Package::new("platform", "1.0.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                compatible = "1.0"  # stays
+                incompatible = "1.0"
+                pinned = "=1.0.0"
+                new-name = { package = "renamed", version = "^0.1" }
+
+                [dev-dependencies.dev]
+                version = "1"
+
+                [target.'cfg(unix)'.dependencies]
+                platform = { version = "1.0.0", default-features = false }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+
+    Package::new("compatible", "1.1.0").publish();
+    Package::new("incompatible", "2.1.3").publish();
+    Package::new("incompatible", "3.0.0-alpha.1").publish();
+    Package::new("pinned", "2.0.0").publish();
+    Package::new("renamed", "0.2.1").publish();
+    Package::new("dev", "2.0.0").publish();
+    Package::new("platform", "2.0.1").publish();
+
+    p.cargo("update --breaking -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[UPDATING] `[..]` index
+[UPGRADING] incompatible 1.0 -> 2.1
+[UPGRADING] renamed ^0.1 -> ^0.2
+[UPGRADING] dev 1 -> 2
+[UPGRADING] platform 1.0.0 -> 2.0.1
+[UPDATING] dev v1.0.0 -> v2.0.0
+[UPDATING] incompatible v1.0.0 -> v2.1.3
+[UPDATING] platform v1.0.0 -> v2.0.1
+[UPDATING] renamed v0.1.0 -> v0.2.1
+",
+        )
+        .run();
+
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                compatible = "1.0"  # stays
+                incompatible = "2.1"
+                pinned = "=1.0.0"
+                new-name = { package = "renamed", version = "^0.2" }
+
+                [dev-dependencies.dev]
+                version = "2"
+
+                [target.'cfg(unix)'.dependencies]
+                platform = { version = "2.0.1", default-features = false }
+            "#
+    );
+    // Packages whose requirements were not upgraded are not updated.
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"compatible\"\nversion = \"1.0.0\""));
+}
+
+#[cargo_test]
+fn breaking_dotted_keys_and_target_tables() {
+    Package::new("dotted", "1.0.0").publish();
+    Package::new("quoted", "1.0.0").publish();
+    Package::new("renamed", "1.0.0").publish();
+    Package::new("windows", "1.0.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+                description = "dotted = \"1.0\""
+
+                [dependencies]
+                dotted.version = "1.0"
+                dotted.default-features = false
+
+                [dependencies."quoted"]
+                # version = "1.0"
+                version = '1.0'
+
+                [target."cfg(windows)".dev-dependencies]
+                new-name.package = "renamed"
+                new-name.version = "1"
+                windows = {version="1.0",optional=false}
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+
+    Package::new("dotted", "2.0.0").publish();
+    Package::new("quoted", "2.0.0").publish();
+    Package::new("renamed", "2.0.0").publish();
+    Package::new("windows", "2.0.0").publish();
+
+    p.cargo("update --breaking -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[UPDATING] `[..]` index
+[UPGRADING] dotted 1.0 -> 2.0
+[UPGRADING] quoted 1.0 -> 2.0
+[UPGRADING] renamed 1 -> 2
+[UPGRADING] windows 1.0 -> 2.0
+[UPDATING] dotted v1.0.0 -> v2.0.0
+[UPDATING] quoted v1.0.0 -> v2.0.0
+[UPDATING] renamed v1.0.0 -> v2.0.0
+[UPDATING] windows v1.0.0 -> v2.0.0
+",
+        )
+        .run();
+
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+                description = "dotted = \"1.0\""
+
+                [dependencies]
+                dotted.version = "2.0"
+                dotted.default-features = false
+
+                [dependencies."quoted"]
+                # version = "1.0"
+                version = '2.0'
+
+                [target."cfg(windows)".dev-dependencies]
+                new-name.package = "renamed"
+                new-name.version = "2"
+                windows = {version="2.0",optional=false}
+            "#
+    );
+}
+
+#[cargo_test]
+fn breaking_keeps_workspace_options() {
+    Package::new("bar", "1.0.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("foo/src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+    Package::new("bar", "2.0.0").publish();
+
+    // The workspace loaded again from the upgraded manifests still refuses to
+    // write the lock file with `--locked`.
+    p.cargo("update --breaking --locked -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .cwd("foo")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPGRADING] bar 1.0 -> 2.0
+[UPDATING] bar v1.0.0 -> v2.0.0
+[ERROR] the lock file [..]Cargo.lock needs to be updated but --locked was passed to prevent this
+[..]
+",
+        )
+        .run();
+    assert!(p.read_file("foo/Cargo.toml").contains("bar = \"1.0\""));
+}
+
+#[cargo_test]
+fn breaking_resolve_failure_keeps_manifests() {
+    Package::new("bar", "1.0.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+    let lock = p.read_lockfile();
+    Package::new("bar", "2.0.0").dep("missing", "1.0").publish();
+
+    // The upgraded manifest is only written once it has been resolved.
+    p.cargo("update --breaking -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[UPGRADING] bar 1.0 -> 2.0")
+        .with_stderr_contains("[ERROR] no matching package named `missing` found")
+        .run();
+    assert!(p.read_file("Cargo.toml").contains("bar = \"1.0\""));
+    assert_eq!(p.read_lockfile(), lock);
+}
+
+#[cargo_test]
+fn breaking_specific_packages() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "1.0.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo"]
+
+                [package]
+                name = "root"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = "1.0"
+                baz = "~1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = "1"
+                baz = "1.0"
+            "#,
+        )
+        .file("foo/src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+
+    Package::new("bar", "2.0.0").publish();
+    Package::new("baz", "2.0.0").publish();
+
+    p.cargo("update --breaking -p bar -p baz --dry-run -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPGRADING] bar 1 -> 2
+[UPGRADING] baz 1.0 -> 2.0
+[UPGRADING] bar 1.0 -> 2.0
+[WARNING] not upgrading `baz` with requirement `~1.0` in `[ROOT]/foo/Cargo.toml`, \
+only caret requirements are upgraded
+[UPDATING] bar v1.0.0 -> v2.0.0
+[ADDING] baz v2.0.0
+[WARNING] not updating manifests or lockfile due to dry run
+",
+        )
+        .run();
+    assert!(p.read_file("foo/Cargo.toml").contains("bar = \"1\""));
+    assert!(p.read_file("Cargo.toml").contains("bar = \"1.0\""));
+    assert!(!p.read_lockfile().contains("2.0.0"));
+
+    p.cargo("update --breaking -p baz -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPGRADING] baz 1.0 -> 2.0
+[WARNING] not upgrading `baz` [..]
+[ADDING] baz v2.0.0
+",
+        )
+        .run();
+    assert!(p.read_file("foo/Cargo.toml").contains("baz = \"2.0\""));
+    assert!(p.read_file("Cargo.toml").contains("baz = \"~1.0\""));
+
+    // Nothing is upgraded when one of the packages is not a dependency.
+    p.cargo("update --breaking -p bar -p qux -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] package `qux` is not a registry dependency of any workspace member")
+        .run();
+    assert!(p.read_file("foo/Cargo.toml").contains("bar = \"1\""));
+
+    p.cargo("update --breaking -p bar:1.0.0 -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the package specification `bar:1.0.0` with --breaking must only be a name",
+        )
+        .run();
+}
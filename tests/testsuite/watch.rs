@@ -0,0 +1,223 @@
+//! Tests for the `--watch` flag.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Stdio};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cargo_test_support::{basic_bin_manifest, basic_manifest, lines_match, project, Execs};
+
+/// A running `cargo --watch`, whose stderr is read line by line.
+struct Watcher {
+    child: Child,
+    lines: Receiver<String>,
+    output: Vec<String>,
+}
+
+impl Watcher {
+    fn start(execs: &mut Execs) -> Watcher {
+        let mut child = execs
+            .masquerade_as_nightly_cargo()
+            .build_command()
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stderr = child.stderr.take().unwrap();
+        let (tx, lines) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines() {
+                if tx.send(line.unwrap()).is_err() {
+                    break;
+                }
+            }
+        });
+        Watcher {
+            child,
+            lines,
+            output: Vec::new(),
+        }
+    }
+
+    /// Waits for a line matching `expected`, and returns the lines before it.
+    fn expect(&mut self, expected: &str) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let mut skipped = Vec::new();
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            let line = match self.lines.recv_timeout(timeout) {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            self.output.push(line.clone());
+            if lines_match(expected, &line) {
+                return skipped;
+            }
+            skipped.push(line);
+        }
+        panic!(
+            "expected to find `{}` in the output:\n{}",
+            expected,
+            self.output.join("\n")
+        );
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cargo_test]
+fn watch_requires_unstable_options() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build --watch")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `--watch` flag is unstable, pass `-Z unstable-options` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn watch_rebuilds_when_dep_info_changes() {
+    let p = project()
+        .file("src/lib.rs", "mod a;")
+        .file("src/a.rs", "")
+        .file("src/unused.rs", "")
+        .build();
+
+    let mut watcher = Watcher::start(&mut p.cargo("build -Zunstable-options --watch"));
+    watcher.expect("[COMPILING] foo v0.0.1 ([..])");
+    // `Cargo.toml`, `Cargo.lock`, `src/lib.rs` and `src/a.rs`.
+    watcher.expect("[..]Watching 4 files for changes");
+
+    // Files which aren't read by the build are not watched.
+    p.change_file("src/unused.rs", "// not a module");
+    p.change_file("src/a.rs", "pub fn a() {}");
+    watcher.expect("[..]Changed src/a.rs");
+    watcher.expect("[COMPILING] foo v0.0.1 ([..])");
+    watcher.expect("[FINISHED] [..]");
+    watcher.expect("[..]Watching 4 files for changes");
+
+    p.change_file("src/lib.rs", "mod a; mod unused;");
+    watcher.expect("[..]Changed src/lib.rs");
+    watcher.expect("[..]Watching 5 files for changes");
+    p.change_file("src/unused.rs", "// now a module");
+    watcher.expect("[..]Changed src/unused.rs");
+}
+
+#[cargo_test]
+fn watch_build_script_rerun_if_changed() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+            fn main() {
+                println!("cargo:rerun-if-changed=build.rs");
+                println!("cargo:rerun-if-changed=data.txt");
+                println!("cargo:warning=data: {}", std::fs::read_to_string("data.txt").unwrap());
+            }
+            "#,
+        )
+        .file("data.txt", "one")
+        .file("unrelated.txt", "")
+        .build();
+
+    let mut watcher = Watcher::start(&mut p.cargo("check -Zunstable-options --watch"));
+    watcher.expect("warning: data: one");
+    watcher.expect("[..]Watching [..] files for changes");
+
+    p.change_file("unrelated.txt", "ignored");
+    p.change_file("data.txt", "two");
+    watcher.expect("[..]Changed data.txt");
+    watcher.expect("warning: data: two");
+}
+
+#[cargo_test]
+fn watch_keeps_watching_after_errors() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "pub fn f() -> u32 { \"not a number\" }")
+        .build();
+
+    let mut watcher = Watcher::start(&mut p.cargo("build -Zunstable-options --watch"));
+    watcher.expect("[ERROR] could not compile `foo`.");
+    watcher.expect("[..]Watching [..] files for changes");
+
+    p.change_file("src/lib.rs", "pub fn f() -> u32 { 1 }");
+    watcher.expect("[..]Changed src/lib.rs");
+    watcher.expect("[FINISHED] [..]");
+
+    // A broken manifest is reported, and watched until it is fixed.
+    p.change_file(
+        "Cargo.toml",
+        "[package]\nname = \"foo\"\nversion = \"0.0.1\"\n[invalid",
+    );
+    watcher.expect("[..]Changed Cargo.toml");
+    watcher.expect("[ERROR] failed to parse manifest at [..]");
+    watcher.expect("[..]Watching [..] files for changes");
+    p.change_file(
+        "Cargo.toml",
+        "[package]\nname = \"foo\"\nversion = \"0.0.1\"\n[dependencies]\nbar = { path = \"bar\" }",
+    );
+    p.change_file("bar/Cargo.toml", &basic_manifest("bar", "0.0.1"));
+    p.change_file("bar/src/lib.rs", "");
+    watcher.expect("[..]Changed [..]");
+    watcher.expect("[COMPILING] bar v0.0.1 ([..])");
+    watcher.expect("[FINISHED] [..]");
+
+    // Path dependencies are watched too.
+    p.change_file("bar/src/lib.rs", "pub fn bar() {}");
+    watcher.expect("[..]Changed bar/src/lib.rs");
+    watcher.expect("[COMPILING] bar v0.0.1 ([..])");
+}
+
+#[cargo_test]
+fn watch_test() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "#[test] fn t() { assert_eq!(1, 2, \"broken test\"); }",
+        )
+        .build();
+
+    let mut watcher = Watcher::start(&mut p.cargo("test -Zunstable-options --watch --lib"));
+    watcher.expect("[ERROR] test failed, to rerun pass '--lib'");
+    watcher.expect("[..]Watching [..] files for changes");
+
+    p.change_file("src/lib.rs", "#[test] fn t() { assert_eq!(1, 1); }");
+    watcher.expect("[..]Changed src/lib.rs");
+    let lines = watcher.expect("[..]Watching [..] files for changes");
+    assert!(
+        lines.iter().all(|line| !line.contains("error")),
+        "{:?}",
+        lines
+    );
+}
+
+#[cargo_test]
+fn watch_run() {
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", r#"fn main() { eprintln!("version one"); }"#)
+        .build();
+
+    let mut watcher = Watcher::start(&mut p.cargo("run -Zunstable-options --watch"));
+    watcher.expect("[RUNNING] `target/debug/foo[EXE]`");
+    watcher.expect("version one");
+
+    p.change_file(
+        "src/main.rs",
+        r#"fn main() { eprintln!("version two"); std::process::exit(3); }"#,
+    );
+    watcher.expect("[..]Changed src/main.rs");
+    watcher.expect("[RUNNING] `target/debug/foo[EXE]`");
+    watcher.expect("version two");
+    watcher
+        .expect("[ERROR] process didn't exit successfully: `target/debug/foo[EXE]` (exit [..]: 3)");
+}